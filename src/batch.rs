@@ -0,0 +1,101 @@
+//! Hashing many files at once on a pool of worker threads.
+
+use crate::{hash_file_with, Digest, Hasher};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+/// The outcome of hashing one file with [`hash_files`].
+#[derive(Debug)]
+pub struct FileResult {
+    pub path: PathBuf,
+    /// One digest per hasher, in the order the hashers were given.
+    pub digests: io::Result<Vec<Digest>>,
+}
+
+/// Number of worker threads used for a `jobs` setting: `0` means one per
+/// CPU.
+pub fn worker_count(jobs: usize) -> usize {
+    if jobs == 0 {
+        thread::available_parallelism().map_or(1, |n| n.get())
+    } else {
+        jobs
+    }
+}
+
+/// Hashes every file in `paths` with each of `hashers`, using up to `jobs`
+/// threads (see [`worker_count`]). Results come back in the same order as
+/// `paths`, whatever order the files finish in. A file that can't be read
+/// only fails its own entry.
+pub fn hash_files<P>(paths: &[P], hashers: &[Hasher], jobs: usize) -> Vec<FileResult>
+where
+    P: AsRef<Path> + Sync,
+{
+    let mut results = Vec::with_capacity(paths.len());
+    for_each_ordered(
+        paths,
+        jobs,
+        |path| FileResult {
+            path: path.as_ref().to_path_buf(),
+            digests: hash_file_with(path.as_ref(), hashers.to_vec(), |_| {}),
+        },
+        |result| results.push(result),
+    );
+    results
+}
+
+/// Runs `work` on every item using up to `jobs` threads, and hands the
+/// results to `emit` on the calling thread in the order of `items`. Each
+/// result is emitted as soon as it and everything before it are done, so
+/// output can be streamed while later items are still being worked on.
+///
+/// With a single job everything runs on the calling thread.
+pub fn for_each_ordered<T, R, W, E>(items: &[T], jobs: usize, work: W, mut emit: E)
+where
+    T: Sync,
+    R: Send,
+    W: Fn(&T) -> R + Sync,
+    E: FnMut(R),
+{
+    let workers = worker_count(jobs).min(items.len());
+    if workers <= 1 {
+        for item in items {
+            emit(work(item));
+        }
+        return;
+    }
+
+    let next = AtomicUsize::new(0);
+    let (tx, rx) = mpsc::channel();
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            let tx = tx.clone();
+            let (next, work) = (&next, &work);
+            scope.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(index) else {
+                    break;
+                };
+                if tx.send((index, work(item))).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(tx);
+
+        // Results arrive in completion order; hold back any that finish
+        // ahead of an earlier item.
+        let mut pending = BTreeMap::new();
+        let mut next_out = 0;
+        for (index, result) in rx {
+            pending.insert(index, result);
+            while let Some(result) = pending.remove(&next_out) {
+                emit(result);
+                next_out += 1;
+            }
+        }
+    });
+}
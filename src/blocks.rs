@@ -0,0 +1,137 @@
+//! Per-block digests, for resuming transfers and pinpointing corruption.
+
+use crate::hash::stream_chunks_into;
+use crate::read::DEFAULT_BUFFER_SIZE;
+use crate::{Digest, Hasher};
+use std::io::{self, Read};
+
+/// The digest of one block of a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub offset: u64,
+    /// Equal to the block size, except for a shorter last block.
+    pub len: u64,
+    pub digest: Digest,
+}
+
+/// Iterator over the digests of consecutive `block_size`-byte blocks of a
+/// reader. The digest of everything read is available from
+/// [`Blocks::full_digest`] afterwards, so the data is only read once. An
+/// empty reader has no blocks.
+pub struct Blocks<R> {
+    reader: R,
+    hasher: Hasher,
+    full: Hasher,
+    block_size: u64,
+    offset: u64,
+    buffer: Vec<u8>,
+    done: bool,
+}
+
+impl<R: Read> Blocks<R> {
+    /// Each block, and the full digest, is computed with a fresh copy of
+    /// `hasher`, so keys and output lengths carry over. Panics if
+    /// `block_size` is zero.
+    pub fn new(reader: R, hasher: Hasher, block_size: u64) -> Self {
+        assert!(block_size > 0, "block size must be greater than zero");
+        let buffer_len = block_size.min(DEFAULT_BUFFER_SIZE as u64) as usize;
+        Blocks {
+            reader,
+            full: hasher.clone(),
+            hasher,
+            block_size,
+            offset: 0,
+            buffer: vec![0; buffer_len],
+            done: false,
+        }
+    }
+
+    /// Digest of all the data the iterator has read, i.e. of the whole
+    /// input once it is exhausted.
+    pub fn full_digest(self) -> Digest {
+        self.full.finalize()
+    }
+
+    fn read_block(&mut self) -> io::Result<Option<Block>> {
+        let mut block = self.hasher.clone();
+        let full = &mut self.full;
+        let mut len = 0;
+        let mut reader = (&mut self.reader).take(self.block_size);
+        stream_chunks_into(&mut reader, &mut self.buffer, |chunk| {
+            block.update(chunk);
+            full.update(chunk);
+            len += chunk.len() as u64;
+        })?;
+
+        if len < self.block_size {
+            self.done = true;
+        }
+        if len == 0 {
+            return Ok(None);
+        }
+        let offset = self.offset;
+        self.offset += len;
+        Ok(Some(Block {
+            offset,
+            len,
+            digest: block.finalize(),
+        }))
+    }
+}
+
+impl<R: Read> Iterator for Blocks<R> {
+    type Item = io::Result<Block>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.read_block() {
+            Ok(block) => block.map(Ok),
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// A block whose digest differs between an expected block list and the
+/// data, or which only one side has.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockMismatch {
+    pub index: usize,
+    /// `None` if the data is longer than the expected block list.
+    pub expected: Option<Block>,
+    /// `None` if the data ends before this block.
+    pub computed: Option<Block>,
+}
+
+/// Recomputes the block digests of `reader` with `hasher` and lists every
+/// block that doesn't match `expected`, in order. An empty list means the
+/// data is intact.
+pub fn diff_blocks<R: Read>(
+    reader: R,
+    hasher: Hasher,
+    block_size: u64,
+    expected: &[Block],
+) -> io::Result<Vec<BlockMismatch>> {
+    let mut mismatches = Vec::new();
+    let mut computed = Blocks::new(reader, hasher, block_size);
+    let mut index = 0;
+    loop {
+        let block = computed.next().transpose()?;
+        let wanted = expected.get(index);
+        if block.is_none() && wanted.is_none() {
+            return Ok(mismatches);
+        }
+        if block.as_ref() != wanted {
+            mismatches.push(BlockMismatch {
+                index,
+                expected: wanted.cloned(),
+                computed: block,
+            });
+        }
+        index += 1;
+    }
+}
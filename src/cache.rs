@@ -0,0 +1,333 @@
+//! An on-disk cache of file digests, so that unchanged files don't have to
+//! be read again.
+//!
+//! Entries are keyed by device, inode, size and modification time (in
+//! nanoseconds) of the file together with the algorithm and digest length,
+//! so any write to the file, or replacing it, misses the cache. Each entry
+//! is a small file of its own, written under a temporary name and renamed
+//! into place, so any number of processes can use the cache at once without
+//! locking. Each entry also records the canonical path of its file, as hex
+//! of the raw path bytes, so that pruning can tell whether the file still
+//! matches from any working directory. Entries are only stored on Unix,
+//! where inode numbers are available; elsewhere the cache never hits.
+
+use crate::{hash_file_with_options, Digest, HashAlgorithm, Hasher, Progress, ReadOptions};
+use std::fs::{self, File, Metadata};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
+
+const ENTRY_HEADER: &str = "file-hasher cache v1";
+const VERSION_DIR: &str = "v1";
+/// Files modified this recently aren't cached: a write within the same
+/// timestamp tick would otherwise go unnoticed.
+pub(crate) const RACY_WINDOW: Duration = Duration::from_secs(2);
+/// Hits refresh the entry's modification time, which pruning goes by, at
+/// most this often.
+const TOUCH_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+/// Temporary files older than this were left behind by a crashed writer.
+const STALE_TEMP_AGE: Duration = Duration::from_secs(60 * 60);
+
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone)]
+pub struct HashCache {
+    dir: PathBuf,
+    refresh: bool,
+}
+
+/// What [`HashCache::prune`] did.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PruneStats {
+    pub kept: usize,
+    pub removed: usize,
+}
+
+/// The identity of a file's current contents, as far as `stat` can tell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileKey {
+    dev: u64,
+    ino: u64,
+    size: u64,
+    mtime_ns: u128,
+}
+
+impl HashCache {
+    /// `$XDG_CACHE_HOME/file-hasher`, or `~/.cache/file-hasher` if that isn't
+    /// set.
+    pub fn default_dir() -> Option<PathBuf> {
+        let from_env = |name| std::env::var_os(name).map(PathBuf::from);
+        from_env("XDG_CACHE_HOME")
+            .filter(|dir| dir.is_absolute())
+            .or_else(|| from_env("HOME").map(|home| home.join(".cache")))
+            .map(|dir| dir.join("file-hasher"))
+    }
+
+    /// Uses `dir` for the cache, creating it if needed.
+    pub fn open(dir: &Path) -> io::Result<Self> {
+        fs::create_dir_all(dir.join(VERSION_DIR))?;
+        Ok(HashCache {
+            dir: dir.to_path_buf(),
+            refresh: false,
+        })
+    }
+
+    /// With `refresh` set, files are always hashed and their entries
+    /// overwritten, e.g. after a filesystem was restored with mtimes intact.
+    pub fn with_refresh(mut self, refresh: bool) -> Self {
+        self.refresh = refresh;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Like [`crate::hash_file`], but takes the digest from the cache if the
+    /// file hasn't changed since it was stored.
+    pub fn hash_file(&self, path: &Path, hasher: &Hasher) -> io::Result<Digest> {
+        let mut digests = self.hash_file_with_options(
+            path,
+            vec![hasher.clone()],
+            &ReadOptions::default(),
+            |_| {},
+        )?;
+        Ok(digests.remove(0))
+    }
+
+    /// Like [`crate::hash_file_with_options`], taking whatever digests it
+    /// can from the cache and only reading the file for the rest. Ranges
+    /// and parameterised hashers (keyed BLAKE3, HMAC, ...) bypass the cache.
+    pub fn hash_file_with_options<F: FnMut(&Progress)>(
+        &self,
+        path: &Path,
+        hashers: Vec<Hasher>,
+        options: &ReadOptions,
+        on_progress: F,
+    ) -> io::Result<Vec<Digest>> {
+        let started = SystemTime::now();
+        let metadata = fs::metadata(path)?;
+        let key = match file_key(&metadata) {
+            Some(key) if options.range.is_none() => key,
+            _ => return hash_file_with_options(path, hashers, options, on_progress),
+        };
+
+        let mut digests: Vec<Option<Digest>> = hashers
+            .iter()
+            .map(|hasher| {
+                if self.refresh {
+                    return None;
+                }
+                hasher
+                    .cache_id()
+                    .and_then(|id| self.lookup(&id, &key, hasher))
+            })
+            .collect();
+        let missing: Vec<Hasher> = hashers
+            .iter()
+            .zip(&digests)
+            .filter(|(_, digest)| digest.is_none())
+            .map(|(hasher, _)| hasher.clone())
+            .collect();
+        if missing.is_empty() {
+            return Ok(digests.into_iter().flatten().collect());
+        }
+
+        let mut computed = hash_file_with_options(path, missing, options, on_progress)?.into_iter();
+        // Only store digests of files that stayed put while being read, and
+        // that weren't written to just before.
+        let settled = fs::metadata(path).ok().and_then(|m| file_key(&m)) == Some(key)
+            && metadata
+                .modified()
+                .is_ok_and(|mtime| mtime + RACY_WINDOW <= started);
+        for (hasher, slot) in hashers.iter().zip(&mut digests) {
+            if slot.is_some() {
+                continue;
+            }
+            let digest = computed.next().expect("one digest per missing hasher");
+            if let Some(id) = hasher.cache_id().filter(|_| settled) {
+                // The cache is only an optimisation; failing to fill it
+                // mustn't fail the hash.
+                let _ = self.store(&id, &key, path, &digest);
+            }
+            *slot = Some(digest);
+        }
+        Ok(digests.into_iter().flatten().collect())
+    }
+
+    /// Removes entries for files that have since changed or disappeared,
+    /// entries that weren't used for `max_age` if given, and anything else
+    /// that doesn't belong.
+    pub fn prune(&self, max_age: Option<Duration>) -> io::Result<PruneStats> {
+        let now = SystemTime::now();
+        let older_than = |metadata: &Metadata, age: Duration| {
+            metadata
+                .modified()
+                .is_ok_and(|mtime| now.duration_since(mtime).is_ok_and(|d| d > age))
+        };
+
+        let mut stats = PruneStats::default();
+        for shard in fs::read_dir(self.dir.join(VERSION_DIR))? {
+            let shard = shard?.path();
+            if !shard.is_dir() {
+                continue;
+            }
+            for entry in fs::read_dir(&shard)? {
+                let entry = entry?;
+                let path = entry.path();
+                // Another process may have renamed or pruned it meanwhile.
+                let Ok(metadata) = entry.metadata() else {
+                    continue;
+                };
+                let is_temp = entry.file_name().to_string_lossy().starts_with('.');
+                let keep = if is_temp {
+                    !older_than(&metadata, STALE_TEMP_AGE)
+                } else {
+                    !max_age.is_some_and(|age| older_than(&metadata, age)) && is_current(&path)
+                };
+                if keep {
+                    stats.kept += usize::from(!is_temp);
+                } else {
+                    match fs::remove_file(&path) {
+                        Ok(()) => stats.removed += 1,
+                        // Another process got there first.
+                        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                        Err(e) => return Err(e),
+                    }
+                }
+            }
+        }
+        Ok(stats)
+    }
+
+    fn entry_path(&self, id: &str, key: &FileKey) -> PathBuf {
+        let mut name = Hasher::new(HashAlgorithm::BLAKE3);
+        name.update(key_line(id, key).as_bytes());
+        let name = name.finalize().to_hex();
+        self.dir.join(VERSION_DIR).join(&name[..2]).join(&name[2..])
+    }
+
+    fn lookup(&self, id: &str, key: &FileKey, hasher: &Hasher) -> Option<Digest> {
+        let entry = self.entry_path(id, key);
+        let text = fs::read_to_string(&entry).ok()?;
+        let (entry_key, hex, _) = parse_entry(&text)?;
+        if entry_key != key_line(id, key) {
+            return None;
+        }
+        let digest = Digest::from_hex(hasher.algorithm(), hex).ok()?;
+        if digest.as_bytes().len() != hasher.output_len() {
+            return None;
+        }
+
+        // Keep entries in use from being pruned for age.
+        let used = fs::metadata(&entry).and_then(|m| m.modified());
+        if used.is_ok_and(|used| used + TOUCH_INTERVAL < SystemTime::now()) {
+            let _ = File::options()
+                .append(true)
+                .open(&entry)
+                .and_then(|file| file.set_modified(SystemTime::now()));
+        }
+        Some(digest)
+    }
+
+    fn store(&self, id: &str, key: &FileKey, path: &Path, digest: &Digest) -> io::Result<()> {
+        // Absolute, so that `prune` finds the file wherever it runs.
+        let path = fs::canonicalize(path)?;
+        let entry = self.entry_path(id, key);
+        let dir = entry.parent().unwrap();
+        fs::create_dir_all(dir)?;
+        let temp = dir.join(format!(
+            ".{}.{}.tmp",
+            std::process::id(),
+            TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let contents = format!(
+            "{}\n{}\n{}\n{}\n",
+            ENTRY_HEADER,
+            key_line(id, key),
+            digest.to_hex(),
+            hex::encode(path.as_os_str().as_encoded_bytes())
+        );
+        fs::write(&temp, contents)?;
+        fs::rename(&temp, &entry).inspect_err(|_| {
+            let _ = fs::remove_file(&temp);
+        })
+    }
+}
+
+/// Whether the cache entry at `entry` still describes the file it was made
+/// for.
+fn is_current(entry: &Path) -> bool {
+    let Ok(text) = fs::read_to_string(entry) else {
+        return false;
+    };
+    let Some((key, _, path)) = parse_entry(&text) else {
+        return false;
+    };
+    let Some((id, _)) = key.split_once(' ') else {
+        return false;
+    };
+    let Some(path) = decode_path(path) else {
+        return false;
+    };
+    fs::metadata(path)
+        .ok()
+        .and_then(|metadata| file_key(&metadata))
+        .is_some_and(|current| key_line(id, &current) == key)
+}
+
+/// Splits an entry into its key line, hex digest and the path it was made
+/// for.
+fn parse_entry(text: &str) -> Option<(&str, &str, &str)> {
+    let mut lines = text.lines();
+    if lines.next()? != ENTRY_HEADER {
+        return None;
+    }
+    Some((lines.next()?, lines.next()?, lines.next()?))
+}
+
+/// The path an entry was made for, from the hex of its bytes.
+#[cfg(unix)]
+fn decode_path(hex: &str) -> Option<PathBuf> {
+    use std::ffi::OsString;
+    use std::os::unix::ffi::OsStringExt;
+
+    hex::decode(hex)
+        .ok()
+        .map(|bytes| OsString::from_vec(bytes).into())
+}
+
+#[cfg(not(unix))]
+fn decode_path(_hex: &str) -> Option<PathBuf> {
+    None
+}
+
+fn key_line(id: &str, key: &FileKey) -> String {
+    format!(
+        "{} {} {} {} {}",
+        id, key.dev, key.ino, key.size, key.mtime_ns
+    )
+}
+
+#[cfg(unix)]
+fn file_key(metadata: &Metadata) -> Option<FileKey> {
+    use std::os::unix::fs::MetadataExt;
+    use std::time::UNIX_EPOCH;
+
+    if !metadata.is_file() {
+        return None;
+    }
+    let mtime = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some(FileKey {
+        dev: metadata.dev(),
+        ino: metadata.ino(),
+        size: metadata.len(),
+        mtime_ns: mtime.as_nanos(),
+    })
+}
+
+#[cfg(not(unix))]
+fn file_key(_metadata: &Metadata) -> Option<FileKey> {
+    None
+}
@@ -0,0 +1,279 @@
+//! Reading and writing checksum files in the formats written by GNU coreutils
+//! (`sha256sum`, `md5sum`, ...) and BSD-style tools (`shasum --tag`).
+
+use crate::{verify_file_using, Digest, HashAlgorithm, Hmac, ParseDigestError, ReadOptions};
+use std::borrow::Cow;
+use std::fmt;
+use std::io::{self, BufRead};
+use std::path::PathBuf;
+
+/// One line of a checksum file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChecksumEntry {
+    pub path: PathBuf,
+    pub expected: Digest,
+    /// Set by the `*` marker of the GNU format. Has no effect on hashing.
+    pub binary: bool,
+    /// The line didn't name its algorithm, so it was guessed from the digest
+    /// length and other algorithms of that length should be tried too.
+    pub algorithm_inferred: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseChecksumError {
+    Malformed,
+    UnknownAlgorithm(String),
+    InvalidDigest(ParseDigestError),
+}
+
+impl fmt::Display for ParseChecksumError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseChecksumError::Malformed => write!(f, "improperly formatted checksum line"),
+            ParseChecksumError::UnknownAlgorithm(name) => write!(f, "unknown algorithm '{}'", name),
+            ParseChecksumError::InvalidDigest(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ParseChecksumError {}
+
+impl From<ParseDigestError> for ParseChecksumError {
+    fn from(e: ParseDigestError) -> Self {
+        ParseChecksumError::InvalidDigest(e)
+    }
+}
+
+/// Parses a single line in either GNU (`HASH  path`, `HASH *path`) or BSD
+/// (`ALGO (path) = HASH`) format.
+///
+/// GNU lines don't name their algorithm; `algorithm` is used if given,
+/// otherwise it is inferred from the digest length.
+pub fn parse_line(
+    line: &str,
+    algorithm: Option<HashAlgorithm>,
+) -> Result<ChecksumEntry, ParseChecksumError> {
+    let line = line.trim_end_matches(['\n', '\r']);
+    let (escaped, line) = match line.strip_prefix('\\') {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+
+    if let Some(entry) = parse_bsd_line(line, escaped)? {
+        return Ok(entry);
+    }
+
+    let (hex, rest) = line.split_once(' ').ok_or(ParseChecksumError::Malformed)?;
+    let (binary, name) = match rest.chars().next() {
+        Some('*') => (true, &rest[1..]),
+        Some(' ') => (false, &rest[1..]),
+        _ => return Err(ParseChecksumError::Malformed),
+    };
+    if name.is_empty() {
+        return Err(ParseChecksumError::Malformed);
+    }
+
+    let expected = match algorithm {
+        Some(algorithm) => Digest::from_hex(algorithm, hex)?,
+        None => hex.parse()?,
+    };
+
+    Ok(ChecksumEntry {
+        path: PathBuf::from(unescape_name(name, escaped)?),
+        expected,
+        binary,
+        algorithm_inferred: algorithm.is_none(),
+    })
+}
+
+fn parse_bsd_line(line: &str, escaped: bool) -> Result<Option<ChecksumEntry>, ParseChecksumError> {
+    let (tag, rest) = match line.split_once(" (") {
+        Some(parts) => parts,
+        None => return Ok(None),
+    };
+    let (name, hex) = match rest.rsplit_once(") = ") {
+        Some(parts) => parts,
+        None => return Ok(None),
+    };
+    // A GNU line whose file name happens to contain " (" has a hex digest
+    // where the tag would be.
+    if tag.contains(' ') || tag.is_empty() {
+        return Ok(None);
+    }
+
+    let (algorithm, bits) =
+        parse_bsd_tag(tag).ok_or_else(|| ParseChecksumError::UnknownAlgorithm(tag.to_string()))?;
+    let expected = Digest::from_hex(algorithm, hex)?;
+    if bits.is_some_and(|bits| bits != expected.as_bytes().len() * 8) {
+        return Err(ParseChecksumError::Malformed);
+    }
+
+    Ok(Some(ChecksumEntry {
+        path: PathBuf::from(unescape_name(name, escaped)?),
+        expected,
+        binary: false,
+        algorithm_inferred: false,
+    }))
+}
+
+/// Splits a tag such as `BLAKE2b-256` into the algorithm and the digest
+/// length in bits, which only algorithms with a selectable length carry.
+fn parse_bsd_tag(tag: &str) -> Option<(HashAlgorithm, Option<usize>)> {
+    if let Some(algorithm) = HashAlgorithm::from_name(tag) {
+        return Some((algorithm, None));
+    }
+    let (name, bits) = tag.rsplit_once('-')?;
+    let algorithm = HashAlgorithm::from_name(name).filter(HashAlgorithm::has_variable_len)?;
+    Some((algorithm, Some(bits.parse().ok()?)))
+}
+
+/// The algorithm name for a BSD-style line, with the length appended when a
+/// variable-length digest isn't the default size, as `b2sum --tag` does.
+fn bsd_tag(digest: &Digest) -> Cow<'static, str> {
+    let algorithm = digest.algorithm();
+    let len = digest.as_bytes().len();
+    if algorithm.has_variable_len() && len != algorithm.digest_len() {
+        Cow::Owned(format!("{}-{}", algorithm.name(), len * 8))
+    } else {
+        Cow::Borrowed(algorithm.name())
+    }
+}
+
+/// Reverses the escaping coreutils applies to file names containing `\`,
+/// newlines or carriage returns.
+fn unescape_name(name: &str, escaped: bool) -> Result<String, ParseChecksumError> {
+    if !escaped {
+        return Ok(name.to_string());
+    }
+
+    let mut out = String::with_capacity(name.len());
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => out.push('\\'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            _ => return Err(ParseChecksumError::Malformed),
+        }
+    }
+    Ok(out)
+}
+
+/// Formats a line the way `sha256sum` and friends print it: `HASH  path`.
+pub fn format_gnu_line(digest: &Digest, path: &str) -> String {
+    let (escaped, name) = escape_name(path);
+    format!("{}{}  {}", if escaped { "\\" } else { "" }, digest, name)
+}
+
+/// Formats a line the way `sha256sum --tag` prints it: `ALGO (path) = HASH`.
+pub fn format_bsd_line(digest: &Digest, path: &str) -> String {
+    let (escaped, name) = escape_name(path);
+    format!(
+        "{}{} ({}) = {}",
+        if escaped { "\\" } else { "" },
+        bsd_tag(digest),
+        name,
+        digest
+    )
+}
+
+/// Escapes `\`, newlines and carriage returns the way coreutils does. The
+/// returned flag says whether the line needs a leading `\`.
+fn escape_name(name: &str) -> (bool, Cow<'_, str>) {
+    if !name.contains(['\\', '\n', '\r']) {
+        return (false, Cow::Borrowed(name));
+    }
+
+    let mut out = String::with_capacity(name.len() + 2);
+    for c in name.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    (true, Cow::Owned(out))
+}
+
+/// Parses every line of a checksum file, skipping blank lines and `#`
+/// comments. Each result is paired with its 1-based line number.
+pub fn parse_checksum_file<R: BufRead>(
+    reader: R,
+    algorithm: Option<HashAlgorithm>,
+) -> io::Result<Vec<(usize, Result<ChecksumEntry, ParseChecksumError>)>> {
+    let mut entries = Vec::new();
+    for (index, line) in reader.split(b'\n').enumerate() {
+        let line = line?;
+        let line = String::from_utf8_lossy(&line);
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        entries.push((index + 1, parse_line(&line, algorithm)));
+    }
+    Ok(entries)
+}
+
+/// Outcome of checking one entry against the file on disk.
+#[derive(Debug)]
+pub enum CheckStatus {
+    /// `computed` is attributed to the algorithm that matched, which for an
+    /// inferred algorithm may not be the one guessed.
+    Ok {
+        computed: Digest,
+    },
+    Failed {
+        computed: Digest,
+    },
+    Missing,
+    Unreadable(io::Error),
+}
+
+pub fn verify_entry(entry: &ChecksumEntry) -> CheckStatus {
+    verify_entry_using(entry, None, &ReadOptions::default())
+}
+
+/// Like [`verify_entry`] for a checksum file of HMAC tags. Each entry is
+/// checked with HMAC-`key` over the entry's algorithm; an inferred algorithm
+/// is taken as is.
+pub fn verify_entry_hmac(entry: &ChecksumEntry, key: &[u8]) -> CheckStatus {
+    verify_entry_using(entry, Some(key), &ReadOptions::default())
+}
+
+/// The general form of [`verify_entry`] and [`verify_entry_hmac`], reading
+/// the file as `options` says.
+pub fn verify_entry_using(
+    entry: &ChecksumEntry,
+    hmac_key: Option<&[u8]>,
+    options: &ReadOptions,
+) -> CheckStatus {
+    let hasher = match hmac_key {
+        Some(key) => match Hmac::new(entry.expected.algorithm(), key) {
+            Ok(hmac) => Some(hmac.into()),
+            Err(e) => return CheckStatus::Unreadable(e),
+        },
+        None => None,
+    };
+    let result = verify_file_using(
+        &entry.path,
+        &entry.expected,
+        hasher,
+        entry.algorithm_inferred,
+        options,
+    );
+    match result {
+        Ok(verification) if verification.is_match() => CheckStatus::Ok {
+            computed: verification.computed,
+        },
+        Ok(verification) => CheckStatus::Failed {
+            computed: verification.computed,
+        },
+        Err(e) if e.kind() == io::ErrorKind::NotFound => CheckStatus::Missing,
+        Err(e) => CheckStatus::Unreadable(e),
+    }
+}
@@ -0,0 +1,158 @@
+use crate::HashAlgorithm;
+use data_encoding::{BASE32, BASE64, BASE64URL_NOPAD, HEXLOWER_PERMISSIVE, HEXUPPER};
+use std::fmt;
+use std::str::FromStr;
+
+/// A computed digest: the raw output bytes together with the algorithm that
+/// produced them. Displays as lowercase hex.
+#[derive(Debug, Clone)]
+pub struct Digest {
+    algorithm: HashAlgorithm,
+    bytes: Vec<u8>,
+}
+
+impl Digest {
+    pub fn new(algorithm: HashAlgorithm, bytes: Vec<u8>) -> Self {
+        Self { algorithm, bytes }
+    }
+
+    /// Parses a hex digest (either case) produced by `algorithm`.
+    pub fn from_hex(algorithm: HashAlgorithm, hex: &str) -> Result<Self, ParseDigestError> {
+        let bytes = decode_hex(hex)?;
+        if !algorithm.output_len_range().contains(&bytes.len()) {
+            return Err(ParseDigestError::LengthMismatch {
+                algorithm,
+                len: bytes.len(),
+            });
+        }
+        Ok(Self::new(algorithm, bytes))
+    }
+
+    pub fn algorithm(&self) -> HashAlgorithm {
+        self.algorithm
+    }
+
+    /// The same bytes attributed to a different algorithm, for resolving
+    /// digests whose algorithm was guessed from an ambiguous length.
+    pub fn with_algorithm(&self, algorithm: HashAlgorithm) -> Self {
+        Self::new(algorithm, self.bytes.clone())
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(&self.bytes)
+    }
+
+    pub fn to_hex_upper(&self) -> String {
+        HEXUPPER.encode(&self.bytes)
+    }
+
+    /// Standard, padded base64 (RFC 4648 §4).
+    pub fn to_base64(&self) -> String {
+        BASE64.encode(&self.bytes)
+    }
+
+    /// URL- and filename-safe base64 without padding (RFC 4648 §5).
+    pub fn to_base64url(&self) -> String {
+        BASE64URL_NOPAD.encode(&self.bytes)
+    }
+
+    /// Padded base32 (RFC 4648 §6).
+    pub fn to_base32(&self) -> String {
+        BASE32.encode(&self.bytes)
+    }
+}
+
+/// Digests compare in constant time with respect to their contents, so they
+/// are safe to use when checking untrusted input.
+impl PartialEq for Digest {
+    fn eq(&self, other: &Self) -> bool {
+        self.algorithm == other.algorithm && constant_time_eq(&self.bytes, &other.bytes)
+    }
+}
+
+impl Eq for Digest {}
+
+/// Compares against a hex string, ignoring case.
+impl PartialEq<str> for Digest {
+    fn eq(&self, other: &str) -> bool {
+        match decode_hex(other) {
+            Ok(bytes) => constant_time_eq(&self.bytes, &bytes),
+            Err(_) => false,
+        }
+    }
+}
+
+impl PartialEq<&str> for Digest {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+impl fmt::Display for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+/// Parses a hex digest, inferring the algorithm from its length.
+impl FromStr for Digest {
+    type Err = ParseDigestError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = decode_hex(s)?;
+        let algorithm = HashAlgorithm::from_digest_len(bytes.len())
+            .ok_or(ParseDigestError::UnknownLength(bytes.len()))?;
+        Ok(Self::new(algorithm, bytes))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseDigestError {
+    InvalidHex,
+    UnknownLength(usize),
+    LengthMismatch {
+        algorithm: HashAlgorithm,
+        len: usize,
+    },
+}
+
+impl fmt::Display for ParseDigestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseDigestError::InvalidHex => write!(f, "digest is not valid hexadecimal"),
+            ParseDigestError::UnknownLength(len) => {
+                write!(f, "no known algorithm produces a {}-byte digest", len)
+            }
+            ParseDigestError::LengthMismatch { algorithm, len } => write!(
+                f,
+                "{:?} digests are {} bytes, got {}",
+                algorithm,
+                algorithm.digest_len(),
+                len
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseDigestError {}
+
+fn decode_hex(s: &str) -> Result<Vec<u8>, ParseDigestError> {
+    HEXLOWER_PERMISSIVE
+        .decode(s.as_bytes())
+        .map_err(|_| ParseDigestError::InvalidHex)
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
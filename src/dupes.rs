@@ -0,0 +1,211 @@
+//! Finding duplicate files without hashing every file in full.
+//!
+//! Files are first grouped by size, which costs nothing but a `stat`. Only
+//! groups of at least two go on to have their first few kilobytes hashed,
+//! and only files that still have company after that are hashed in full.
+
+use crate::batch::for_each_ordered;
+use crate::{hash_file, hash_file_range, ByteRange, Digest, HashAlgorithm};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DupeOptions {
+    /// Bytes hashed from the start of each candidate before the full hash;
+    /// `0` goes straight to full hashes.
+    pub prefix_len: u64,
+    /// Smaller files are ignored. Empty files are all alike and waste no
+    /// space, so the default of 1 leaves them out.
+    pub min_size: u64,
+}
+
+impl Default for DupeOptions {
+    fn default() -> Self {
+        DupeOptions {
+            prefix_len: 64 * 1024,
+            min_size: 1,
+        }
+    }
+}
+
+/// Files with identical contents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateSet {
+    pub size: u64,
+    pub digest: Digest,
+    /// At least two, sorted.
+    pub paths: Vec<PathBuf>,
+}
+
+impl DuplicateSet {
+    /// Bytes that would be freed by keeping a single copy.
+    pub fn wasted_bytes(&self) -> u64 {
+        self.size * (self.paths.len() as u64 - 1)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DupeScan {
+    /// Most wasted bytes first.
+    pub sets: Vec<DuplicateSet>,
+    /// Files that couldn't be read. They are left out of every set.
+    pub errors: Vec<io::Error>,
+    /// Files considered, after hard links to the same file were merged.
+    pub files: usize,
+    /// Files whose prefix was hashed.
+    pub prefix_hashed: usize,
+    /// Files hashed in full.
+    pub fully_hashed: usize,
+}
+
+impl DupeScan {
+    pub fn wasted_bytes(&self) -> u64 {
+        self.sets.iter().map(DuplicateSet::wasted_bytes).sum()
+    }
+}
+
+/// Finds the duplicates among `paths`, hashing on up to `jobs` threads (`0`
+/// for one per CPU). Paths that are hard links to the same file count as
+/// one, since keeping both wastes nothing.
+pub fn find_duplicates(
+    paths: &[PathBuf],
+    algorithm: HashAlgorithm,
+    options: &DupeOptions,
+    jobs: usize,
+) -> DupeScan {
+    let mut scan = DupeScan::default();
+
+    let mut by_size: BTreeMap<u64, Vec<PathBuf>> = BTreeMap::new();
+    let mut seen = HashSet::new();
+    for path in paths {
+        let metadata = match fs::metadata(path) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                scan.errors.push(with_path(path, e));
+                continue;
+            }
+        };
+        if !metadata.is_file() || metadata.len() < options.min_size {
+            continue;
+        }
+        if let Some(id) = file_id(&metadata) {
+            if !seen.insert(id) {
+                continue;
+            }
+        }
+        scan.files += 1;
+        by_size
+            .entry(metadata.len())
+            .or_default()
+            .push(path.clone());
+    }
+
+    let mut candidates: Vec<(u64, Vec<PathBuf>)> = by_size
+        .into_iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .collect();
+
+    if options.prefix_len > 0 {
+        let items: Vec<(u64, &Path)> = candidates
+            .iter()
+            .flat_map(|(size, paths)| paths.iter().map(move |p| (*size, p.as_path())))
+            .collect();
+        scan.prefix_hashed = items.len();
+        let groups = group_by_digest(&items, jobs, &mut scan.errors, |&(size, path)| {
+            let len = size.min(options.prefix_len);
+            hash_file_range(path, algorithm, ByteRange::head(len))
+        });
+
+        // Files no longer than the prefix have been hashed in full already.
+        candidates = Vec::new();
+        for ((size, digest), paths) in groups {
+            if size <= options.prefix_len {
+                scan.sets.push(DuplicateSet {
+                    size,
+                    digest,
+                    paths,
+                });
+            } else {
+                candidates.push((size, paths));
+            }
+        }
+    }
+
+    let items: Vec<(u64, &Path)> = candidates
+        .iter()
+        .flat_map(|(size, paths)| paths.iter().map(move |p| (*size, p.as_path())))
+        .collect();
+    scan.fully_hashed = items.len();
+    let groups = group_by_digest(&items, jobs, &mut scan.errors, |&(_, path)| {
+        hash_file(path, algorithm)
+    });
+    for ((size, digest), paths) in groups {
+        scan.sets.push(DuplicateSet {
+            size,
+            digest,
+            paths,
+        });
+    }
+
+    scan.sets.sort_by(|a, b| {
+        b.wasted_bytes()
+            .cmp(&a.wasted_bytes())
+            .then_with(|| a.paths.cmp(&b.paths))
+    });
+    scan
+}
+
+/// Hashes every `(size, path)` item and groups them by size and digest,
+/// keeping only groups of two or more.
+fn group_by_digest<F>(
+    items: &[(u64, &Path)],
+    jobs: usize,
+    errors: &mut Vec<io::Error>,
+    hash: F,
+) -> Vec<((u64, Digest), Vec<PathBuf>)>
+where
+    F: Fn(&(u64, &Path)) -> io::Result<Digest> + Sync,
+{
+    let mut groups: BTreeMap<(u64, Vec<u8>), (Digest, Vec<PathBuf>)> = BTreeMap::new();
+    for_each_ordered(
+        items,
+        jobs,
+        |item| (item.0, item.1, hash(item)),
+        |(size, path, result)| match result {
+            Ok(digest) => {
+                groups
+                    .entry((size, digest.as_bytes().to_vec()))
+                    .or_insert_with(|| (digest, Vec::new()))
+                    .1
+                    .push(path.to_path_buf());
+            }
+            Err(e) => errors.push(with_path(path, e)),
+        },
+    );
+    groups
+        .into_iter()
+        .filter(|(_, (_, paths))| paths.len() > 1)
+        .map(|((size, _), (digest, mut paths))| {
+            paths.sort();
+            ((size, digest), paths)
+        })
+        .collect()
+}
+
+#[cfg(unix)]
+fn file_id(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+fn with_path(path: &Path, e: io::Error) -> io::Error {
+    io::Error::new(e.kind(), format!("{}: {}", path.display(), e))
+}
@@ -1,49 +1,515 @@
-use hex::encode;
+use crate::read::DEFAULT_BUFFER_SIZE;
+use crate::{check_output_len, Blake2Options, Digest, HashAlgorithm, Progress};
 use sha1::Sha1;
-use sha2::{Digest, Sha256};
-use std::io::{self, BufReader, Read};
+use sha2::{Sha224, Sha256, Sha384, Sha512, Sha512_224, Sha512_256};
+use sha3::digest::{ExtendableOutput, XofReader};
+use sha3::{Sha3_224, Sha3_256, Sha3_384, Sha3_512, Shake128, Shake256};
+use std::io::{self, Read};
+use std::time::Instant;
+use xxhash_rust::xxh3::Xxh3Default;
+use xxhash_rust::xxh64;
 
-const BUFFER_SIZE: usize = 1024 * 1024; // 1MB buffer for streaming
+/// Slice size for [`compute_states_parallel`]: large enough for BLAKE3 to
+/// spread each slice across every core, small enough for smooth progress.
+const PARALLEL_SLICE_SIZE: usize = 16 * 1024 * 1024;
 
-pub fn compute_sha256<R: Read>(reader: &mut R) -> io::Result<String> {
-    let mut hasher = Sha256::new();
-    stream_to_hasher(reader, &mut hasher)?;
-    Ok(encode(hasher.finalize()))
+pub fn compute_sha256<R: Read>(reader: &mut R) -> io::Result<Digest> {
+    compute_with::<R, Sha256>(reader, HashAlgorithm::SHA256)
 }
 
-pub fn compute_md5<R: Read>(reader: &mut R) -> io::Result<String> {
-    let mut buffer = vec![0u8; BUFFER_SIZE];
-    let mut buf_reader = BufReader::with_capacity(BUFFER_SIZE, reader);
+pub fn compute_md5<R: Read>(reader: &mut R) -> io::Result<Digest> {
     let mut context = md5::Context::new();
+    stream_chunks(reader, |chunk| context.consume(chunk))?;
+    Ok(Digest::new(HashAlgorithm::MD5, context.compute().to_vec()))
+}
 
-    loop {
-        let bytes_read = buf_reader.read(&mut buffer)?;
-        if bytes_read == 0 {
-            break;
+pub fn compute_sha1<R: Read>(reader: &mut R) -> io::Result<Digest> {
+    compute_with::<R, Sha1>(reader, HashAlgorithm::SHA1)
+}
+
+pub fn compute_sha224<R: Read>(reader: &mut R) -> io::Result<Digest> {
+    compute_with::<R, Sha224>(reader, HashAlgorithm::SHA224)
+}
+
+pub fn compute_sha384<R: Read>(reader: &mut R) -> io::Result<Digest> {
+    compute_with::<R, Sha384>(reader, HashAlgorithm::SHA384)
+}
+
+pub fn compute_sha512<R: Read>(reader: &mut R) -> io::Result<Digest> {
+    compute_with::<R, Sha512>(reader, HashAlgorithm::SHA512)
+}
+
+pub fn compute_sha512_224<R: Read>(reader: &mut R) -> io::Result<Digest> {
+    compute_with::<R, Sha512_224>(reader, HashAlgorithm::SHA512_224)
+}
+
+pub fn compute_sha512_256<R: Read>(reader: &mut R) -> io::Result<Digest> {
+    compute_with::<R, Sha512_256>(reader, HashAlgorithm::SHA512_256)
+}
+
+pub fn compute_sha3_224<R: Read>(reader: &mut R) -> io::Result<Digest> {
+    compute_with::<R, Sha3_224>(reader, HashAlgorithm::SHA3_224)
+}
+
+pub fn compute_sha3_256<R: Read>(reader: &mut R) -> io::Result<Digest> {
+    compute_with::<R, Sha3_256>(reader, HashAlgorithm::SHA3_256)
+}
+
+pub fn compute_sha3_384<R: Read>(reader: &mut R) -> io::Result<Digest> {
+    compute_with::<R, Sha3_384>(reader, HashAlgorithm::SHA3_384)
+}
+
+pub fn compute_sha3_512<R: Read>(reader: &mut R) -> io::Result<Digest> {
+    compute_with::<R, Sha3_512>(reader, HashAlgorithm::SHA3_512)
+}
+
+/// SHAKE128 with an `output_len`-byte digest.
+pub fn compute_shake128<R: Read>(reader: &mut R, output_len: usize) -> io::Result<Digest> {
+    let mut hasher = Shake128::default();
+    stream_chunks(reader, |chunk| {
+        sha3::digest::Update::update(&mut hasher, chunk)
+    })?;
+    Ok(read_xof(HashAlgorithm::SHAKE128, hasher, output_len))
+}
+
+/// SHAKE256 with an `output_len`-byte digest.
+pub fn compute_shake256<R: Read>(reader: &mut R, output_len: usize) -> io::Result<Digest> {
+    let mut hasher = Shake256::default();
+    stream_chunks(reader, |chunk| {
+        sha3::digest::Update::update(&mut hasher, chunk)
+    })?;
+    Ok(read_xof(HashAlgorithm::SHAKE256, hasher, output_len))
+}
+
+pub fn compute_blake3<R: Read>(reader: &mut R) -> io::Result<Digest> {
+    let mut hasher = blake3::Hasher::new();
+    stream_chunks(reader, |chunk| {
+        hasher.update(chunk);
+    })?;
+    Ok(finish(HashAlgorithm::BLAKE3, hasher.finalize().as_bytes()))
+}
+
+/// BLAKE2b with an `output_len`-byte digest, at most 64. Other lengths
+/// fail with `InvalidInput`.
+pub fn compute_blake2b<R: Read>(reader: &mut R, output_len: usize) -> io::Result<Digest> {
+    check_output_len(HashAlgorithm::BLAKE2B, output_len)?;
+    let mut state = DigestState::new(HashAlgorithm::BLAKE2B, output_len);
+    stream_chunks(reader, |chunk| state.update(chunk))?;
+    Ok(state.finalize())
+}
+
+/// BLAKE2s with an `output_len`-byte digest, at most 32. Other lengths
+/// fail with `InvalidInput`.
+pub fn compute_blake2s<R: Read>(reader: &mut R, output_len: usize) -> io::Result<Digest> {
+    check_output_len(HashAlgorithm::BLAKE2S, output_len)?;
+    let mut state = DigestState::new(HashAlgorithm::BLAKE2S, output_len);
+    stream_chunks(reader, |chunk| state.update(chunk))?;
+    Ok(state.finalize())
+}
+
+/// xxHash64 with seed 0. Not cryptographic.
+pub fn compute_xxh64<R: Read>(reader: &mut R) -> io::Result<Digest> {
+    compute_default(reader, HashAlgorithm::XXH64)
+}
+
+/// 64-bit XXH3. Not cryptographic.
+pub fn compute_xxh3<R: Read>(reader: &mut R) -> io::Result<Digest> {
+    compute_default(reader, HashAlgorithm::XXH3)
+}
+
+/// 128-bit XXH3. Not cryptographic.
+pub fn compute_xxh128<R: Read>(reader: &mut R) -> io::Result<Digest> {
+    compute_default(reader, HashAlgorithm::XXH128)
+}
+
+/// The CRC-32 used by zip, gzip and PNG. Not cryptographic.
+pub fn compute_crc32<R: Read>(reader: &mut R) -> io::Result<Digest> {
+    compute_default(reader, HashAlgorithm::CRC32)
+}
+
+/// CRC-32C (Castagnoli), as used by iSCSI, ext4 and cloud object stores.
+/// Not cryptographic.
+pub fn compute_crc32c<R: Read>(reader: &mut R) -> io::Result<Digest> {
+    compute_default(reader, HashAlgorithm::CRC32C)
+}
+
+/// Adler-32, as used by zlib. Not cryptographic.
+pub fn compute_adler32<R: Read>(reader: &mut R) -> io::Result<Digest> {
+    compute_default(reader, HashAlgorithm::ADLER32)
+}
+
+/// Computes several digests in a single pass: every buffer read from `reader`
+/// is fed to each algorithm in turn. Results are returned in the same order as
+/// `algorithms`.
+pub fn compute_multi<R: Read>(
+    reader: &mut R,
+    algorithms: &[HashAlgorithm],
+) -> io::Result<Vec<Digest>> {
+    let states = algorithms
+        .iter()
+        .map(|&a| DigestState::new(a, a.digest_len()))
+        .collect();
+    compute_states(reader, states)
+}
+
+fn compute_default<R: Read>(reader: &mut R, algorithm: HashAlgorithm) -> io::Result<Digest> {
+    let mut state = DigestState::new(algorithm, algorithm.digest_len());
+    stream_chunks(reader, |chunk| state.update(chunk))?;
+    Ok(state.finalize())
+}
+
+pub(crate) fn compute_states<R: Read>(
+    reader: &mut R,
+    states: Vec<DigestState>,
+) -> io::Result<Vec<Digest>> {
+    compute_states_into(reader, states, &mut vec![0u8; DEFAULT_BUFFER_SIZE])
+}
+
+/// Like [`compute_states`], reading through a caller-provided `buffer`.
+pub(crate) fn compute_states_into<R: Read>(
+    reader: &mut R,
+    mut states: Vec<DigestState>,
+    buffer: &mut [u8],
+) -> io::Result<Vec<Digest>> {
+    stream_chunks_into(reader, buffer, |chunk| {
+        for state in states.iter_mut() {
+            state.update(chunk);
+        }
+    })?;
+
+    Ok(states.into_iter().map(DigestState::finalize).collect())
+}
+
+/// Like [`compute_states`] over data that is already in memory, e.g. a
+/// memory-mapped file. BLAKE3 hashes each slice on the rayon thread pool.
+pub(crate) fn compute_states_parallel<F: FnMut(&Progress)>(
+    data: &[u8],
+    mut states: Vec<DigestState>,
+    mut on_progress: F,
+) -> Vec<Digest> {
+    let start = Instant::now();
+    let mut bytes = 0;
+    for slice in data.chunks(PARALLEL_SLICE_SIZE) {
+        for state in states.iter_mut() {
+            state.update_parallel(slice);
         }
-        context.consume(&buffer[..bytes_read]);
+        bytes += slice.len() as u64;
+        on_progress(&Progress {
+            bytes,
+            elapsed: start.elapsed(),
+        });
     }
 
-    Ok(format!("{:x}", context.compute()))
+    states.into_iter().map(DigestState::finalize).collect()
+}
+
+#[derive(Clone)]
+pub(crate) enum DigestState {
+    Sha256(Sha256),
+    Md5(md5::Context),
+    Sha1(Sha1),
+    Sha224(Sha224),
+    Sha384(Sha384),
+    Sha512(Sha512),
+    Sha512_224(Sha512_224),
+    Sha512_256(Sha512_256),
+    Sha3_224(Sha3_224),
+    Sha3_256(Sha3_256),
+    Sha3_384(Sha3_384),
+    Sha3_512(Sha3_512),
+    Shake128(Shake128, usize),
+    Shake256(Shake256, usize),
+    // Boxed because blake3::Hasher is far larger than the other states.
+    Blake3(Box<blake3::Hasher>),
+    Blake2b(blake2b_simd::State),
+    Blake2s(blake2s_simd::State),
+    Xxh64(xxh64::Xxh64),
+    // Boxed like BLAKE3: the XXH3 state carries a 256-byte buffer.
+    Xxh3(Box<Xxh3Default>),
+    Xxh128(Box<Xxh3Default>),
+    Crc32(crc32fast::Hasher),
+    Crc32c(u32),
+    Adler32(adler2::Adler32),
+    Hmac(Box<HmacState>),
 }
 
-pub fn compute_sha1<R: Read>(reader: &mut R) -> io::Result<String> {
-    let mut hasher = Sha1::new();
+/// Keyed SHA-1/SHA-2 states, see [`crate::hmac::Hmac`].
+#[derive(Clone)]
+pub(crate) enum HmacState {
+    Sha1(::hmac::Hmac<Sha1>),
+    Sha224(::hmac::Hmac<Sha224>),
+    Sha256(::hmac::Hmac<Sha256>),
+    Sha384(::hmac::Hmac<Sha384>),
+    Sha512(::hmac::Hmac<Sha512>),
+    Sha512_224(::hmac::Hmac<Sha512_224>),
+    Sha512_256(::hmac::Hmac<Sha512_256>),
+}
+
+impl DigestState {
+    /// `output_len` only matters for algorithms with a selectable output
+    /// length; the others always produce [`HashAlgorithm::digest_len`] bytes.
+    pub(crate) fn new(algorithm: HashAlgorithm, output_len: usize) -> Self {
+        use sha2::Digest as _;
+
+        match algorithm {
+            HashAlgorithm::SHA256 => DigestState::Sha256(Sha256::new()),
+            HashAlgorithm::MD5 => DigestState::Md5(md5::Context::new()),
+            HashAlgorithm::SHA1 => DigestState::Sha1(Sha1::new()),
+            HashAlgorithm::SHA224 => DigestState::Sha224(Sha224::new()),
+            HashAlgorithm::SHA384 => DigestState::Sha384(Sha384::new()),
+            HashAlgorithm::SHA512 => DigestState::Sha512(Sha512::new()),
+            HashAlgorithm::SHA512_224 => DigestState::Sha512_224(Sha512_224::new()),
+            HashAlgorithm::SHA512_256 => DigestState::Sha512_256(Sha512_256::new()),
+            HashAlgorithm::SHA3_224 => DigestState::Sha3_224(Sha3_224::new()),
+            HashAlgorithm::SHA3_256 => DigestState::Sha3_256(Sha3_256::new()),
+            HashAlgorithm::SHA3_384 => DigestState::Sha3_384(Sha3_384::new()),
+            HashAlgorithm::SHA3_512 => DigestState::Sha3_512(Sha3_512::new()),
+            HashAlgorithm::SHAKE128 => DigestState::Shake128(Shake128::default(), output_len),
+            HashAlgorithm::SHAKE256 => DigestState::Shake256(Shake256::default(), output_len),
+            HashAlgorithm::BLAKE3 => DigestState::Blake3(Box::new(blake3::Hasher::new())),
+            HashAlgorithm::BLAKE2B | HashAlgorithm::BLAKE2S => {
+                DigestState::blake2(algorithm, output_len, &Blake2Options::default())
+            }
+            HashAlgorithm::XXH64 => DigestState::Xxh64(xxh64::Xxh64::new(0)),
+            HashAlgorithm::XXH3 => DigestState::Xxh3(Box::new(Xxh3Default::new())),
+            HashAlgorithm::XXH128 => DigestState::Xxh128(Box::new(Xxh3Default::new())),
+            HashAlgorithm::CRC32 => DigestState::Crc32(crc32fast::Hasher::new()),
+            HashAlgorithm::CRC32C => DigestState::Crc32c(0),
+            HashAlgorithm::ADLER32 => DigestState::Adler32(adler2::Adler32::new()),
+        }
+    }
+
+    /// Callers validate the lengths; the BLAKE2 crates panic on oversized
+    /// parameters.
+    pub(crate) fn blake2(
+        algorithm: HashAlgorithm,
+        output_len: usize,
+        options: &Blake2Options,
+    ) -> Self {
+        if algorithm == HashAlgorithm::BLAKE2S {
+            return DigestState::Blake2s(
+                blake2s_simd::Params::new()
+                    .hash_length(output_len)
+                    .key(&options.key)
+                    .salt(&options.salt)
+                    .personal(&options.personalization)
+                    .to_state(),
+            );
+        }
+        DigestState::Blake2b(
+            blake2b_simd::Params::new()
+                .hash_length(output_len)
+                .key(&options.key)
+                .salt(&options.salt)
+                .personal(&options.personalization)
+                .to_state(),
+        )
+    }
+
+    pub(crate) fn blake3_keyed(key: &[u8; 32]) -> Self {
+        DigestState::Blake3(Box::new(blake3::Hasher::new_keyed(key)))
+    }
+
+    pub(crate) fn blake3_derive_key(context: &str) -> Self {
+        DigestState::Blake3(Box::new(blake3::Hasher::new_derive_key(context)))
+    }
+
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        use sha2::Digest as _;
+
+        match self {
+            DigestState::Sha256(hasher) => hasher.update(data),
+            DigestState::Md5(context) => context.consume(data),
+            DigestState::Sha1(hasher) => hasher.update(data),
+            DigestState::Sha224(hasher) => hasher.update(data),
+            DigestState::Sha384(hasher) => hasher.update(data),
+            DigestState::Sha512(hasher) => hasher.update(data),
+            DigestState::Sha512_224(hasher) => hasher.update(data),
+            DigestState::Sha512_256(hasher) => hasher.update(data),
+            DigestState::Sha3_224(hasher) => hasher.update(data),
+            DigestState::Sha3_256(hasher) => hasher.update(data),
+            DigestState::Sha3_384(hasher) => hasher.update(data),
+            DigestState::Sha3_512(hasher) => hasher.update(data),
+            DigestState::Shake128(hasher, _) => sha3::digest::Update::update(hasher, data),
+            DigestState::Shake256(hasher, _) => sha3::digest::Update::update(hasher, data),
+            DigestState::Blake3(hasher) => {
+                hasher.update(data);
+            }
+            DigestState::Blake2b(state) => {
+                state.update(data);
+            }
+            DigestState::Blake2s(state) => {
+                state.update(data);
+            }
+            DigestState::Xxh64(state) => state.update(data),
+            DigestState::Xxh3(state) | DigestState::Xxh128(state) => state.update(data),
+            DigestState::Crc32(state) => state.update(data),
+            DigestState::Crc32c(crc) => *crc = crc32c::crc32c_append(*crc, data),
+            DigestState::Adler32(state) => state.write_slice(data),
+            DigestState::Hmac(state) => {
+                use ::hmac::Mac;
+                match state.as_mut() {
+                    HmacState::Sha1(mac) => mac.update(data),
+                    HmacState::Sha224(mac) => mac.update(data),
+                    HmacState::Sha256(mac) => mac.update(data),
+                    HmacState::Sha384(mac) => mac.update(data),
+                    HmacState::Sha512(mac) => mac.update(data),
+                    HmacState::Sha512_224(mac) => mac.update(data),
+                    HmacState::Sha512_256(mac) => mac.update(data),
+                }
+            }
+        }
+    }
+
+    /// HMAC keyed with `key`, or `None` if `algorithm` isn't SHA-1 or SHA-2.
+    pub(crate) fn hmac(algorithm: HashAlgorithm, key: &[u8]) -> Option<Self> {
+        use ::hmac::Mac;
+
+        // HMAC accepts keys of any length, so new_from_slice can't fail.
+        let state = match algorithm {
+            HashAlgorithm::SHA1 => HmacState::Sha1(Mac::new_from_slice(key).ok()?),
+            HashAlgorithm::SHA224 => HmacState::Sha224(Mac::new_from_slice(key).ok()?),
+            HashAlgorithm::SHA256 => HmacState::Sha256(Mac::new_from_slice(key).ok()?),
+            HashAlgorithm::SHA384 => HmacState::Sha384(Mac::new_from_slice(key).ok()?),
+            HashAlgorithm::SHA512 => HmacState::Sha512(Mac::new_from_slice(key).ok()?),
+            HashAlgorithm::SHA512_224 => HmacState::Sha512_224(Mac::new_from_slice(key).ok()?),
+            HashAlgorithm::SHA512_256 => HmacState::Sha512_256(Mac::new_from_slice(key).ok()?),
+            _ => return None,
+        };
+        Some(DigestState::Hmac(Box::new(state)))
+    }
+
+    /// Same as [`DigestState::update`], but multithreaded where the
+    /// algorithm supports it. Only worthwhile for large inputs.
+    pub(crate) fn update_parallel(&mut self, data: &[u8]) {
+        match self {
+            DigestState::Blake3(hasher) => {
+                hasher.update_rayon(data);
+            }
+            state => state.update(data),
+        }
+    }
+
+    pub(crate) fn is_hmac(&self) -> bool {
+        matches!(self, DigestState::Hmac(_))
+    }
+
+    pub(crate) fn finalize(self) -> Digest {
+        use sha2::Digest as _;
+
+        match self {
+            DigestState::Sha256(hasher) => finish(HashAlgorithm::SHA256, hasher.finalize()),
+            DigestState::Md5(context) => {
+                Digest::new(HashAlgorithm::MD5, context.compute().to_vec())
+            }
+            DigestState::Sha1(hasher) => finish(HashAlgorithm::SHA1, hasher.finalize()),
+            DigestState::Sha224(hasher) => finish(HashAlgorithm::SHA224, hasher.finalize()),
+            DigestState::Sha384(hasher) => finish(HashAlgorithm::SHA384, hasher.finalize()),
+            DigestState::Sha512(hasher) => finish(HashAlgorithm::SHA512, hasher.finalize()),
+            DigestState::Sha512_224(hasher) => finish(HashAlgorithm::SHA512_224, hasher.finalize()),
+            DigestState::Sha512_256(hasher) => finish(HashAlgorithm::SHA512_256, hasher.finalize()),
+            DigestState::Sha3_224(hasher) => finish(HashAlgorithm::SHA3_224, hasher.finalize()),
+            DigestState::Sha3_256(hasher) => finish(HashAlgorithm::SHA3_256, hasher.finalize()),
+            DigestState::Sha3_384(hasher) => finish(HashAlgorithm::SHA3_384, hasher.finalize()),
+            DigestState::Sha3_512(hasher) => finish(HashAlgorithm::SHA3_512, hasher.finalize()),
+            DigestState::Shake128(hasher, len) => read_xof(HashAlgorithm::SHAKE128, hasher, len),
+            DigestState::Shake256(hasher, len) => read_xof(HashAlgorithm::SHAKE256, hasher, len),
+            DigestState::Blake3(hasher) => {
+                finish(HashAlgorithm::BLAKE3, hasher.finalize().as_bytes())
+            }
+            DigestState::Blake2b(state) => {
+                finish(HashAlgorithm::BLAKE2B, state.finalize().as_bytes())
+            }
+            DigestState::Blake2s(state) => {
+                finish(HashAlgorithm::BLAKE2S, state.finalize().as_bytes())
+            }
+            // Big-endian, the canonical form printed by xxhsum and the
+            // usual CRC tools.
+            DigestState::Xxh64(state) => finish(HashAlgorithm::XXH64, state.digest().to_be_bytes()),
+            DigestState::Xxh3(state) => finish(HashAlgorithm::XXH3, state.digest().to_be_bytes()),
+            DigestState::Xxh128(state) => {
+                finish(HashAlgorithm::XXH128, state.digest128().to_be_bytes())
+            }
+            DigestState::Crc32(state) => {
+                finish(HashAlgorithm::CRC32, state.finalize().to_be_bytes())
+            }
+            DigestState::Crc32c(crc) => finish(HashAlgorithm::CRC32C, crc.to_be_bytes()),
+            DigestState::Adler32(state) => {
+                finish(HashAlgorithm::ADLER32, state.checksum().to_be_bytes())
+            }
+            DigestState::Hmac(state) => {
+                use ::hmac::Mac;
+                match *state {
+                    HmacState::Sha1(mac) => {
+                        finish(HashAlgorithm::SHA1, mac.finalize().into_bytes())
+                    }
+                    HmacState::Sha224(mac) => {
+                        finish(HashAlgorithm::SHA224, mac.finalize().into_bytes())
+                    }
+                    HmacState::Sha256(mac) => {
+                        finish(HashAlgorithm::SHA256, mac.finalize().into_bytes())
+                    }
+                    HmacState::Sha384(mac) => {
+                        finish(HashAlgorithm::SHA384, mac.finalize().into_bytes())
+                    }
+                    HmacState::Sha512(mac) => {
+                        finish(HashAlgorithm::SHA512, mac.finalize().into_bytes())
+                    }
+                    HmacState::Sha512_224(mac) => {
+                        finish(HashAlgorithm::SHA512_224, mac.finalize().into_bytes())
+                    }
+                    HmacState::Sha512_256(mac) => {
+                        finish(HashAlgorithm::SHA512_256, mac.finalize().into_bytes())
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn finish(algorithm: HashAlgorithm, output: impl AsRef<[u8]>) -> Digest {
+    Digest::new(algorithm, output.as_ref().to_vec())
+}
+
+fn read_xof<X: ExtendableOutput>(algorithm: HashAlgorithm, hasher: X, output_len: usize) -> Digest {
+    let mut output = vec![0u8; output_len];
+    hasher.finalize_xof().read(&mut output);
+    Digest::new(algorithm, output)
+}
+
+fn compute_with<R: Read, D: sha2::Digest>(
+    reader: &mut R,
+    algorithm: HashAlgorithm,
+) -> io::Result<Digest> {
+    let mut hasher = D::new();
     stream_to_hasher(reader, &mut hasher)?;
-    Ok(encode(hasher.finalize()))
+    Ok(finish(algorithm, hasher.finalize()))
 }
 
-fn stream_to_hasher<R: Read, D: Digest>(reader: &mut R, hasher: &mut D) -> io::Result<()> {
-    let mut buffer = vec![0u8; BUFFER_SIZE];
-    let mut buf_reader = BufReader::with_capacity(BUFFER_SIZE, reader);
+fn stream_to_hasher<R: Read, D: sha2::Digest>(reader: &mut R, hasher: &mut D) -> io::Result<()> {
+    stream_chunks(reader, |chunk| hasher.update(chunk))
+}
 
+pub(crate) fn stream_chunks<R: Read, F: FnMut(&[u8])>(
+    reader: &mut R,
+    consume: F,
+) -> io::Result<()> {
+    stream_chunks_into(reader, &mut vec![0u8; DEFAULT_BUFFER_SIZE], consume)
+}
+
+/// Reads `reader` straight into `buffer` and hands each chunk to `consume`,
+/// with no intermediate `BufReader` copy.
+pub(crate) fn stream_chunks_into<R: Read, F: FnMut(&[u8])>(
+    reader: &mut R,
+    buffer: &mut [u8],
+    mut consume: F,
+) -> io::Result<()> {
     loop {
-        let bytes_read = buf_reader.read(&mut buffer)?;
-        if bytes_read == 0 {
-            break;
+        match reader.read(buffer) {
+            Ok(0) => return Ok(()),
+            Ok(bytes_read) => consume(&buffer[..bytes_read]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
         }
-        hasher.update(&buffer[..bytes_read]);
     }
-
-    Ok(())
 }
@@ -0,0 +1,60 @@
+//! HMAC (RFC 2104) over the SHA-1 and SHA-2 algorithms, for checksums that
+//! can't be regenerated by someone who doesn't hold the key.
+
+use crate::hash::DigestState;
+use crate::{Digest, HashAlgorithm, Hasher};
+use std::io;
+
+/// An HMAC computation. Convert it into a [`Hasher`] to use it with
+/// [`crate::hash_file_with`], [`crate::verify_file_with`] and friends.
+///
+/// The key is only kept inside the underlying MAC state and is never
+/// exposed, formatted or logged.
+#[derive(Clone)]
+pub struct Hmac {
+    hasher: Hasher,
+}
+
+impl Hmac {
+    /// Fails with `InvalidInput` for algorithms other than SHA-1 and SHA-2.
+    pub fn new(algorithm: HashAlgorithm, key: &[u8]) -> io::Result<Self> {
+        let state = DigestState::hmac(algorithm, key).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("HMAC is not supported with {}", algorithm.name()),
+            )
+        })?;
+        Ok(Self {
+            hasher: Hasher::from_state(algorithm, algorithm.digest_len(), state),
+        })
+    }
+
+    /// Whether [`Hmac::new`] accepts `algorithm`.
+    pub fn supports(algorithm: HashAlgorithm) -> bool {
+        DigestState::hmac(algorithm, &[]).is_some()
+    }
+
+    pub fn algorithm(&self) -> HashAlgorithm {
+        self.hasher.algorithm()
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.hasher.update(data);
+    }
+
+    /// The authentication tag, attributed to the underlying algorithm.
+    pub fn finalize(self) -> Digest {
+        self.hasher.finalize()
+    }
+
+    /// Checks the tag against `expected` in constant time.
+    pub fn verify(self, expected: &Digest) -> bool {
+        self.finalize() == *expected
+    }
+}
+
+impl From<Hmac> for Hasher {
+    fn from(hmac: Hmac) -> Self {
+        hmac.hasher
+    }
+}
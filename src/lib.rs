@@ -1,37 +1,688 @@
+pub mod batch;
+pub mod blocks;
+pub mod cache;
+pub mod checksum;
+pub mod digest;
+pub mod dupes;
 pub mod hash;
+pub mod hmac;
+pub mod merkle;
+pub mod progress;
+pub mod read;
+pub mod snapshot;
+pub mod tree;
+pub mod walk;
+pub mod xattr;
 
-use std::io;
+use hash::DigestState;
+use std::io::{self, Read};
+use std::ops::RangeInclusive;
 use std::path::Path;
 
-pub use hash::{compute_md5, compute_sha1, compute_sha256};
+pub use batch::{hash_files, FileResult};
+pub use blocks::{diff_blocks, Block, BlockMismatch, Blocks};
+pub use cache::{HashCache, PruneStats};
+pub use digest::{Digest, ParseDigestError};
+pub use dupes::{find_duplicates, DupeOptions, DupeScan, DuplicateSet};
+pub use hash::{
+    compute_adler32, compute_blake2b, compute_blake2s, compute_blake3, compute_crc32,
+    compute_crc32c, compute_md5, compute_multi, compute_sha1, compute_sha224, compute_sha256,
+    compute_sha384, compute_sha3_224, compute_sha3_256, compute_sha3_384, compute_sha3_512,
+    compute_sha512, compute_sha512_224, compute_sha512_256, compute_shake128, compute_shake256,
+    compute_xxh128, compute_xxh3, compute_xxh64,
+};
+pub use hmac::Hmac;
+pub use merkle::{MerkleOptions, MerkleProof, MerkleTree, ProofStep};
+pub use progress::{Progress, ProgressReader};
+pub use read::{ByteRange, FileReader, ReadOptions, ReadStrategy};
+pub use snapshot::{Drift, Snapshot, SnapshotEntry};
+pub use tree::{Tree, TreeEntry, TreeEntryKind, TreeOptions};
+pub use xattr::{hash_file_with_xattrs, read_xattr, write_xattr, StoredDigest, XattrOptions};
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum HashAlgorithm {
     SHA256,
     MD5,
     SHA1,
+    SHA224,
+    SHA384,
+    SHA512,
+    SHA512_224,
+    SHA512_256,
+    SHA3_224,
+    SHA3_256,
+    SHA3_384,
+    SHA3_512,
+    /// Extendable-output function; see [`Hasher::with_output_len`].
+    SHAKE128,
+    /// Extendable-output function; see [`Hasher::with_output_len`].
+    SHAKE256,
+    /// See [`Hasher::blake3_keyed`] and [`Hasher::blake3_derive_key`] for
+    /// its other modes.
+    BLAKE3,
+    /// Digests of 1 to 64 bytes; see [`Hasher::blake2`] for keys, salts and
+    /// personalization.
+    BLAKE2B,
+    /// Digests of 1 to 32 bytes; see [`Hasher::blake2`].
+    BLAKE2S,
+    /// Non-cryptographic, like the rest below; see
+    /// [`HashAlgorithm::is_cryptographic`].
+    XXH64,
+    XXH3,
+    XXH128,
+    CRC32,
+    CRC32C,
+    ADLER32,
 }
 
+impl HashAlgorithm {
+    pub const VARIANTS: [HashAlgorithm; 23] = [
+        HashAlgorithm::SHA256,
+        HashAlgorithm::MD5,
+        HashAlgorithm::SHA1,
+        HashAlgorithm::SHA224,
+        HashAlgorithm::SHA384,
+        HashAlgorithm::SHA512,
+        HashAlgorithm::SHA512_224,
+        HashAlgorithm::SHA512_256,
+        HashAlgorithm::SHA3_224,
+        HashAlgorithm::SHA3_256,
+        HashAlgorithm::SHA3_384,
+        HashAlgorithm::SHA3_512,
+        HashAlgorithm::SHAKE128,
+        HashAlgorithm::SHAKE256,
+        HashAlgorithm::BLAKE3,
+        HashAlgorithm::BLAKE2B,
+        HashAlgorithm::BLAKE2S,
+        HashAlgorithm::XXH64,
+        HashAlgorithm::XXH3,
+        HashAlgorithm::XXH128,
+        HashAlgorithm::CRC32,
+        HashAlgorithm::CRC32C,
+        HashAlgorithm::ADLER32,
+    ];
+
+    /// Size of the raw digest in bytes. For algorithms with a selectable
+    /// output length this is the default: twice the security level for
+    /// extendable-output functions, the maximum for BLAKE2.
+    pub fn digest_len(&self) -> usize {
+        match self {
+            HashAlgorithm::SHA256 => 32,
+            HashAlgorithm::MD5 => 16,
+            HashAlgorithm::SHA1 => 20,
+            HashAlgorithm::SHA224 => 28,
+            HashAlgorithm::SHA384 => 48,
+            HashAlgorithm::SHA512 => 64,
+            HashAlgorithm::SHA512_224 => 28,
+            HashAlgorithm::SHA512_256 => 32,
+            HashAlgorithm::SHA3_224 => 28,
+            HashAlgorithm::SHA3_256 => 32,
+            HashAlgorithm::SHA3_384 => 48,
+            HashAlgorithm::SHA3_512 => 64,
+            HashAlgorithm::SHAKE128 => 32,
+            HashAlgorithm::SHAKE256 => 64,
+            HashAlgorithm::BLAKE3 => 32,
+            HashAlgorithm::BLAKE2B => 64,
+            HashAlgorithm::BLAKE2S => 32,
+            HashAlgorithm::XXH64 | HashAlgorithm::XXH3 => 8,
+            HashAlgorithm::XXH128 => 16,
+            HashAlgorithm::CRC32 | HashAlgorithm::CRC32C | HashAlgorithm::ADLER32 => 4,
+        }
+    }
+
+    /// Whether the algorithm was designed to resist deliberate collisions.
+    /// Checksums and fast hashes such as CRC32 and xxHash only catch
+    /// accidental corruption and must not be relied on for integrity against
+    /// tampering. (MD5 and SHA-1 count as cryptographic despite being broken.)
+    pub fn is_cryptographic(&self) -> bool {
+        !matches!(
+            self,
+            HashAlgorithm::XXH64
+                | HashAlgorithm::XXH3
+                | HashAlgorithm::XXH128
+                | HashAlgorithm::CRC32
+                | HashAlgorithm::CRC32C
+                | HashAlgorithm::ADLER32
+        )
+    }
+
+    /// Whether the output length can be chosen freely.
+    pub fn is_xof(&self) -> bool {
+        matches!(self, HashAlgorithm::SHAKE128 | HashAlgorithm::SHAKE256)
+    }
+
+    /// Digest lengths in bytes this algorithm can produce, see
+    /// [`Hasher::with_output_len`]. A single value for fixed-size algorithms.
+    pub fn output_len_range(&self) -> RangeInclusive<usize> {
+        match self {
+            HashAlgorithm::SHAKE128 | HashAlgorithm::SHAKE256 => 1..=usize::MAX,
+            HashAlgorithm::BLAKE2B => 1..=blake2b_simd::OUTBYTES,
+            HashAlgorithm::BLAKE2S => 1..=blake2s_simd::OUTBYTES,
+            fixed => fixed.digest_len()..=fixed.digest_len(),
+        }
+    }
+
+    /// Whether [`HashAlgorithm::output_len_range`] allows more than one length.
+    pub fn has_variable_len(&self) -> bool {
+        let range = self.output_len_range();
+        range.start() != range.end()
+    }
+
+    /// Name used in BSD-style checksum lines, e.g. `SHA256 (file) = ...`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashAlgorithm::SHA256 => "SHA256",
+            HashAlgorithm::MD5 => "MD5",
+            HashAlgorithm::SHA1 => "SHA1",
+            HashAlgorithm::SHA224 => "SHA224",
+            HashAlgorithm::SHA384 => "SHA384",
+            HashAlgorithm::SHA512 => "SHA512",
+            HashAlgorithm::SHA512_224 => "SHA512/224",
+            HashAlgorithm::SHA512_256 => "SHA512/256",
+            HashAlgorithm::SHA3_224 => "SHA3-224",
+            HashAlgorithm::SHA3_256 => "SHA3-256",
+            HashAlgorithm::SHA3_384 => "SHA3-384",
+            HashAlgorithm::SHA3_512 => "SHA3-512",
+            HashAlgorithm::SHAKE128 => "SHAKE128",
+            HashAlgorithm::SHAKE256 => "SHAKE256",
+            HashAlgorithm::BLAKE3 => "BLAKE3",
+            HashAlgorithm::BLAKE2B => "BLAKE2b",
+            HashAlgorithm::BLAKE2S => "BLAKE2s",
+            HashAlgorithm::XXH64 => "XXH64",
+            HashAlgorithm::XXH3 => "XXH3",
+            HashAlgorithm::XXH128 => "XXH128",
+            HashAlgorithm::CRC32 => "CRC32",
+            HashAlgorithm::CRC32C => "CRC32C",
+            HashAlgorithm::ADLER32 => "ADLER32",
+        }
+    }
+
+    /// Looks up an algorithm by its [`HashAlgorithm::name`], ignoring case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::VARIANTS
+            .into_iter()
+            .find(|algorithm| algorithm.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Name of the extended attribute its digests are stored in, see
+    /// [`xattr`].
+    pub fn xattr_name(&self) -> &'static str {
+        match self {
+            HashAlgorithm::SHA256 => "user.checksum.sha256",
+            HashAlgorithm::MD5 => "user.checksum.md5",
+            HashAlgorithm::SHA1 => "user.checksum.sha1",
+            HashAlgorithm::SHA224 => "user.checksum.sha224",
+            HashAlgorithm::SHA384 => "user.checksum.sha384",
+            HashAlgorithm::SHA512 => "user.checksum.sha512",
+            HashAlgorithm::SHA512_224 => "user.checksum.sha512-224",
+            HashAlgorithm::SHA512_256 => "user.checksum.sha512-256",
+            HashAlgorithm::SHA3_224 => "user.checksum.sha3-224",
+            HashAlgorithm::SHA3_256 => "user.checksum.sha3-256",
+            HashAlgorithm::SHA3_384 => "user.checksum.sha3-384",
+            HashAlgorithm::SHA3_512 => "user.checksum.sha3-512",
+            HashAlgorithm::SHAKE128 => "user.checksum.shake128",
+            HashAlgorithm::SHAKE256 => "user.checksum.shake256",
+            HashAlgorithm::BLAKE3 => "user.checksum.blake3",
+            HashAlgorithm::BLAKE2B => "user.checksum.blake2b",
+            HashAlgorithm::BLAKE2S => "user.checksum.blake2s",
+            HashAlgorithm::XXH64 => "user.checksum.xxh64",
+            HashAlgorithm::XXH3 => "user.checksum.xxh3",
+            HashAlgorithm::XXH128 => "user.checksum.xxh128",
+            HashAlgorithm::CRC32 => "user.checksum.crc32",
+            HashAlgorithm::CRC32C => "user.checksum.crc32c",
+            HashAlgorithm::ADLER32 => "user.checksum.adler32",
+        }
+    }
+
+    /// Guesses the algorithm that produced a digest of `len` bytes.
+    ///
+    /// Most lengths are shared: a 32-byte digest may be SHA-256, SHA-512/256
+    /// or SHA3-256, for example. This picks the most common one; use
+    /// [`HashAlgorithm::candidates_for_digest_len`] to consider every
+    /// possibility. Extendable-output functions and non-cryptographic
+    /// checksums are never guessed.
+    pub fn from_digest_len(len: usize) -> Option<Self> {
+        Self::candidates_for_digest_len(len).first().copied()
+    }
+
+    /// Every algorithm producing `len`-byte digests, most common first.
+    /// Algorithms with a selectable output length are left out since they
+    /// would match almost any length, and non-cryptographic ones since a
+    /// forged collision would pass for, say, an MD5 digest; both have to be
+    /// named explicitly.
+    pub fn candidates_for_digest_len(len: usize) -> &'static [HashAlgorithm] {
+        match len {
+            16 => &[HashAlgorithm::MD5],
+            20 => &[HashAlgorithm::SHA1],
+            28 => &[
+                HashAlgorithm::SHA224,
+                HashAlgorithm::SHA512_224,
+                HashAlgorithm::SHA3_224,
+            ],
+            32 => &[
+                HashAlgorithm::SHA256,
+                HashAlgorithm::SHA512_256,
+                HashAlgorithm::SHA3_256,
+                HashAlgorithm::BLAKE3,
+            ],
+            48 => &[HashAlgorithm::SHA384, HashAlgorithm::SHA3_384],
+            64 => &[HashAlgorithm::SHA512, HashAlgorithm::SHA3_512],
+            _ => &[],
+        }
+    }
+}
+
+/// Incremental hasher for a single algorithm.
+///
+/// Feed data with [`Hasher::update`] (or through `std::io::Write`, e.g. with
+/// `io::copy`) and call [`Hasher::finalize`] for the digest.
+#[derive(Clone)]
 pub struct Hasher {
     algorithm: HashAlgorithm,
+    output_len: usize,
+    state: DigestState,
+    initial: DigestState,
+    /// Made by [`Hasher::new`] or [`Hasher::with_output_len`], so the digest
+    /// depends on nothing but the data.
+    plain: bool,
 }
 
 impl Hasher {
     pub fn new(algorithm: HashAlgorithm) -> Self {
-        Self { algorithm }
+        Self {
+            plain: true,
+            ..Self::from_state(
+                algorithm,
+                algorithm.digest_len(),
+                DigestState::new(algorithm, algorithm.digest_len()),
+            )
+        }
+    }
+
+    /// Creates a hasher producing `output_len`-byte digests. Fails unless the
+    /// length is in [`HashAlgorithm::output_len_range`].
+    pub fn with_output_len(algorithm: HashAlgorithm, output_len: usize) -> io::Result<Self> {
+        check_output_len(algorithm, output_len)?;
+        Ok(Self {
+            plain: true,
+            ..Self::from_state(
+                algorithm,
+                output_len,
+                DigestState::new(algorithm, output_len),
+            )
+        })
+    }
+
+    /// BLAKE3 in keyed-hash mode, a MAC keyed with `key`.
+    pub fn blake3_keyed(key: &[u8; 32]) -> Self {
+        Self::from_state(HashAlgorithm::BLAKE3, 32, DigestState::blake3_keyed(key))
+    }
+
+    /// BLAKE3 in derive-key mode. `context` should be a hardcoded,
+    /// globally unique string describing the application and purpose.
+    pub fn blake3_derive_key(context: &str) -> Self {
+        Self::from_state(
+            HashAlgorithm::BLAKE3,
+            32,
+            DigestState::blake3_derive_key(context),
+        )
+    }
+
+    /// BLAKE2b or BLAKE2s with the given output length, key, salt and
+    /// personalization. Fails for other algorithms and for parameters longer
+    /// than the algorithm allows.
+    pub fn blake2(algorithm: HashAlgorithm, options: &Blake2Options) -> io::Result<Self> {
+        let (key_max, salt_max) = match algorithm {
+            HashAlgorithm::BLAKE2B => (blake2b_simd::KEYBYTES, blake2b_simd::SALTBYTES),
+            HashAlgorithm::BLAKE2S => (blake2s_simd::KEYBYTES, blake2s_simd::SALTBYTES),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{} is not a BLAKE2 algorithm", algorithm.name()),
+                ))
+            }
+        };
+        // Personalization is the same size as the salt in both variants.
+        for (what, len, max) in [
+            ("key", options.key.len(), key_max),
+            ("salt", options.salt.len(), salt_max),
+            ("personalization", options.personalization.len(), salt_max),
+        ] {
+            if len > max {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "{} {} is {} bytes, at most {} allowed",
+                        algorithm.name(),
+                        what,
+                        len,
+                        max
+                    ),
+                ));
+            }
+        }
+
+        let output_len = options.output_len.unwrap_or(algorithm.digest_len());
+        check_output_len(algorithm, output_len)?;
+        Ok(Self::from_state(
+            algorithm,
+            output_len,
+            DigestState::blake2(algorithm, output_len, options),
+        ))
+    }
+
+    pub(crate) fn from_state(
+        algorithm: HashAlgorithm,
+        output_len: usize,
+        state: DigestState,
+    ) -> Self {
+        Self {
+            algorithm,
+            output_len,
+            initial: state.clone(),
+            state,
+            plain: false,
+        }
+    }
+
+    pub fn algorithm(&self) -> HashAlgorithm {
+        self.algorithm
+    }
+
+    /// Whether this hasher computes an HMAC (see [`Hmac`]) rather than a
+    /// plain digest.
+    pub fn is_hmac(&self) -> bool {
+        self.state.is_hmac()
+    }
+
+    /// Names what the hasher computes, for [`HashCache`] entries. `None` for
+    /// keyed and other parameterised hashers, whose digests mustn't be
+    /// stored.
+    pub(crate) fn cache_id(&self) -> Option<String> {
+        self.plain
+            .then(|| format!("{}-{}", self.algorithm.name(), self.output_len))
+    }
+
+    /// Length in bytes of the digest [`Hasher::finalize`] will return.
+    pub fn output_len(&self) -> usize {
+        self.output_len
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.state.update(data);
+    }
+
+    pub fn finalize(self) -> Digest {
+        self.state.finalize()
+    }
+
+    /// Discards everything hashed so far, as if freshly created.
+    pub fn reset(&mut self) {
+        self.state = self.initial.clone();
+    }
+
+    /// Hashes the file at `path` with this hasher's settings. This is
+    /// independent of any data passed to [`Hasher::update`].
+    pub fn hash_file(&self, path: &Path) -> io::Result<Digest> {
+        let mut hasher = self.clone();
+        hasher.reset();
+        let mut digests = hash_file_with(path, vec![hasher], |_| {})?;
+        Ok(digests.remove(0))
     }
+}
 
-    pub fn hash_file(&self, path: &Path) -> io::Result<String> {
-        hash_file(path, self.algorithm)
+fn check_output_len(algorithm: HashAlgorithm, output_len: usize) -> io::Result<()> {
+    if algorithm.output_len_range().contains(&output_len) {
+        return Ok(());
     }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!(
+            "{} does not support a {}-byte output",
+            algorithm.name(),
+            output_len
+        ),
+    ))
+}
+
+/// Parameters for [`Hasher::blake2`]. Empty fields are left out.
+#[derive(Debug, Clone, Default)]
+pub struct Blake2Options {
+    /// Digest length in bytes; the algorithm's maximum if `None`.
+    pub output_len: Option<usize>,
+    /// Turns the hash into a MAC. Up to 64 bytes for BLAKE2b, 32 for BLAKE2s.
+    pub key: Vec<u8>,
+    /// Up to 16 bytes for BLAKE2b, 8 for BLAKE2s.
+    pub salt: Vec<u8>,
+    /// Up to 16 bytes for BLAKE2b, 8 for BLAKE2s.
+    pub personalization: Vec<u8>,
 }
 
-pub fn hash_file(path: &Path, algorithm: HashAlgorithm) -> io::Result<String> {
-    let mut file = std::fs::File::open(path)?;
+impl io::Write for Hasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
 
+pub fn hash_file(path: &Path, algorithm: HashAlgorithm) -> io::Result<Digest> {
+    Hasher::new(algorithm).hash_file(path)
+}
+
+/// Hashes only `range` of the file at `path`. Fails with `UnexpectedEof` if
+/// the range runs past the end of the file.
+pub fn hash_file_range(
+    path: &Path,
+    algorithm: HashAlgorithm,
+    range: ByteRange,
+) -> io::Result<Digest> {
+    let mut reader = range.apply(std::fs::File::open(path)?)?;
+    hash_reader(&mut reader, algorithm)
+}
+
+/// Hashes everything `reader` yields, e.g. a pipe or standard input.
+pub fn hash_reader<R: Read>(reader: &mut R, algorithm: HashAlgorithm) -> io::Result<Digest> {
     match algorithm {
-        HashAlgorithm::SHA256 => compute_sha256(&mut file),
-        HashAlgorithm::MD5 => compute_md5(&mut file),
-        HashAlgorithm::SHA1 => compute_sha1(&mut file),
+        HashAlgorithm::SHA256 => compute_sha256(reader),
+        HashAlgorithm::MD5 => compute_md5(reader),
+        HashAlgorithm::SHA1 => compute_sha1(reader),
+        HashAlgorithm::SHA224 => compute_sha224(reader),
+        HashAlgorithm::SHA384 => compute_sha384(reader),
+        HashAlgorithm::SHA512 => compute_sha512(reader),
+        HashAlgorithm::SHA512_224 => compute_sha512_224(reader),
+        HashAlgorithm::SHA512_256 => compute_sha512_256(reader),
+        HashAlgorithm::SHA3_224 => compute_sha3_224(reader),
+        HashAlgorithm::SHA3_256 => compute_sha3_256(reader),
+        HashAlgorithm::SHA3_384 => compute_sha3_384(reader),
+        HashAlgorithm::SHA3_512 => compute_sha3_512(reader),
+        HashAlgorithm::SHAKE128 => compute_shake128(reader, algorithm.digest_len()),
+        HashAlgorithm::SHAKE256 => compute_shake256(reader, algorithm.digest_len()),
+        HashAlgorithm::BLAKE3 => compute_blake3(reader),
+        HashAlgorithm::BLAKE2B => compute_blake2b(reader, algorithm.digest_len()),
+        HashAlgorithm::BLAKE2S => compute_blake2s(reader, algorithm.digest_len()),
+        HashAlgorithm::XXH64 => compute_xxh64(reader),
+        HashAlgorithm::XXH3 => compute_xxh3(reader),
+        HashAlgorithm::XXH128 => compute_xxh128(reader),
+        HashAlgorithm::CRC32 => compute_crc32(reader),
+        HashAlgorithm::CRC32C => compute_crc32c(reader),
+        HashAlgorithm::ADLER32 => compute_adler32(reader),
+    }
+}
+
+/// Feeds everything `reader` yields to each of `hashers` in a single pass.
+/// Unlike [`compute_multi`], this keeps per-hasher settings such as the
+/// output length of an extendable-output function.
+pub fn hash_reader_with<R: Read>(reader: &mut R, hashers: Vec<Hasher>) -> io::Result<Vec<Digest>> {
+    hash::compute_states(reader, hashers.into_iter().map(|h| h.state).collect())
+}
+
+/// Hashes `path` with every algorithm in `algorithms`, reading the file once.
+pub fn hash_file_multi(path: &Path, algorithms: &[HashAlgorithm]) -> io::Result<Vec<Digest>> {
+    hash_file_with_progress(path, algorithms, |_| {})
+}
+
+/// Like [`hash_file_multi`], but calls `on_progress` as bytes are hashed.
+pub fn hash_file_with_progress<F: FnMut(&Progress)>(
+    path: &Path,
+    algorithms: &[HashAlgorithm],
+    on_progress: F,
+) -> io::Result<Vec<Digest>> {
+    let hashers = algorithms.iter().map(|&a| Hasher::new(a)).collect();
+    hash_file_with(path, hashers, on_progress)
+}
+
+/// File counterpart of [`hash_reader_with`], reporting progress like
+/// [`hash_file_with_progress`]. Large files are memory-mapped, and BLAKE3
+/// then hashes them on all cores.
+pub fn hash_file_with<F: FnMut(&Progress)>(
+    path: &Path,
+    hashers: Vec<Hasher>,
+    on_progress: F,
+) -> io::Result<Vec<Digest>> {
+    hash_file_with_options(path, hashers, &ReadOptions::default(), on_progress)
+}
+
+/// Like [`hash_file_with`], reading the file as `options` says.
+pub fn hash_file_with_options<F: FnMut(&Progress)>(
+    path: &Path,
+    hashers: Vec<Hasher>,
+    options: &ReadOptions,
+    on_progress: F,
+) -> io::Result<Vec<Digest>> {
+    let states: Vec<DigestState> = hashers.into_iter().map(|h| h.state).collect();
+    match read::Source::open(path, options)? {
+        read::Source::Mapped(map) => Ok(hash::compute_states_parallel(&map, states, on_progress)),
+        read::Source::Stream { reader, mut buffer } => {
+            let mut reader = ProgressReader::new(reader, on_progress);
+            hash::compute_states_into(&mut reader, states, buffer.as_mut_slice())
+        }
+    }
+}
+
+/// Result of checking data against an expected digest.
+#[derive(Debug, Clone)]
+pub struct Verification {
+    pub expected: Digest,
+    pub computed: Digest,
+}
+
+impl Verification {
+    pub fn is_match(&self) -> bool {
+        self.expected == self.computed
+    }
+}
+
+/// Hashes `reader` and compares it with `expected`.
+///
+/// If `expected`'s algorithm was only guessed from the digest length, pass
+/// `try_alternatives` to also try every other algorithm with that length (see
+/// [`HashAlgorithm::candidates_for_digest_len`]) in the same pass. The first
+/// one that matches wins and `expected` is re-attributed to it.
+pub fn verify_reader<R: Read>(
+    reader: &mut R,
+    expected: &Digest,
+    try_alternatives: bool,
+) -> io::Result<Verification> {
+    let hashers = verification_hashers(expected, try_alternatives)?;
+    let computed = hash_reader_with(reader, hashers)?;
+    Ok(pick_verification(expected, computed))
+}
+
+/// File counterpart of [`verify_reader`].
+pub fn verify_file(
+    path: &Path,
+    expected: &Digest,
+    try_alternatives: bool,
+) -> io::Result<Verification> {
+    verify_file_using(
+        path,
+        expected,
+        None,
+        try_alternatives,
+        &ReadOptions::default(),
+    )
+}
+
+/// Like [`verify_reader`], but computes the digest with `hasher`, e.g. a
+/// keyed one. No other algorithms are tried.
+pub fn verify_reader_with<R: Read>(
+    reader: &mut R,
+    expected: &Digest,
+    hasher: Hasher,
+) -> io::Result<Verification> {
+    let computed = hash_reader_with(reader, vec![hasher])?;
+    Ok(pick_verification(expected, computed))
+}
+
+/// File counterpart of [`verify_reader_with`].
+pub fn verify_file_with(
+    path: &Path,
+    expected: &Digest,
+    hasher: Hasher,
+) -> io::Result<Verification> {
+    verify_file_using(path, expected, Some(hasher), false, &ReadOptions::default())
+}
+
+/// The general form of [`verify_file`] and [`verify_file_with`]: checks with
+/// `hasher` if given, and otherwise like [`verify_file`]. The file is read
+/// as `options` says.
+pub fn verify_file_using(
+    path: &Path,
+    expected: &Digest,
+    hasher: Option<Hasher>,
+    try_alternatives: bool,
+    options: &ReadOptions,
+) -> io::Result<Verification> {
+    let hashers = match hasher {
+        Some(hasher) => vec![hasher],
+        None => verification_hashers(expected, try_alternatives)?,
+    };
+    let computed = hash_file_with_options(path, hashers, options, |_| {})?;
+    Ok(pick_verification(expected, computed))
+}
+
+fn verification_hashers(expected: &Digest, try_alternatives: bool) -> io::Result<Vec<Hasher>> {
+    let mut hashers = vec![hasher_for(expected)?];
+    if try_alternatives {
+        hashers.extend(
+            HashAlgorithm::candidates_for_digest_len(expected.as_bytes().len())
+                .iter()
+                .filter(|&&a| a != expected.algorithm())
+                .map(|&a| Hasher::new(a)),
+        );
+    }
+    Ok(hashers)
+}
+
+/// The first of `computed` matching `expected`, or else the first one.
+fn pick_verification(expected: &Digest, mut computed: Vec<Digest>) -> Verification {
+    for digest in &computed {
+        let candidate = expected.with_algorithm(digest.algorithm());
+        if *digest == candidate {
+            return Verification {
+                expected: candidate,
+                computed: digest.clone(),
+            };
+        }
+    }
+
+    Verification {
+        expected: expected.clone(),
+        computed: computed.swap_remove(0),
+    }
+}
+
+/// A hasher producing digests as long as `expected`, which for algorithms
+/// with a selectable output length need not be the default.
+fn hasher_for(expected: &Digest) -> io::Result<Hasher> {
+    let algorithm = expected.algorithm();
+    if algorithm.has_variable_len() {
+        Hasher::with_output_len(algorithm, expected.as_bytes().len())
+    } else {
+        Ok(Hasher::new(algorithm))
     }
 }
@@ -1,25 +1,436 @@
-use clap::{Parser, ValueEnum};
+use clap::error::ErrorKind;
+use clap::{ArgGroup, CommandFactory, Parser, Subcommand, ValueEnum};
 use colored::Colorize;
-use file_hasher::{hash_file, HashAlgorithm};
+use file_hasher::batch::{for_each_ordered, worker_count};
+use file_hasher::checksum::{
+    format_bsd_line, format_gnu_line, parse_checksum_file, verify_entry_using, CheckStatus,
+};
+use file_hasher::walk::{walk, SymlinkPolicy, WalkEntry, WalkOptions};
+use file_hasher::xattr;
+use file_hasher::{
+    diff_blocks, find_duplicates, hash_file, hash_file_with_options, hash_file_with_xattrs,
+    hash_reader_with, read_xattr, verify_file_using, verify_reader, verify_reader_with,
+    Blake2Options, Block, BlockMismatch, Blocks, ByteRange, Digest, DupeOptions, DuplicateSet,
+    FileReader, HashAlgorithm, HashCache, Hasher, Hmac, MerkleOptions, MerkleProof, MerkleTree,
+    Progress, ProgressReader, ProofStep, ReadOptions, ReadStrategy, Snapshot, SnapshotEntry,
+    StoredDigest, Tree, TreeEntryKind, TreeOptions, XattrOptions,
+};
 use indicatif::{ProgressBar, ProgressStyle};
-use std::fs;
-use std::path::Path;
-use std::time::Duration;
+use serde_json::{json, Map, Value};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, BufReader, IsTerminal, Read};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, UNIX_EPOCH};
 
 #[derive(Parser)]
 #[command(author, version, about = "A CLI tool to calculate multiple hash types for files", long_about = None)]
+#[command(group(ArgGroup::new("walking").args(["recursive", "tree"]).multiple(true)))]
+#[command(args_conflicts_with_subcommands = true)]
 struct Args {
-    /// Files to hash
-    #[arg(required = true)]
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Files to hash. Use `-` for standard input, which is also the default
+    /// when input is piped in
     files: Vec<String>,
 
-    /// Hash algorithm to use
-    #[arg(short, long, value_enum, default_value = "sha256")]
-    algorithm: Algorithm,
+    /// Hash algorithm to use [default: sha256]
+    #[arg(short, long, value_enum)]
+    algorithm: Option<Algorithm>,
+
+    /// Digest length in bits for shake128, shake256, blake2b and blake2s (a
+    /// multiple of 8) [default: 256 for shake128 and blake2s, 512 otherwise]
+    #[arg(short, long, value_name = "BITS")]
+    length: Option<usize>,
+
+    /// Key the hash with the contents of FILE: exactly 32 bytes for BLAKE3's
+    /// keyed-hash mode, up to 64 (blake2b) or 32 (blake2s) bytes for BLAKE2
+    #[arg(long, value_name = "FILE", conflicts_with = "check")]
+    key_file: Option<PathBuf>,
+
+    /// Compute HMAC tags keyed with the contents of FILE instead of plain
+    /// digests (sha1 and the SHA-2 algorithms). Also applies to --verify and
+    /// --check
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["key_file", "derive_key", "salt", "personalization"]
+    )]
+    hmac_key_file: Option<PathBuf>,
+
+    /// Like --hmac-key-file, but read the key from environment variable VAR
+    #[arg(
+        long,
+        value_name = "VAR",
+        conflicts_with_all = ["hmac_key_file", "key_file", "derive_key", "salt", "personalization"]
+    )]
+    hmac_key_env: Option<String>,
+
+    /// BLAKE2 salt, as hex (up to 16 bytes for blake2b, 8 for blake2s)
+    #[arg(long, value_name = "HEX", conflicts_with = "check")]
+    salt: Option<String>,
+
+    /// BLAKE2 personalization, as hex (up to 16 bytes for blake2b, 8 for
+    /// blake2s)
+    #[arg(long, value_name = "HEX", conflicts_with = "check")]
+    personalization: Option<String>,
+
+    /// Use BLAKE3's key derivation mode with the given context string
+    #[arg(
+        long,
+        value_name = "CONTEXT",
+        conflicts_with_all = ["key_file", "check"]
+    )]
+    derive_key: Option<String>,
 
     /// Verify file against provided hash
     #[arg(short, long)]
     verify: Option<String>,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value = "text")]
+    format: OutputFormat,
+
+    /// Hash every file below the given directories
+    #[arg(short, long)]
+    recursive: bool,
+
+    /// Only hash files whose path below the directory matches GLOB
+    /// (repeatable)
+    #[arg(long, value_name = "GLOB", requires = "walking")]
+    include: Vec<String>,
+
+    /// Skip files and directories whose path below the directory matches
+    /// GLOB (repeatable)
+    #[arg(long, value_name = "GLOB", requires = "walking")]
+    exclude: Vec<String>,
+
+    /// Skip files ignored by .gitignore, and .git directories, when recursing
+    /// or hashing trees
+    #[arg(long, requires = "walking")]
+    gitignore: bool,
+
+    /// How to treat symbolic links when recursing or hashing trees
+    #[arg(long, value_enum, default_value = "skip", requires = "walking")]
+    symlinks: Symlinks,
+
+    /// Hash up to N files at once (0 = one per CPU). Output stays in input
+    /// order. Progress bars are only shown with a single job
+    #[arg(short, long, value_name = "N", default_value_t = 1)]
+    jobs: usize,
+
+    /// How to read files [default: mmap for files of 4 MiB and up, read
+    /// otherwise]
+    #[arg(long, value_enum, default_value = "auto")]
+    io: Io,
+
+    /// Buffer size for --io read and direct, e.g. 64K or 4M
+    #[arg(long, value_name = "SIZE", value_parser = parse_buffer_size, default_value = "1M")]
+    buffer_size: u64,
+
+    /// Reuse digests of unchanged files from an on-disk cache, keyed by
+    /// device, inode, size and modification time. Also turned on by setting
+    /// FILE_HASHER_CACHE=1
+    #[arg(long)]
+    cache: bool,
+
+    /// Don't use the cache, even if FILE_HASHER_CACHE is set
+    #[arg(long, conflicts_with_all = ["cache", "refresh_cache", "cache_dir"])]
+    no_cache: bool,
+
+    /// Hash every file again and overwrite its cache entry. Implies --cache
+    #[arg(long)]
+    refresh_cache: bool,
+
+    /// Keep the cache in DIR [default: $XDG_CACHE_HOME/file-hasher, or
+    /// ~/.cache/file-hasher]. Implies --cache
+    #[arg(long, value_name = "DIR")]
+    cache_dir: Option<PathBuf>,
+
+    /// Store each digest, with the file's modification time, in a
+    /// user.checksum.<algorithm> extended attribute (Linux only). Files
+    /// modified in the last two seconds are skipped
+    #[arg(long, conflicts_with_all = ["check", "verify", "cache", "refresh_cache", "cache_dir"])]
+    write_xattr: bool,
+
+    /// Take digests from user.checksum.* attributes whose modification time
+    /// still matches the file instead of reading it. Works with --check too
+    #[arg(long, conflicts_with_all = ["verify", "cache", "refresh_cache", "cache_dir"])]
+    use_xattr: bool,
+
+    /// Hash files again and compare with their user.checksum.* attributes,
+    /// to find contents that changed although the file wasn't modified
+    #[arg(long, conflicts_with_all = ["check", "verify", "write_xattr", "use_xattr", "cache"])]
+    verify_xattr: bool,
+
+    /// Only hash from byte N onwards (sizes like 4K are accepted here and
+    /// below)
+    #[arg(long, value_name = "N", value_parser = parse_size, conflicts_with = "check")]
+    offset: Option<u64>,
+
+    /// Only hash N bytes (from --offset, or the start of the file). Named
+    /// so because --length is the digest length
+    #[arg(long, value_name = "N", value_parser = parse_size, conflicts_with = "check")]
+    range_length: Option<u64>,
+
+    /// Only hash the first N bytes
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = parse_size,
+        conflicts_with_all = ["offset", "range_length", "check"]
+    )]
+    head: Option<u64>,
+
+    /// Only hash the last N bytes
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = parse_size,
+        conflicts_with_all = ["offset", "range_length", "head", "check"]
+    )]
+    tail: Option<u64>,
+
+    /// Also print the digest of every SIZE-byte block (e.g. 1M), to find
+    /// out later exactly which parts of a file changed. Needs text or JSON
+    /// output and a single algorithm
+    #[arg(
+        long,
+        value_name = "SIZE",
+        value_parser = parse_buffer_size,
+        conflicts_with_all = ["check", "verify", "offset", "range_length", "head", "tail"]
+    )]
+    block_size: Option<u64>,
+
+    /// Check files against a block manifest written by --block-size with
+    /// --format json or ndjson, and report every block that differs. Exits
+    /// like --check
+    #[arg(
+        long,
+        value_name = "MANIFEST",
+        conflicts_with_all = ["files", "check", "verify", "block_size", "offset", "range_length", "head", "tail"]
+    )]
+    verify_blocks: Option<PathBuf>,
+
+    /// Print the root of a Merkle tree over the file instead of a plain
+    /// digest, so single chunks can be verified against it. Needs text or
+    /// JSON output and a single algorithm
+    #[arg(
+        long,
+        conflicts_with_all = ["check", "verify", "block_size", "offset", "range_length", "head", "tail"]
+    )]
+    merkle: bool,
+
+    /// Merkle leaf size, e.g. 64K
+    #[arg(long, value_name = "SIZE", value_parser = parse_buffer_size, default_value = "1M", requires = "merkle")]
+    leaf_size: u64,
+
+    /// Children per Merkle tree node
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 2,
+        value_parser = clap::value_parser!(u16).range(2..),
+        requires = "merkle"
+    )]
+    fan_out: u16,
+
+    /// Also print the inclusion proof of leaf number INDEX (counting from 0)
+    #[arg(long, value_name = "INDEX", requires = "merkle")]
+    merkle_proof: Option<usize>,
+
+    /// Check that the file given is the leaf a Merkle proof (from
+    /// --merkle-proof with --format json) is for, in the tree whose root is
+    /// given with --verify
+    #[arg(
+        long,
+        value_name = "PROOF",
+        requires = "verify",
+        conflicts_with_all = ["merkle", "check", "block_size", "offset", "range_length", "head", "tail"]
+    )]
+    verify_proof: Option<PathBuf>,
+
+    /// Print a single digest for each directory given, covering the
+    /// relative path, type and contents of everything below it. It doesn't
+    /// depend on the order the filesystem lists entries in. Needs text or
+    /// JSON output and a single algorithm
+    #[arg(
+        long,
+        conflicts_with_all = ["recursive", "check", "verify", "block_size", "merkle", "offset", "range_length", "head", "tail"]
+    )]
+    tree: bool,
+
+    /// Include permission bits in the tree digest
+    #[arg(long, requires = "tree")]
+    tree_mode: bool,
+
+    /// Include directories in the tree digest, so that adding or removing
+    /// an empty one changes it
+    #[arg(long, requires = "tree")]
+    tree_dirs: bool,
+
+    /// Verify every entry of a checksum file (GNU or BSD format, `-` for
+    /// standard input). Exits with 1
+    /// if any file failed or is missing, 2 if the checksum file is unusable
+    #[arg(short, long, value_name = "FILE", conflicts_with_all = ["files", "verify"])]
+    check: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Write a manifest of every file below a directory, with its digest,
+    /// size and modification time
+    Snapshot(SnapshotArgs),
+    /// Compare a directory with a manifest written by `snapshot` and report
+    /// added, removed, modified and renamed files. Exits with 1 if anything
+    /// drifted, 2 if the comparison couldn't be made
+    Diff(DiffArgs),
+    /// Find files with identical contents and report the space the extra
+    /// copies take. Same-sized files have their first --prefix-size bytes
+    /// hashed, and only files that still match are hashed in full
+    Dupes(DupesArgs),
+    /// Remove cache entries for files that changed or no longer exist
+    PruneCache(PruneCacheArgs),
+}
+
+#[derive(clap::Args)]
+struct SnapshotArgs {
+    /// Directory to snapshot
+    dir: PathBuf,
+
+    /// Write the manifest to FILE instead of standard output
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Hash algorithm to use
+    #[arg(short, long, value_enum, default_value = "sha256")]
+    algorithm: Algorithm,
+
+    /// Only include files whose path below the directory matches GLOB
+    /// (repeatable). Recorded in the manifest for `diff`
+    #[arg(long, value_name = "GLOB")]
+    include: Vec<String>,
+
+    /// Leave out files and directories whose path below the directory
+    /// matches GLOB (repeatable). Recorded in the manifest for `diff`
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Leave out files ignored by .gitignore, and .git directories.
+    /// Recorded in the manifest for `diff`
+    #[arg(long)]
+    gitignore: bool,
+
+    /// Hash up to N files at once (0 = one per CPU)
+    #[arg(short, long, value_name = "N", default_value_t = 1)]
+    jobs: usize,
+}
+
+#[derive(clap::Args)]
+struct DiffArgs {
+    /// Manifest written by `snapshot`
+    manifest: PathBuf,
+
+    /// Directory to compare with it
+    #[arg(default_value = ".")]
+    dir: PathBuf,
+
+    /// Assume files whose size and modification time match the manifest
+    /// are unchanged instead of hashing them again
+    #[arg(long)]
+    trust_mtime: bool,
+
+    /// Report format
+    #[arg(short, long, value_enum, default_value = "text")]
+    format: ReportFormat,
+
+    /// Hash up to N files at once (0 = one per CPU)
+    #[arg(short, long, value_name = "N", default_value_t = 1)]
+    jobs: usize,
+}
+
+#[derive(clap::Args)]
+struct DupesArgs {
+    /// Directories and files to search
+    #[arg(required = true)]
+    paths: Vec<PathBuf>,
+
+    /// Hash algorithm to use
+    #[arg(short, long, value_enum, default_value = "sha256")]
+    algorithm: Algorithm,
+
+    /// Bytes to hash from the start of same-sized files before hashing
+    /// them in full (0 = hash them in full straight away)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, default_value = "64K")]
+    prefix_size: u64,
+
+    /// Ignore files smaller than SIZE
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, default_value = "1")]
+    min_size: u64,
+
+    /// Only look at files whose path below a directory matches GLOB
+    /// (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    include: Vec<String>,
+
+    /// Skip files and directories whose path below a directory matches
+    /// GLOB (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Skip files ignored by .gitignore, and .git directories
+    #[arg(long)]
+    gitignore: bool,
+
+    /// Hash up to N files at once (0 = one per CPU)
+    #[arg(short, long, value_name = "N", default_value_t = 1)]
+    jobs: usize,
+
+    /// Report format
+    #[arg(short, long, value_enum, default_value = "text")]
+    format: ReportFormat,
+
+    /// Write a shell script to FILE that deletes every copy but the first
+    /// of each set, to review and run later. Like --hardlink and --delete,
+    /// needs a cryptographic --algorithm
+    #[arg(long, value_name = "FILE")]
+    script: Option<PathBuf>,
+
+    /// Replace every copy but the first of each set with a hard link to
+    /// it. Only lists what would be done unless --confirm is given too
+    #[arg(long, conflicts_with = "delete")]
+    hardlink: bool,
+
+    /// Delete every copy but the first of each set. Only lists what would
+    /// be done unless --confirm is given too
+    #[arg(long)]
+    delete: bool,
+
+    /// Really go ahead with --hardlink or --delete
+    #[arg(long)]
+    confirm: bool,
+}
+
+#[derive(clap::Args)]
+struct PruneCacheArgs {
+    /// Also remove entries that haven't been used for DAYS days
+    #[arg(long, value_name = "DAYS")]
+    max_age: Option<u64>,
+
+    /// The cache to prune [default: $XDG_CACHE_HOME/file-hasher, or
+    /// ~/.cache/file-hasher]
+    #[arg(long, value_name = "DIR")]
+    cache_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum ReportFormat {
+    /// One line per change and a summary
+    Text,
+    /// A single JSON object
+    Json,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
@@ -30,25 +441,386 @@ enum Algorithm {
     Md5,
     #[value(name = "sha1")]
     Sha1,
+    #[value(name = "sha224")]
+    Sha224,
+    #[value(name = "sha384")]
+    Sha384,
+    #[value(name = "sha512")]
+    Sha512,
+    #[value(name = "sha512-224")]
+    Sha512_224,
+    #[value(name = "sha512-256")]
+    Sha512_256,
+    #[value(name = "sha3-224")]
+    Sha3_224,
+    #[value(name = "sha3-256")]
+    Sha3_256,
+    #[value(name = "sha3-384")]
+    Sha3_384,
+    #[value(name = "sha3-512")]
+    Sha3_512,
+    #[value(name = "shake128")]
+    Shake128,
+    #[value(name = "shake256")]
+    Shake256,
+    #[value(name = "blake3")]
+    Blake3,
+    #[value(name = "blake2b")]
+    Blake2b,
+    #[value(name = "blake2s")]
+    Blake2s,
+    #[value(name = "xxh64")]
+    Xxh64,
+    #[value(name = "xxh3")]
+    Xxh3,
+    #[value(name = "xxh128")]
+    Xxh128,
+    #[value(name = "crc32")]
+    Crc32,
+    #[value(name = "crc32c")]
+    Crc32c,
+    #[value(name = "adler32")]
+    Adler32,
     #[value(name = "all")]
     All,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum Symlinks {
+    /// Hash the files links point to and descend into linked directories
+    Follow,
+    /// Ignore symbolic links
+    Skip,
+    /// Hash the link's target path instead of its contents
+    Target,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum Io {
+    /// Pick by file size
+    Auto,
+    /// Memory-map the file
+    Mmap,
+    /// Plain reads into a --buffer-size buffer
+    Read,
+    /// O_DIRECT reads that bypass the page cache (Linux only)
+    Direct,
+}
+
+impl From<Io> for ReadStrategy {
+    fn from(io: Io) -> Self {
+        match io {
+            Io::Auto => ReadStrategy::Auto,
+            Io::Mmap => ReadStrategy::Mmap,
+            Io::Read => ReadStrategy::Read,
+            Io::Direct => ReadStrategy::Direct,
+        }
+    }
+}
+
+impl From<Symlinks> for SymlinkPolicy {
+    fn from(symlinks: Symlinks) -> Self {
+        match symlinks {
+            Symlinks::Follow => SymlinkPolicy::Follow,
+            Symlinks::Skip => SymlinkPolicy::Skip,
+            Symlinks::Target => SymlinkPolicy::HashTarget,
+        }
+    }
+}
+
+/// How each file is hashed.
+#[derive(Clone, Copy)]
+enum Layout {
+    /// One digest of the whole file per algorithm.
+    Whole,
+    /// The whole-file digest plus one per block of this many bytes.
+    Blocks(u64),
+    /// The root of a Merkle tree, and optionally the proof for one leaf.
+    /// Leaves are hashed on `jobs` threads.
+    Merkle {
+        options: MerkleOptions,
+        proof: Option<usize>,
+        jobs: usize,
+    },
+}
+
+/// Merkle tree details for a record.
+struct MerkleInfo {
+    options: MerkleOptions,
+    leaf_count: usize,
+    proof: Option<MerkleProof>,
+}
+
+/// A single thing to hash, after directories have been expanded.
+enum Input {
+    File(PathBuf),
+    LinkTarget { path: PathBuf, target: PathBuf },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// Decorated, colored output
+    Text,
+    /// `HASH  path`, as written by sha256sum and md5sum
+    Gnu,
+    /// `SHA256 (path) = HASH`, as written by `shasum --tag`
+    Bsd,
+    /// A single JSON array of records
+    Json,
+    /// One JSON record per line
+    Ndjson,
+}
+
+impl OutputFormat {
+    fn is_json(self) -> bool {
+        matches!(self, OutputFormat::Json | OutputFormat::Ndjson)
+    }
+}
+
+/// Everything learned about one input file, successful or not.
+struct FileRecord {
+    path: String,
+    size: Option<u64>,
+    mtime: Option<u64>,
+    digests: Vec<Digest>,
+    /// The digests are HMAC tags.
+    hmac: bool,
+    /// Set with --block-size; `blocks` then holds the per-block digests.
+    block_size: Option<u64>,
+    blocks: Vec<Block>,
+    /// Set with --merkle; `digests` then holds the root.
+    merkle: Option<MerkleInfo>,
+    elapsed: Duration,
+    error: Option<io::Error>,
+}
+
+impl FileRecord {
+    fn to_json(&self) -> Value {
+        let digests: Map<String, Value> = self
+            .digests
+            .iter()
+            .map(|d| {
+                let label = algorithm_label(d.algorithm(), self.hmac).into_owned();
+                (label, Value::from(d.to_hex()))
+            })
+            .collect();
+        let algorithms: Vec<Cow<str>> = self
+            .digests
+            .iter()
+            .map(|d| algorithm_label(d.algorithm(), self.hmac))
+            .collect();
+
+        let mut record = json!({
+            "path": self.path,
+            "size": self.size,
+            "mtime": self.mtime,
+            "algorithms": algorithms,
+            "digests": digests,
+            "elapsed_secs": self.elapsed.as_secs_f64(),
+        });
+        if let Some(block_size) = self.block_size {
+            record["block_size"] = Value::from(block_size);
+            record["blocks"] = self
+                .blocks
+                .iter()
+                .map(|block| {
+                    json!({
+                        "offset": block.offset,
+                        "length": block.len,
+                        "digest": block.digest.to_hex(),
+                    })
+                })
+                .collect();
+        }
+        if let Some(merkle) = &self.merkle {
+            record["merkle"] = json!({
+                "leaf_size": merkle.options.leaf_size,
+                "fan_out": merkle.options.fan_out,
+                "leaves": merkle.leaf_count,
+            });
+            if let (Some(proof), Some(root)) = (&merkle.proof, self.digests.first()) {
+                record["proof"] = proof_json(proof, root, self.hmac);
+            }
+        }
+        if let Some(e) = &self.error {
+            record["error"] = Value::from(e.to_string());
+        }
+        record
+    }
+}
+
 impl From<Algorithm> for HashAlgorithm {
     fn from(algo: Algorithm) -> Self {
         match algo {
             Algorithm::Sha256 => HashAlgorithm::SHA256,
             Algorithm::Md5 => HashAlgorithm::MD5,
             Algorithm::Sha1 => HashAlgorithm::SHA1,
+            Algorithm::Sha224 => HashAlgorithm::SHA224,
+            Algorithm::Sha384 => HashAlgorithm::SHA384,
+            Algorithm::Sha512 => HashAlgorithm::SHA512,
+            Algorithm::Sha512_224 => HashAlgorithm::SHA512_224,
+            Algorithm::Sha512_256 => HashAlgorithm::SHA512_256,
+            Algorithm::Sha3_224 => HashAlgorithm::SHA3_224,
+            Algorithm::Sha3_256 => HashAlgorithm::SHA3_256,
+            Algorithm::Sha3_384 => HashAlgorithm::SHA3_384,
+            Algorithm::Sha3_512 => HashAlgorithm::SHA3_512,
+            Algorithm::Shake128 => HashAlgorithm::SHAKE128,
+            Algorithm::Shake256 => HashAlgorithm::SHAKE256,
+            Algorithm::Blake3 => HashAlgorithm::BLAKE3,
+            Algorithm::Blake2b => HashAlgorithm::BLAKE2B,
+            Algorithm::Blake2s => HashAlgorithm::BLAKE2S,
+            Algorithm::Xxh64 => HashAlgorithm::XXH64,
+            Algorithm::Xxh3 => HashAlgorithm::XXH3,
+            Algorithm::Xxh128 => HashAlgorithm::XXH128,
+            Algorithm::Crc32 => HashAlgorithm::CRC32,
+            Algorithm::Crc32c => HashAlgorithm::CRC32C,
+            Algorithm::Adler32 => HashAlgorithm::ADLER32,
             Algorithm::All => HashAlgorithm::SHA256, // Default for all
         }
     }
 }
 
+/// Path argument that stands for standard input.
+const STDIN_PATH: &str = "-";
+
 fn main() {
-    let args = Args::parse();
+    let mut args = Args::parse();
+
+    if let Some(command) = args.command.take() {
+        std::process::exit(match command {
+            Command::Snapshot(snapshot) => write_snapshot(&snapshot),
+            Command::Diff(diff) => diff_snapshot(&diff),
+            Command::Dupes(dupes) => find_dupes(&dupes),
+            Command::PruneCache(prune) => prune_cache(&prune),
+        });
+    }
+
+    if let Some(checksum_file) = args.check.take() {
+        // GNU-style lines carry no algorithm name, so only pin one down if
+        // the user asked for it explicitly.
+        let algorithm = args
+            .algorithm
+            .filter(|&algo| algo != Algorithm::All)
+            .map(HashAlgorithm::from);
+        let hmac_key = hmac_key(&args);
+        std::process::exit(check_files(
+            &checksum_file,
+            algorithm,
+            hmac_key.as_deref(),
+            &read_options(&args),
+            args.use_xattr,
+            args.format,
+        ));
+    }
+
+    let algorithm = args.algorithm.unwrap_or(Algorithm::Sha256);
+    let output_len = args.length.map(|bits| output_len_for(algorithm, bits));
+    let mode_hasher = mode_hasher(&args, algorithm, output_len);
+
+    if let Some(manifest) = &args.verify_blocks {
+        std::process::exit(verify_block_manifest(
+            manifest,
+            mode_hasher.as_ref(),
+            &read_options(&args),
+            args.format,
+        ));
+    }
+    for (set, flag) in [
+        (args.block_size.is_some(), "--block-size"),
+        (args.merkle, "--merkle"),
+        (args.tree, "--tree"),
+    ] {
+        if !set {
+            continue;
+        }
+        if algorithm == Algorithm::All {
+            usage_error(&format!("{} needs a single --algorithm", flag));
+        }
+        if matches!(args.format, OutputFormat::Gnu | OutputFormat::Bsd) {
+            usage_error(&format!("{} needs --format text, json or ndjson", flag));
+        }
+    }
+    let xattrs = XattrOptions {
+        read: args.use_xattr,
+        write: args.write_xattr,
+    };
+    for (set, flag) in [
+        (args.use_xattr, "--use-xattr"),
+        (args.write_xattr, "--write-xattr"),
+        (args.verify_xattr, "--verify-xattr"),
+    ] {
+        if !set {
+            continue;
+        }
+        if args.block_size.is_some() || args.merkle || args.tree || byte_range(&args).is_some() {
+            usage_error(&format!("{} only works with whole-file digests", flag));
+        }
+        let storable = match &mode_hasher {
+            Some(hasher) => xattr::storable(hasher),
+            None => hashers_for(algorithm, output_len)
+                .iter()
+                .any(xattr::storable),
+        };
+        if !storable {
+            usage_error(&format!(
+                "{} needs plain digests of the default length, without keys or --length",
+                flag
+            ));
+        }
+    }
+
+    if args.files.is_empty() {
+        if io::stdin().is_terminal() {
+            Args::command()
+                .error(
+                    ErrorKind::MissingRequiredArgument,
+                    "no files given (pipe data in or pass `-` to hash standard input)",
+                )
+                .exit();
+        }
+        args.files.push(STDIN_PATH.to_string());
+    }
+    if byte_range(&args).is_some() && args.files.iter().any(|f| f == STDIN_PATH) {
+        usage_error("--offset, --range-length, --head and --tail need files, not standard input");
+    }
+    if (args.use_xattr || args.write_xattr || args.verify_xattr)
+        && args.files.iter().any(|f| f == STDIN_PATH)
+    {
+        usage_error("--use-xattr, --write-xattr and --verify-xattr need files, not standard input");
+    }
+
+    if args.verify_xattr {
+        let hashers = match mode_hasher {
+            Some(hasher) => vec![hasher],
+            None => hashers_for(algorithm, output_len),
+        };
+        std::process::exit(verify_xattrs(&args, &hashers));
+    }
+
+    if args.tree {
+        if args.files.iter().any(|f| f == STDIN_PATH) {
+            usage_error("--tree needs directories, not standard input");
+        }
+        let hasher = match mode_hasher {
+            Some(hasher) => hasher,
+            None => hashers_for(algorithm, output_len).remove(0),
+        };
+        std::process::exit(hash_trees(&args, &hasher));
+    }
 
-    if let Some(expected_hash) = args.verify {
+    if let Some(expected_hash) = args.verify.take() {
+        if let Some(proof) = &args.verify_proof {
+            if args.files.len() != 1 || args.files[0] == STDIN_PATH {
+                usage_error("--verify-proof needs exactly one leaf file");
+            }
+            std::process::exit(verify_merkle_proof(
+                Path::new(&args.files[0]),
+                proof,
+                &expected_hash,
+                mode_hasher.as_ref(),
+                args.format,
+            ));
+        }
         if args.files.len() > 1 {
             eprintln!(
                 "{}: Cannot verify multiple files against a single hash",
@@ -56,135 +828,1285 @@ fn main() {
             );
             std::process::exit(1);
         }
-        verify_file(&args.files[0], &expected_hash, args.algorithm);
+        verify_file(
+            &args.files[0],
+            &expected_hash,
+            args.algorithm,
+            output_len,
+            mode_hasher,
+            &read_options(&args),
+            args.format,
+        );
     } else {
-        for file in &args.files {
-            hash_and_display(file, args.algorithm);
+        let hashers = match mode_hasher {
+            Some(hasher) => vec![hasher],
+            None => hashers_for(algorithm, output_len),
+        };
+        let mut json_records = Vec::new();
+        let mut had_errors = false;
+
+        let inputs = if args.recursive {
+            expand_directories(&args.files, &walk_options(&args), &mut had_errors)
+        } else {
+            args.files
+                .iter()
+                .map(|file| Input::File(file.into()))
+                .collect()
+        };
+
+        let show_progress = worker_count(args.jobs) == 1;
+        let read_options = read_options(&args);
+        let cache = hash_cache(&args);
+        let layout = if args.merkle {
+            Layout::Merkle {
+                options: MerkleOptions {
+                    leaf_size: args.leaf_size,
+                    fan_out: args.fan_out.into(),
+                },
+                proof: args.merkle_proof,
+                // Spread the leaves over every core unless files already are.
+                jobs: if show_progress { 0 } else { 1 },
+            }
+        } else if let Some(block_size) = args.block_size {
+            Layout::Blocks(block_size)
+        } else {
+            Layout::Whole
+        };
+        for_each_ordered(
+            &inputs,
+            args.jobs,
+            |input| match input {
+                Input::File(file) => hash_file_record(
+                    file,
+                    &hashers,
+                    &read_options,
+                    layout,
+                    cache.as_ref(),
+                    xattrs,
+                    show_progress,
+                ),
+                Input::LinkTarget { path, target } => link_target_record(path, target, &hashers),
+            },
+            |record| {
+                had_errors |= record.error.is_some();
+                match args.format {
+                    OutputFormat::Json => json_records.push(record.to_json()),
+                    OutputFormat::Ndjson => println!("{}", record.to_json()),
+                    _ => display_record(&record, args.format),
+                }
+            },
+        );
+
+        if args.format == OutputFormat::Json {
+            println!("{}", Value::Array(json_records));
+        }
+        if had_errors {
+            std::process::exit(1);
         }
     }
 }
 
-fn hash_and_display(file_path: &str, algorithm: Algorithm) {
-    let path = Path::new(file_path);
+fn walk_options(args: &Args) -> WalkOptions {
+    WalkOptions {
+        include: args.include.clone(),
+        exclude: args.exclude.clone(),
+        gitignore: args.gitignore,
+        symlinks: args.symlinks.into(),
+        directories: args.tree_dirs,
+    }
+}
 
-    if !path.exists() {
-        eprintln!("{}: File '{}' not found", "Error".red(), file_path);
-        std::process::exit(1);
+/// Prints the tree digest of every directory in `args.files`, returning the
+/// exit code.
+fn hash_trees(args: &Args, hasher: &Hasher) -> i32 {
+    let options = TreeOptions {
+        walk: walk_options(args),
+        mode: args.tree_mode,
+    };
+    let label = algorithm_label(hasher.algorithm(), hasher.is_hmac());
+    let mut json_records = Vec::new();
+    let mut exit_code = 0;
+
+    for root in &args.files {
+        let tree = match Tree::from_dir(Path::new(root), hasher, &options, args.jobs) {
+            Ok(tree) => tree,
+            Err(e) => {
+                eprintln!("{}: Failed to hash tree '{}' - {}", "Error".red(), root, e);
+                if args.format.is_json() {
+                    let record = json!({ "path": root, "error": e.to_string() });
+                    match args.format {
+                        OutputFormat::Ndjson => println!("{}", record),
+                        _ => json_records.push(record),
+                    }
+                }
+                exit_code = 1;
+                continue;
+            }
+        };
+
+        if !args.format.is_json() {
+            println!("\n{} {}", "Tree:".bold(), root.cyan());
+            println!(
+                "  {}: {}",
+                format!("{} tree", label).green().bold(),
+                tree.digest()
+            );
+            println!("  {}: {}", "Entries".bold(), tree.entries().len());
+            continue;
+        }
+        let entries: Vec<Value> = tree
+            .entries()
+            .iter()
+            .map(|entry| {
+                let mut value = json!({ "path": entry.path.to_string_lossy() });
+                match &entry.kind {
+                    TreeEntryKind::File(digest) => {
+                        value["type"] = Value::from("file");
+                        value["digest"] = Value::from(digest.to_hex());
+                    }
+                    TreeEntryKind::Symlink(target) => {
+                        value["type"] = Value::from("symlink");
+                        value["target"] = Value::from(target.to_string_lossy());
+                    }
+                    TreeEntryKind::Dir => value["type"] = Value::from("directory"),
+                }
+                if let Some(mode) = entry.mode {
+                    value["mode"] = Value::from(format!("{:04o}", mode));
+                }
+                value
+            })
+            .collect();
+        let record = json!({
+            "path": root,
+            "algorithm": label,
+            "tree": tree.digest().to_hex(),
+            "mode": options.mode,
+            "directories": options.walk.directories,
+            "entries": entries,
+        });
+        match args.format {
+            OutputFormat::Ndjson => println!("{}", record),
+            _ => json_records.push(record),
+        }
+    }
+
+    if args.format == OutputFormat::Json {
+        println!("{}", Value::Array(json_records));
     }
+    exit_code
+}
 
-    let file_size = match fs::metadata(path) {
-        Ok(metadata) => metadata.len(),
+/// Runs `snapshot`, returning the exit code.
+fn write_snapshot(args: &SnapshotArgs) -> i32 {
+    if args.algorithm == Algorithm::All {
+        usage_error("snapshot needs a single --algorithm");
+    }
+    let options = WalkOptions {
+        include: args.include.clone(),
+        exclude: args.exclude.clone(),
+        gitignore: args.gitignore,
+        ..Default::default()
+    };
+    let snapshot = match Snapshot::new(&args.dir, args.algorithm.into(), &options, args.jobs) {
+        Ok(snapshot) => snapshot,
         Err(e) => {
             eprintln!(
-                "{}: Cannot read file metadata for '{}': {}",
+                "{}: Cannot snapshot '{}': {}",
                 "Error".red(),
-                file_path,
+                args.dir.display(),
                 e
             );
-            return;
+            return 1;
         }
     };
 
-    let show_progress = file_size > 10 * 1024 * 1024; // Show progress for files > 10MB
-    let pb = if show_progress {
-        let pb = ProgressBar::new(file_size);
-        pb.set_style(
-            ProgressStyle::default_bar()
-                .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} {msg}")
-                .unwrap()
-                .progress_chars("##-"),
-        );
-        pb.set_message(format!(
-            "Hashing {}",
-            path.file_name().unwrap().to_string_lossy()
-        ));
-        pb.enable_steady_tick(Duration::from_millis(100));
-        Some(pb)
-    } else {
-        None
-    };
+    let manifest = snapshot.to_manifest();
+    match &args.output {
+        Some(path) => {
+            if let Err(e) = fs::write(path, manifest) {
+                eprintln!(
+                    "{}: Cannot write '{}': {}",
+                    "Error".red(),
+                    path.display(),
+                    e
+                );
+                return 1;
+            }
+            eprintln!(
+                "{} {} files in '{}'",
+                "Snapshot:".bold(),
+                snapshot.entries.len(),
+                path.display()
+            );
+        }
+        None => print!("{}", manifest),
+    }
+    0
+}
 
-    println!("\n{} {}", "File:".bold(), file_path.cyan());
+/// Runs `diff`, returning the exit code.
+fn diff_snapshot(args: &DiffArgs) -> i32 {
+    let snapshot = match fs::read_to_string(&args.manifest).and_then(|text| Snapshot::parse(&text))
+    {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            eprintln!(
+                "{}: Cannot read manifest '{}': {}",
+                "Error".red(),
+                args.manifest.display(),
+                e
+            );
+            return 2;
+        }
+    };
+    let drift = match snapshot.diff(&args.dir, args.jobs, args.trust_mtime) {
+        Ok(drift) => drift,
+        Err(e) => {
+            eprintln!(
+                "{}: Cannot compare '{}': {}",
+                "Error".red(),
+                args.dir.display(),
+                e
+            );
+            return 2;
+        }
+    };
 
-    if algorithm == Algorithm::All {
-        let algorithms = vec![
-            (Algorithm::Sha256, HashAlgorithm::SHA256),
-            (Algorithm::Md5, HashAlgorithm::MD5),
-            (Algorithm::Sha1, HashAlgorithm::SHA1),
-        ];
-
-        for (algo, hash_algo) in algorithms {
-            match hash_file(path, hash_algo) {
-                Ok(hash) => {
-                    let algo_name = format!("{:?}", algo).to_uppercase();
-                    println!("  {}: {}", algo_name.green().bold(), hash);
-                }
-                Err(e) => {
-                    eprintln!("  {}: Failed to compute {:?} - {}", "Error".red(), algo, e);
-                }
+    let changed = |pairs: &[(SnapshotEntry, SnapshotEntry)]| -> Vec<Value> {
+        pairs
+            .iter()
+            .map(|(before, after)| json!({ "before": before.to_json(), "after": after.to_json() }))
+            .collect()
+    };
+    match args.format {
+        ReportFormat::Json => println!(
+            "{}",
+            json!({
+                "algorithm": snapshot.algorithm.name(),
+                "clean": drift.is_clean(),
+                "added": drift.added.iter().map(SnapshotEntry::to_json).collect::<Vec<_>>(),
+                "removed": drift.removed.iter().map(SnapshotEntry::to_json).collect::<Vec<_>>(),
+                "modified": changed(&drift.modified),
+                "renamed": changed(&drift.renamed),
+            })
+        ),
+        ReportFormat::Text => {
+            for entry in &drift.added {
+                println!("{}: {}", entry.path, "ADDED".yellow().bold());
             }
-        }
-    } else {
-        match hash_file(path, algorithm.into()) {
-            Ok(hash) => {
-                let algo_name = format!("{:?}", algorithm).to_uppercase();
-                println!("  {}: {}", algo_name.green().bold(), hash);
+            for entry in &drift.removed {
+                println!("{}: {}", entry.path, "REMOVED".red().bold());
             }
-            Err(e) => {
-                eprintln!("  {}: Failed to compute hash - {}", "Error".red(), e);
+            for (before, _) in &drift.modified {
+                println!("{}: {}", before.path, "MODIFIED".red().bold());
+            }
+            for (before, after) in &drift.renamed {
+                println!(
+                    "{} -> {}: {}",
+                    before.path,
+                    after.path,
+                    "RENAMED".yellow().bold()
+                );
             }
+            println!(
+                "\n{} {} ADDED, {} REMOVED, {} MODIFIED, {} RENAMED",
+                "Summary:".bold(),
+                drift.added.len(),
+                drift.removed.len(),
+                drift.modified.len(),
+                drift.renamed.len()
+            );
         }
     }
 
-    if let Some(pb) = pb {
-        pb.finish_and_clear();
+    if drift.is_clean() {
+        0
+    } else {
+        1
     }
 }
 
-fn verify_file(file_path: &str, expected_hash: &str, algorithm: Algorithm) {
-    let path = Path::new(file_path);
-
-    if !path.exists() {
-        eprintln!("{}: File '{}' not found", "Error".red(), file_path);
-        std::process::exit(1);
+/// Runs `dupes`, returning the exit code.
+fn find_dupes(args: &DupesArgs) -> i32 {
+    if args.algorithm == Algorithm::All {
+        usage_error("dupes needs a single --algorithm");
+    }
+    if args.confirm && !(args.hardlink || args.delete) {
+        usage_error("--confirm only applies to --hardlink and --delete");
+    }
+    let algorithm = HashAlgorithm::from(args.algorithm);
+    // Files are only ever compared by digest, and checksums like CRC32 can
+    // be made to collide on purpose.
+    if !algorithm.is_cryptographic() && (args.hardlink || args.delete || args.script.is_some()) {
+        usage_error(&format!(
+            "{} is not a cryptographic hash; --hardlink, --delete and --script need one",
+            algorithm.name()
+        ));
     }
+    let options = WalkOptions {
+        include: args.include.clone(),
+        exclude: args.exclude.clone(),
+        gitignore: args.gitignore,
+        ..Default::default()
+    };
 
-    let hash_algo = if algorithm == Algorithm::All {
-        // Try to detect hash type by length
-        match expected_hash.len() {
-            32 => HashAlgorithm::MD5,
-            40 => HashAlgorithm::SHA1,
-            64 => HashAlgorithm::SHA256,
-            _ => {
-                eprintln!(
-                    "{}: Cannot determine hash algorithm from hash length",
-                    "Error".red()
-                );
-                std::process::exit(1);
+    let mut had_errors = false;
+    let mut files = Vec::new();
+    for root in &args.paths {
+        if !root.is_dir() {
+            files.push(root.clone());
+            continue;
+        }
+        match walk(root, &options) {
+            Ok((entries, errors)) => {
+                for e in errors {
+                    eprintln!("{}: {}", "Error".red(), e);
+                    had_errors = true;
+                }
+                files.extend(entries.into_iter().map(|entry| entry.path().to_path_buf()));
+            }
+            Err(e) => {
+                eprintln!("{}: Cannot walk '{}': {}", "Error".red(), root.display(), e);
+                return 1;
             }
         }
-    } else {
-        algorithm.into()
+    }
+
+    let dupe_options = DupeOptions {
+        prefix_len: args.prefix_size,
+        min_size: args.min_size,
     };
+    let scan = find_duplicates(&files, algorithm, &dupe_options, args.jobs);
+    for e in &scan.errors {
+        eprintln!("{}: {}", "Error".red(), e);
+        had_errors = true;
+    }
 
-    match hash_file(path, hash_algo) {
-        Ok(computed_hash) => {
-            if computed_hash.eq_ignore_ascii_case(expected_hash) {
+    let label = algorithm_label(algorithm, false);
+    match args.format {
+        ReportFormat::Json => {
+            let sets: Vec<Value> = scan
+                .sets
+                .iter()
+                .map(|set| {
+                    json!({
+                        "size": set.size,
+                        "digest": set.digest.to_hex(),
+                        "wasted_bytes": set.wasted_bytes(),
+                        "paths": set.paths.iter().map(|p| p.to_string_lossy()).collect::<Vec<_>>(),
+                    })
+                })
+                .collect();
+            println!(
+                "{}",
+                json!({
+                    "algorithm": label,
+                    "files": scan.files,
+                    "prefix_hashed": scan.prefix_hashed,
+                    "fully_hashed": scan.fully_hashed,
+                    "wasted_bytes": scan.wasted_bytes(),
+                    "sets": sets,
+                    "errors": scan.errors.iter().map(|e| e.to_string()).collect::<Vec<_>>(),
+                })
+            );
+        }
+        ReportFormat::Text => {
+            for set in &scan.sets {
                 println!(
-                    "{} Hash verification for '{}'",
+                    "\n{} {} copies of {} bytes, {} bytes wasted",
+                    "Duplicates:".bold(),
+                    set.paths.len(),
+                    set.size,
+                    set.wasted_bytes()
+                );
+                println!("  {}: {}", label.green().bold(), set.digest);
+                for path in &set.paths {
+                    println!("  {}", path.display().to_string().cyan());
+                }
+            }
+            println!(
+                "\n{} {} duplicate sets, {} bytes wasted ({} files, {} prefix hashes, {} full hashes)",
+                "Summary:".bold(),
+                scan.sets.len(),
+                scan.wasted_bytes(),
+                scan.files,
+                scan.prefix_hashed,
+                scan.fully_hashed
+            );
+        }
+    }
+
+    if let Some(path) = &args.script {
+        if let Err(e) = dupes_script(&scan.sets).and_then(|script| fs::write(path, script)) {
+            eprintln!(
+                "{}: Cannot write '{}': {}",
+                "Error".red(),
+                path.display(),
+                e
+            );
+            had_errors = true;
+        }
+    }
+    if args.hardlink || args.delete {
+        had_errors |= !remove_dupes(&scan.sets, algorithm, args);
+    }
+
+    if had_errors {
+        1
+    } else {
+        0
+    }
+}
+
+/// A shell script that deletes every copy but the first of each set. Paths
+/// that aren't UTF-8 fail it with `InvalidData`: sh has no portable way to
+/// spell them, and a lossy name could delete the wrong file.
+fn dupes_script(sets: &[DuplicateSet]) -> io::Result<String> {
+    let quote = |path: &Path| -> io::Result<String> {
+        let text = path.to_str().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("'{}' is not valid UTF-8", path.display()),
+            )
+        })?;
+        Ok(format!("'{}'", text.replace('\'', "'\\''")))
+    };
+    let mut script = String::from(
+        "#!/bin/sh\n\
+         # Written by file-hasher dupes. Deletes every copy but the first of each\n\
+         # set of duplicates; review before running.\n\
+         set -e\n",
+    );
+    for set in sets {
+        script += &format!(
+            "\n# {} copies of {} bytes ({} {})\n# keep {}\n",
+            set.paths.len(),
+            set.size,
+            set.digest.algorithm().name(),
+            set.digest,
+            // A line break would end the comment.
+            quote(&set.paths[0])?.replace('\n', "\\n")
+        );
+        for path in &set.paths[1..] {
+            script += &format!("rm -- {}\n", quote(path)?);
+        }
+    }
+    Ok(script)
+}
+
+/// Deletes or hard-links every copy but the first of each set, or only says
+/// what it would do without --confirm. Returns false if anything failed.
+fn remove_dupes(sets: &[DuplicateSet], algorithm: HashAlgorithm, args: &DupesArgs) -> bool {
+    let verb = if args.hardlink { "link" } else { "delete" };
+    // Keep stdout parseable in JSON mode.
+    let report = |message: String| match args.format {
+        ReportFormat::Json => eprintln!("{}", message),
+        ReportFormat::Text => println!("{}", message),
+    };
+    if !args.confirm {
+        for set in sets {
+            for path in &set.paths[1..] {
+                report(format!("Would {} '{}'", verb, path.display()));
+            }
+        }
+        report(format!(
+            "{}: nothing was changed; add --confirm to {} these files",
+            "Note".yellow(),
+            verb
+        ));
+        return true;
+    }
+
+    let mut ok = true;
+    for set in sets {
+        // Either file may have changed since it was hashed.
+        let unchanged = |p: &Path| hash_file(p, algorithm).is_ok_and(|d| d == set.digest);
+        let keep = &set.paths[0];
+        let keep_unchanged = unchanged(keep);
+        for path in &set.paths[1..] {
+            let result = if !keep_unchanged || !unchanged(path) {
+                Err(io::Error::other("changed since it was hashed"))
+            } else if args.hardlink {
+                replace_with_hard_link(keep, path)
+            } else {
+                fs::remove_file(path)
+            };
+            match result {
+                Ok(()) if args.hardlink => report(format!(
+                    "Linked '{}' to '{}'",
+                    path.display(),
+                    keep.display()
+                )),
+                Ok(()) => report(format!("Deleted '{}'", path.display())),
+                Err(e) => {
+                    eprintln!(
+                        "{}: Cannot {} '{}': {}",
+                        "Error".red(),
+                        verb,
+                        path.display(),
+                        e
+                    );
+                    ok = false;
+                }
+            }
+        }
+    }
+    ok
+}
+
+/// Replaces `path` with a hard link to `target`. The link is made under a
+/// temporary name first and renamed over `path`, so `path` never goes
+/// missing.
+fn replace_with_hard_link(target: &Path, path: &Path) -> io::Result<()> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp = path.with_file_name(format!(".{}.file-hasher-link", name));
+    fs::hard_link(target, &temp)?;
+    fs::rename(&temp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&temp);
+    })
+}
+
+fn expand_directories(
+    files: &[String],
+    options: &WalkOptions,
+    had_errors: &mut bool,
+) -> Vec<Input> {
+    let mut inputs = Vec::new();
+
+    for file in files {
+        let root = Path::new(file);
+        if !root.is_dir() {
+            inputs.push(Input::File(root.to_path_buf()));
+            continue;
+        }
+
+        let (entries, errors) = match walk(root, options) {
+            Ok(walked) => walked,
+            Err(e) => {
+                eprintln!("{}: Cannot walk '{}': {}", "Error".red(), file, e);
+                std::process::exit(1);
+            }
+        };
+        for e in errors {
+            eprintln!("{}: {}", "Error".red(), e);
+            *had_errors = true;
+        }
+        inputs.extend(entries.into_iter().filter_map(|entry| match entry {
+            WalkEntry::File(path) => Some(Input::File(path)),
+            WalkEntry::Symlink { path, target } => Some(Input::LinkTarget { path, target }),
+            WalkEntry::Dir(_) => None,
+        }));
+    }
+
+    inputs
+}
+
+/// Validates `--length` for `algorithm`, returning the output length in bytes.
+fn output_len_for(algorithm: Algorithm, bits: usize) -> usize {
+    let algorithm = match algorithm {
+        Algorithm::All => None,
+        algorithm => Some(HashAlgorithm::from(algorithm)),
+    };
+    let Some(algorithm) = algorithm.filter(HashAlgorithm::has_variable_len) else {
+        Args::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--length only applies to shake128, shake256, blake2b and blake2s",
+            )
+            .exit();
+    };
+    if !bits.is_multiple_of(8) || !algorithm.output_len_range().contains(&(bits / 8)) {
+        let range = algorithm.output_len_range();
+        Args::command()
+            .error(
+                ErrorKind::InvalidValue,
+                format!(
+                    "--length must be a multiple of 8 between 8 and {} for {}, got {}",
+                    range.end().saturating_mul(8),
+                    algorithm.name(),
+                    bits
+                ),
+            )
+            .exit();
+    }
+    bits / 8
+}
+
+/// The hasher requested by `--key-file`, `--derive-key`, `--salt` or
+/// `--personalization`, if any.
+fn mode_hasher(args: &Args, algorithm: Algorithm, output_len: Option<usize>) -> Option<Hasher> {
+    if let Some(key) = hmac_key(args) {
+        let hmac = match algorithm {
+            Algorithm::All => None,
+            algorithm => Hmac::new(algorithm.into(), &key).ok(),
+        };
+        let Some(hmac) = hmac else {
+            usage_error("HMAC requires --algorithm sha1 or one of the SHA-2 algorithms");
+        };
+        return Some(hmac.into());
+    }
+
+    let blake2 = matches!(algorithm, Algorithm::Blake2b | Algorithm::Blake2s);
+    if (args.salt.is_some() || args.personalization.is_some()) && !blake2 {
+        usage_error("--salt and --personalization require --algorithm blake2b or blake2s");
+    }
+    if args.derive_key.is_some() && algorithm != Algorithm::Blake3 {
+        usage_error("--derive-key requires --algorithm blake3");
+    }
+    if args.key_file.is_some() && !blake2 && algorithm != Algorithm::Blake3 {
+        usage_error("--key-file requires --algorithm blake3, blake2b or blake2s");
+    }
+
+    if let Some(context) = &args.derive_key {
+        return Some(Hasher::blake3_derive_key(context));
+    }
+
+    let key = args.key_file.as_deref().map(read_key_file);
+    if blake2 {
+        if key.is_none() && args.salt.is_none() && args.personalization.is_none() {
+            return None;
+        }
+        let options = Blake2Options {
+            output_len,
+            key: key.unwrap_or_default(),
+            salt: args.salt.as_deref().map(hex_arg).unwrap_or_default(),
+            personalization: args
+                .personalization
+                .as_deref()
+                .map(hex_arg)
+                .unwrap_or_default(),
+        };
+        return match Hasher::blake2(algorithm.into(), &options) {
+            Ok(hasher) => Some(hasher),
+            Err(e) => {
+                eprintln!("{}: {}", "Error".red(), e);
+                std::process::exit(1);
+            }
+        };
+    }
+
+    let key = key?;
+    match <[u8; 32]>::try_from(key.as_slice()) {
+        Ok(key) => Some(Hasher::blake3_keyed(&key)),
+        Err(_) => {
+            eprintln!(
+                "{}: BLAKE3 keys must be exactly 32 bytes, found {}",
+                "Error".red(),
+                key.len()
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+/// The key given with `--hmac-key-file` or `--hmac-key-env`, if any. The key
+/// itself never appears in messages.
+fn hmac_key(args: &Args) -> Option<Vec<u8>> {
+    let key = if let Some(path) = &args.hmac_key_file {
+        read_key_file(path)
+    } else {
+        let var = args.hmac_key_env.as_ref()?;
+        match std::env::var_os(var) {
+            Some(value) => value.into_encoded_bytes(),
+            None => {
+                eprintln!(
+                    "{}: Environment variable '{}' is not set",
+                    "Error".red(),
+                    var
+                );
+                std::process::exit(1);
+            }
+        }
+    };
+    if key.is_empty() {
+        eprintln!("{}: The HMAC key is empty", "Error".red());
+        std::process::exit(1);
+    }
+    Some(key)
+}
+
+/// The cache to hash through, if it is turned on.
+fn hash_cache(args: &Args) -> Option<HashCache> {
+    let from_env = std::env::var("FILE_HASHER_CACHE").is_ok_and(|v| !v.is_empty() && v != "0");
+    let wanted = args.cache || args.refresh_cache || args.cache_dir.is_some() || from_env;
+    if args.no_cache || !wanted {
+        return None;
+    }
+    let Some(dir) = args.cache_dir.clone().or_else(HashCache::default_dir) else {
+        eprintln!(
+            "{}: No cache directory (set XDG_CACHE_HOME or use --cache-dir); not caching",
+            "Warning".yellow()
+        );
+        return None;
+    };
+    match HashCache::open(&dir) {
+        Ok(cache) => Some(cache.with_refresh(args.refresh_cache)),
+        Err(e) => {
+            eprintln!(
+                "{}: Cannot use cache '{}': {}; not caching",
+                "Warning".yellow(),
+                dir.display(),
+                e
+            );
+            None
+        }
+    }
+}
+
+/// Runs `prune-cache`, returning the exit code.
+fn prune_cache(args: &PruneCacheArgs) -> i32 {
+    let Some(dir) = args.cache_dir.clone().or_else(HashCache::default_dir) else {
+        usage_error("no cache directory (set XDG_CACHE_HOME or use --cache-dir)");
+    };
+    let max_age = args
+        .max_age
+        .map(|days| Duration::from_secs(days.saturating_mul(24 * 60 * 60)));
+    match HashCache::open(&dir).and_then(|cache| cache.prune(max_age)) {
+        Ok(stats) => {
+            println!(
+                "{} removed {} entries, kept {}",
+                "Pruned:".bold(),
+                stats.removed,
+                stats.kept
+            );
+            0
+        }
+        Err(e) => {
+            eprintln!(
+                "{}: Cannot prune cache '{}': {}",
+                "Error".red(),
+                dir.display(),
+                e
+            );
+            1
+        }
+    }
+}
+
+fn read_options(args: &Args) -> ReadOptions {
+    ReadOptions {
+        strategy: args.io.into(),
+        buffer_size: usize::try_from(args.buffer_size).unwrap_or(usize::MAX),
+        range: byte_range(args),
+    }
+}
+
+/// The part of each file selected by --offset/--range-length, --head or
+/// --tail, if any.
+fn byte_range(args: &Args) -> Option<ByteRange> {
+    if let Some(n) = args.tail {
+        return Some(ByteRange::Tail(n));
+    }
+    if let Some(n) = args.head {
+        return Some(ByteRange::head(n));
+    }
+    if args.offset.is_none() && args.range_length.is_none() {
+        return None;
+    }
+    Some(ByteRange::Slice {
+        offset: args.offset.unwrap_or(0),
+        len: args.range_length,
+    })
+}
+
+/// Parses a byte count with an optional binary suffix: `4096`, `64K`, `4M`,
+/// `1G`.
+fn parse_size(s: &str) -> Result<u64, String> {
+    let (digits, multiplier) = match s.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&s[..i], 1 << 10),
+        Some((i, 'm' | 'M')) => (&s[..i], 1 << 20),
+        Some((i, 'g' | 'G')) => (&s[..i], 1 << 30),
+        _ => (s, 1),
+    };
+    let value: u64 = digits
+        .parse()
+        .map_err(|_| format!("'{}' is not a size like 4096, 64K or 4M", s))?;
+    value
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("'{}' is too large", s))
+}
+
+fn parse_buffer_size(s: &str) -> Result<u64, String> {
+    match parse_size(s)? {
+        0 => Err("size must be greater than zero".to_string()),
+        size => Ok(size),
+    }
+}
+
+/// How an algorithm is labelled in output, e.g. `HMAC-SHA256` for tags.
+fn algorithm_label(algorithm: HashAlgorithm, hmac: bool) -> Cow<'static, str> {
+    if hmac {
+        Cow::Owned(format!("HMAC-{}", algorithm.name()))
+    } else {
+        Cow::Borrowed(algorithm.name())
+    }
+}
+
+fn usage_error(message: &str) -> ! {
+    Args::command()
+        .error(ErrorKind::ArgumentConflict, message)
+        .exit()
+}
+
+fn read_key_file(path: &Path) -> Vec<u8> {
+    match fs::read(path) {
+        Ok(key) => key,
+        Err(e) => {
+            eprintln!(
+                "{}: Cannot read key file '{}': {}",
+                "Error".red(),
+                path.display(),
+                e
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+fn hex_arg(value: &str) -> Vec<u8> {
+    match hex::decode(value) {
+        Ok(bytes) => bytes,
+        Err(e) => Args::command()
+            .error(
+                ErrorKind::InvalidValue,
+                format!("'{}' is not valid hex: {}", value, e),
+            )
+            .exit(),
+    }
+}
+
+/// Fresh hashers for every digest to compute. `all` leaves out the
+/// extendable-output functions, whose length is arbitrary, and the
+/// non-cryptographic checksums.
+fn hashers_for(algorithm: Algorithm, output_len: Option<usize>) -> Vec<Hasher> {
+    if algorithm == Algorithm::All {
+        return HashAlgorithm::VARIANTS
+            .into_iter()
+            .filter(|a| !a.is_xof() && a.is_cryptographic())
+            .map(Hasher::new)
+            .collect();
+    }
+
+    let algorithm = HashAlgorithm::from(algorithm);
+    match output_len {
+        Some(len) => vec![Hasher::with_output_len(algorithm, len)
+            .expect("--length is validated against the algorithm")],
+        None => vec![Hasher::new(algorithm)],
+    }
+}
+
+/// Hashes the path a symbolic link points to rather than the data behind it.
+fn link_target_record(path: &Path, target: &Path, hashers: &[Hasher]) -> FileRecord {
+    let start = Instant::now();
+    let target = target.as_os_str().as_encoded_bytes();
+    let digests = hashers
+        .iter()
+        .map(|hasher| {
+            let mut hasher = hasher.clone();
+            hasher.update(target);
+            hasher.finalize()
+        })
+        .collect();
+
+    FileRecord {
+        path: path.display().to_string(),
+        size: Some(target.len() as u64),
+        mtime: None,
+        digests,
+        hmac: hashers.iter().any(Hasher::is_hmac),
+        block_size: None,
+        blocks: Vec::new(),
+        merkle: None,
+        elapsed: start.elapsed(),
+        error: None,
+    }
+}
+
+/// Hashes `reader` with `hasher` block by block, filling in the full digest
+/// and the digest of each block.
+fn hash_blocks<R: Read>(
+    reader: R,
+    hasher: &Hasher,
+    block_size: u64,
+    record: &mut FileRecord,
+) -> io::Result<()> {
+    let mut blocks = Blocks::new(reader, hasher.clone(), block_size);
+    record.blocks = blocks.by_ref().collect::<io::Result<_>>()?;
+    record.digests = vec![blocks.full_digest()];
+    Ok(())
+}
+
+/// Fills in the root of `tree`, and the proof for leaf `proof` if asked.
+fn merkle_record(
+    tree: MerkleTree,
+    proof: Option<usize>,
+    record: &mut FileRecord,
+) -> io::Result<()> {
+    let proof = match proof {
+        Some(index) => Some(tree.proof(index).ok_or_else(|| {
+            io::Error::other(format!(
+                "no Merkle leaf #{} (the file has {})",
+                index,
+                tree.leaf_count()
+            ))
+        })?),
+        None => None,
+    };
+    record.digests = vec![tree.root().clone()];
+    record.merkle = Some(MerkleInfo {
+        options: *tree.options(),
+        leaf_count: tree.leaf_count(),
+        proof,
+    });
+    Ok(())
+}
+
+fn hash_file_record(
+    path: &Path,
+    hashers: &[Hasher],
+    read_options: &ReadOptions,
+    layout: Layout,
+    cache: Option<&HashCache>,
+    xattrs: XattrOptions,
+    show_progress: bool,
+) -> FileRecord {
+    let start = Instant::now();
+    let mut record = FileRecord {
+        path: path.display().to_string(),
+        size: None,
+        mtime: None,
+        digests: Vec::new(),
+        hmac: hashers.iter().any(Hasher::is_hmac),
+        block_size: match layout {
+            Layout::Blocks(block_size) => Some(block_size),
+            _ => None,
+        },
+        blocks: Vec::new(),
+        merkle: None,
+        elapsed: Duration::ZERO,
+        error: None,
+    };
+
+    if path == Path::new(STDIN_PATH) {
+        let mut stdin = ProgressReader::new(io::stdin().lock(), |_| {});
+        let result = match layout {
+            Layout::Whole => hash_reader_with(&mut stdin, hashers.to_vec())
+                .map(|digests| record.digests = digests),
+            Layout::Blocks(block_size) => {
+                hash_blocks(&mut stdin, &hashers[0], block_size, &mut record)
+            }
+            Layout::Merkle { options, proof, .. } => {
+                MerkleTree::from_reader(&mut stdin, hashers[0].clone(), &options)
+                    .and_then(|tree| merkle_record(tree, proof, &mut record))
+            }
+        };
+        if let Err(e) = result {
+            record.error = Some(e);
+        }
+        record.size = Some(stdin.bytes_read());
+        record.elapsed = start.elapsed();
+        return record;
+    }
+
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            record.error = Some(e);
+            return record;
+        }
+    };
+    if metadata.is_dir() {
+        record.error = Some(io::Error::other("is a directory (use --recursive)"));
+        return record;
+    }
+    let file_size = metadata.len();
+    record.size = Some(file_size);
+    record.mtime = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs());
+
+    let show_progress = show_progress && file_size > 10 * 1024 * 1024; // Show progress for files > 10MB
+    let pb = if show_progress {
+        let pb = ProgressBar::new(file_size);
+        pb.set_style(
+            ProgressStyle::default_bar()
+                .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta}) {msg}")
+                .unwrap()
+                .progress_chars("##-"),
+        );
+        pb.set_message(format!(
+            "Hashing {}",
+            path.file_name().unwrap().to_string_lossy()
+        ));
+        pb.enable_steady_tick(Duration::from_millis(100));
+        Some(pb)
+    } else {
+        None
+    };
+
+    let on_progress = |progress: &Progress| {
+        if let Some(pb) = &pb {
+            pb.set_position(progress.bytes);
+        }
+    };
+    let result = match layout {
+        Layout::Whole => {
+            let digests = if xattrs.read || xattrs.write {
+                hash_file_with_xattrs(path, hashers.to_vec(), read_options, xattrs, on_progress)
+            } else if let Some(cache) = cache {
+                cache.hash_file_with_options(path, hashers.to_vec(), read_options, on_progress)
+            } else {
+                hash_file_with_options(path, hashers.to_vec(), read_options, on_progress)
+            };
+            digests.map(|digests| record.digests = digests)
+        }
+        Layout::Blocks(block_size) => FileReader::open(path, read_options).and_then(|file| {
+            let reader = ProgressReader::new(file, on_progress);
+            hash_blocks(reader, &hashers[0], block_size, &mut record)
+        }),
+        Layout::Merkle {
+            options,
+            proof,
+            jobs,
+        } => MerkleTree::from_file_with_options(
+            path,
+            hashers[0].clone(),
+            &options,
+            jobs,
+            read_options,
+            on_progress,
+        )
+        .and_then(|tree| merkle_record(tree, proof, &mut record)),
+    };
+
+    if let Some(pb) = pb {
+        pb.finish_and_clear();
+    }
+
+    if let Err(e) = result {
+        record.error = Some(e);
+    }
+    record.elapsed = start.elapsed();
+    record
+}
+
+fn display_record(record: &FileRecord, format: OutputFormat) {
+    if let Some(e) = &record.error {
+        if e.kind() == io::ErrorKind::NotFound {
+            eprintln!("{}: File '{}' not found", "Error".red(), record.path);
+        } else {
+            eprintln!(
+                "{}: Failed to compute hash for '{}' - {}",
+                "Error".red(),
+                record.path,
+                e
+            );
+        }
+        return;
+    }
+
+    match format {
+        OutputFormat::Gnu => {
+            for hash in &record.digests {
+                println!("{}", format_gnu_line(hash, &record.path));
+            }
+        }
+        OutputFormat::Bsd => {
+            for hash in &record.digests {
+                println!("{}", format_bsd_line(hash, &record.path));
+            }
+        }
+        _ => {
+            println!("\n{} {}", "File:".bold(), record.path.cyan());
+            for hash in &record.digests {
+                let mut label = algorithm_label(hash.algorithm(), record.hmac);
+                if record.merkle.is_some() {
+                    label += " Merkle root";
+                }
+                println!("  {}: {}", label.green().bold(), hash);
+            }
+            if let Some(merkle) = &record.merkle {
+                println!(
+                    "  {}: {} of {} bytes, fan-out {}",
+                    "Leaves".bold(),
+                    merkle.leaf_count,
+                    merkle.options.leaf_size,
+                    merkle.options.fan_out
+                );
+                if let Some(proof) = &merkle.proof {
+                    println!(
+                        "  {} for leaf #{} @{}:",
+                        "Proof".bold(),
+                        proof.leaf_index,
+                        proof
+                            .leaf_offset()
+                            .expect("leaves of a built tree lie within the file")
+                    );
+                    for (level, step) in proof.path.iter().enumerate() {
+                        let siblings: Vec<String> =
+                            step.siblings.iter().map(Digest::to_hex).collect();
+                        println!(
+                            "    level {} position {}: {}",
+                            level,
+                            step.position,
+                            siblings.join(" ")
+                        );
+                    }
+                }
+            }
+            if let Some(block_size) = record.block_size {
+                println!("  {} ({} bytes each):", "Blocks".bold(), block_size);
+                for (index, block) in record.blocks.iter().enumerate() {
+                    println!("    #{} @{}: {}", index, block.offset, block.digest);
+                }
+            }
+        }
+    }
+}
+
+/// Structured form of a verification, shared by `--verify` and `--check`.
+fn verification_json(
+    path: &str,
+    expected: &Digest,
+    computed: Option<&Digest>,
+    status: &str,
+    error: Option<&io::Error>,
+    hmac: bool,
+) -> Value {
+    let mut record = json!({
+        "path": path,
+        "algorithm": algorithm_label(expected.algorithm(), hmac),
+        "expected": expected.to_hex(),
+        "computed": computed.map(Digest::to_hex),
+        "match": computed == Some(expected),
+        "status": status,
+    });
+    if let Some(e) = error {
+        record["error"] = Value::from(e.to_string());
+    }
+    record
+}
+
+fn verify_file(
+    file_path: &str,
+    expected_hash: &str,
+    algorithm: Option<Algorithm>,
+    output_len: Option<usize>,
+    hasher: Option<Hasher>,
+    read_options: &ReadOptions,
+    format: OutputFormat,
+) {
+    let path = Path::new(file_path);
+    let hmac = hasher.as_ref().is_some_and(Hasher::is_hmac);
+
+    if file_path != STDIN_PATH && !path.exists() && !format.is_json() {
+        eprintln!("{}: File '{}' not found", "Error".red(), file_path);
+        std::process::exit(1);
+    }
+
+    // Without an explicit algorithm, detect the hash type by its length
+    let detected = matches!(algorithm, None | Some(Algorithm::All));
+    let expected = match algorithm {
+        Some(algorithm) if !detected => Digest::from_hex(algorithm.into(), expected_hash),
+        _ => expected_hash.parse::<Digest>(),
+    };
+    let expected = match expected {
+        Ok(digest) => digest,
+        Err(e) => {
+            eprintln!(
+                "{}: Invalid hash '{}' - {}",
+                "Error".red(),
+                expected_hash,
+                e
+            );
+            std::process::exit(1);
+        }
+    };
+    // Variable-length digests take their length from the expected hash
+    // unless --length or a configured hasher pins it down.
+    if let Some(len) = hasher.as_ref().map(Hasher::output_len).or(output_len) {
+        if expected.as_bytes().len() != len {
+            eprintln!(
+                "{}: Hash '{}' is {} bits but the selected digest is {} bits",
+                "Error".red(),
+                expected_hash,
+                expected.as_bytes().len() * 8,
+                len * 8
+            );
+            std::process::exit(1);
+        }
+    }
+
+    // A digest whose algorithm was only guessed from its length may also
+    // belong to another algorithm of the same size, e.g. SHA-512/256.
+    let result = match (hasher, file_path == STDIN_PATH) {
+        (Some(hasher), true) => verify_reader_with(&mut io::stdin().lock(), &expected, hasher),
+        (None, true) => verify_reader(&mut io::stdin().lock(), &expected, detected),
+        (hasher, false) => verify_file_using(path, &expected, hasher, detected, read_options),
+    };
+    if let Ok(verification) = &result {
+        warn_if_not_cryptographic(verification.expected.algorithm());
+    }
+
+    if format.is_json() {
+        let record = match &result {
+            Ok(verification) => {
+                let status = if verification.is_match() {
+                    "OK"
+                } else {
+                    "FAILED"
+                };
+                verification_json(
+                    file_path,
+                    &verification.expected,
+                    Some(&verification.computed),
+                    status,
+                    None,
+                    hmac,
+                )
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                verification_json(file_path, &expected, None, "MISSING", Some(e), hmac)
+            }
+            Err(e) => verification_json(file_path, &expected, None, "FAILED", Some(e), hmac),
+        };
+        if format == OutputFormat::Json {
+            println!("{}", Value::Array(vec![record]));
+        } else {
+            println!("{}", record);
+        }
+        if !matches!(&result, Ok(verification) if verification.is_match()) {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    match result {
+        Ok(verification) => {
+            if verification.is_match() {
+                println!(
+                    "{} Hash verification for '{}'",
                     "✓ MATCH:".green().bold(),
                     file_path
                 );
-                println!("  Expected: {}", expected_hash);
-                println!("  Computed: {}", computed_hash);
             } else {
                 println!(
                     "{} Hash verification for '{}'",
                     "✗ MISMATCH:".red().bold(),
                     file_path
                 );
-                println!("  Expected: {}", expected_hash);
-                println!("  Computed: {}", computed_hash);
+            }
+            if detected {
+                println!(
+                    "  Algorithm: {}",
+                    algorithm_label(verification.expected.algorithm(), hmac)
+                );
+            }
+            println!("  Expected: {}", expected_hash);
+            println!("  Computed: {}", verification.computed);
+            if !verification.is_match() {
                 std::process::exit(1);
             }
         }
@@ -194,3 +2116,768 @@ fn verify_file(file_path: &str, expected_hash: &str, algorithm: Algorithm) {
         }
     }
 }
+
+/// Checksums like CRC32 only catch accidental corruption; say so when one is
+/// used to check integrity.
+fn warn_if_not_cryptographic(algorithm: HashAlgorithm) {
+    if !algorithm.is_cryptographic() {
+        eprintln!(
+            "{}: {} is not a cryptographic hash; a match does not rule out deliberate tampering",
+            "Warning".yellow(),
+            algorithm.name()
+        );
+    }
+}
+
+fn check_files(
+    checksum_file: &str,
+    algorithm: Option<HashAlgorithm>,
+    hmac_key: Option<&[u8]>,
+    read_options: &ReadOptions,
+    use_xattr: bool,
+    format: OutputFormat,
+) -> i32 {
+    let entries = if checksum_file == STDIN_PATH {
+        parse_checksum_file(io::stdin().lock(), algorithm)
+    } else {
+        File::open(checksum_file)
+            .and_then(|file| parse_checksum_file(BufReader::new(file), algorithm))
+    };
+    let entries = match entries {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!(
+                "{}: Cannot read checksum file '{}': {}",
+                "Error".red(),
+                checksum_file,
+                e
+            );
+            return 2;
+        }
+    };
+
+    let (mut ok, mut failed, mut missing, mut malformed) = (0, 0, 0, 0);
+    let mut json_records = Vec::new();
+    let mut warned = HashSet::new();
+
+    for (line_number, entry) in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                eprintln!(
+                    "{}: {}:{}: {}",
+                    "Warning".yellow(),
+                    checksum_file,
+                    line_number,
+                    e
+                );
+                malformed += 1;
+                continue;
+            }
+        };
+
+        let stored = match hmac_key {
+            None if use_xattr => current_xattr(&entry.path, &entry.expected),
+            _ => None,
+        };
+        let status = match stored {
+            Some(stored) if stored == entry.expected => CheckStatus::Ok { computed: stored },
+            // A guessed algorithm may be the wrong one of several.
+            Some(computed) if !entry.algorithm_inferred => CheckStatus::Failed { computed },
+            _ => verify_entry_using(&entry, hmac_key, read_options),
+        };
+        // Warn about the algorithm that was actually used, not the guess.
+        let algorithm = match &status {
+            CheckStatus::Ok { computed } | CheckStatus::Failed { computed } => computed.algorithm(),
+            _ => entry.expected.algorithm(),
+        };
+        if warned.insert(algorithm) {
+            warn_if_not_cryptographic(algorithm);
+        }
+        let label = match &status {
+            CheckStatus::Ok { .. } => {
+                ok += 1;
+                "OK"
+            }
+            CheckStatus::Failed { .. } | CheckStatus::Unreadable(_) => {
+                failed += 1;
+                "FAILED"
+            }
+            CheckStatus::Missing => {
+                missing += 1;
+                "MISSING"
+            }
+        };
+
+        let name = entry.path.to_string_lossy();
+        if format.is_json() {
+            let (computed, error) = match &status {
+                CheckStatus::Ok { computed } => (Some(computed), None),
+                CheckStatus::Failed { computed } => (Some(computed), None),
+                CheckStatus::Unreadable(e) => (None, Some(e)),
+                CheckStatus::Missing => (None, None),
+            };
+            let expected = match &status {
+                CheckStatus::Ok { computed } => computed,
+                _ => &entry.expected,
+            };
+            let record =
+                verification_json(&name, expected, computed, label, error, hmac_key.is_some());
+            if format == OutputFormat::Json {
+                json_records.push(record);
+            } else {
+                println!("{}", record);
+            }
+            continue;
+        }
+
+        match &status {
+            CheckStatus::Ok { .. } => println!("{}: {}", name, label.green().bold()),
+            CheckStatus::Failed { .. } => println!("{}: {}", name, label.red().bold()),
+            CheckStatus::Missing => println!("{}: {}", name, label.yellow().bold()),
+            CheckStatus::Unreadable(e) => {
+                println!("{}: {} ({})", name, label.red().bold(), e)
+            }
+        }
+    }
+
+    if format == OutputFormat::Json {
+        println!("{}", Value::Array(json_records));
+    } else if !format.is_json() {
+        println!(
+            "\n{} {} OK, {} FAILED, {} MISSING",
+            "Summary:".bold(),
+            ok,
+            failed,
+            missing
+        );
+        if malformed > 0 {
+            println!("  {} improperly formatted line(s) ignored", malformed);
+        }
+    }
+
+    if ok + failed + missing == 0 {
+        eprintln!(
+            "{}: No properly formatted checksum lines found in '{}'",
+            "Error".red(),
+            checksum_file
+        );
+        2
+    } else if failed + missing > 0 {
+        1
+    } else {
+        0
+    }
+}
+
+/// The digest stored in `path`'s attribute for `expected`'s algorithm, if
+/// the file hasn't been modified since and it is comparable with `expected`.
+fn current_xattr(path: &Path, expected: &Digest) -> Option<Digest> {
+    let algorithm = expected.algorithm();
+    if expected.as_bytes().len() != algorithm.digest_len() {
+        return None;
+    }
+    let stored = read_xattr(path, algorithm).ok()??;
+    let metadata = fs::metadata(path).ok()?;
+    stored.is_current(&metadata).then_some(stored.digest)
+}
+
+/// One attribute compared by `--verify-xattr`.
+struct XattrCheck {
+    stored: StoredDigest,
+    computed: Digest,
+    /// The file was modified after the digest was stored.
+    modified: bool,
+}
+
+/// Runs `--verify-xattr`, returning the exit code.
+fn verify_xattrs(args: &Args, hashers: &[Hasher]) -> i32 {
+    let mut had_errors = false;
+    let inputs = if args.recursive {
+        expand_directories(&args.files, &walk_options(args), &mut had_errors)
+    } else {
+        args.files
+            .iter()
+            .map(|file| Input::File(file.into()))
+            .collect()
+    };
+    let files: Vec<&PathBuf> = inputs
+        .iter()
+        .filter_map(|input| match input {
+            Input::File(file) => Some(file),
+            Input::LinkTarget { .. } => None,
+        })
+        .collect();
+    let read_options = read_options(args);
+
+    let (mut ok, mut failed, mut modified, mut unchecked) = (0, 0, 0, 0);
+    let mut json_records = Vec::new();
+    for_each_ordered(
+        &files,
+        args.jobs,
+        |&file| (file, check_xattrs(file, hashers, &read_options)),
+        |(file, checks)| {
+            let (label, algorithms) = match &checks {
+                Err(_) => ("FAILED", Vec::new()),
+                Ok(checks) if checks.is_empty() => ("NO CHECKSUM", Vec::new()),
+                Ok(checks) => {
+                    let mismatched = |modified: bool| -> Vec<&str> {
+                        checks
+                            .iter()
+                            .filter(|c| c.computed != c.stored.digest && c.modified == modified)
+                            .map(|c| c.computed.algorithm().name())
+                            .collect()
+                    };
+                    let rotted = mismatched(false);
+                    let changed = mismatched(true);
+                    if !rotted.is_empty() {
+                        ("FAILED", rotted)
+                    } else if !changed.is_empty() {
+                        ("CHANGED", changed)
+                    } else {
+                        ("OK", Vec::new())
+                    }
+                }
+            };
+            match label {
+                "OK" => ok += 1,
+                "FAILED" => failed += 1,
+                "CHANGED" => modified += 1,
+                _ => unchecked += 1,
+            }
+
+            if args.format.is_json() {
+                let mut record = json!({ "path": file.to_string_lossy(), "status": label });
+                match &checks {
+                    Ok(checks) => {
+                        record["attributes"] = checks
+                            .iter()
+                            .map(|check| {
+                                json!({
+                                    "algorithm": check.computed.algorithm().name(),
+                                    "stored": check.stored.digest.to_hex(),
+                                    "computed": check.computed.to_hex(),
+                                    "match": check.computed == check.stored.digest,
+                                    "modified": check.modified,
+                                })
+                            })
+                            .collect();
+                    }
+                    Err(e) => record["error"] = Value::from(e.to_string()),
+                }
+                if args.format == OutputFormat::Json {
+                    json_records.push(record);
+                } else {
+                    println!("{}", record);
+                }
+                return;
+            }
+            let colored = match label {
+                "OK" => label.green().bold(),
+                "FAILED" => label.red().bold(),
+                _ => label.yellow().bold(),
+            };
+            match &checks {
+                Err(e) => println!("{}: {} ({})", file.display(), colored, e),
+                Ok(_) if algorithms.is_empty() => println!("{}: {}", file.display(), colored),
+                Ok(_) => println!(
+                    "{}: {} ({})",
+                    file.display(),
+                    colored,
+                    algorithms.join(", ")
+                ),
+            }
+        },
+    );
+
+    if args.format == OutputFormat::Json {
+        println!("{}", Value::Array(json_records));
+    } else if !args.format.is_json() {
+        println!(
+            "\n{} {} OK, {} FAILED, {} CHANGED, {} NO CHECKSUM",
+            "Summary:".bold(),
+            ok,
+            failed,
+            modified,
+            unchecked
+        );
+    }
+    if failed > 0 || had_errors {
+        1
+    } else {
+        0
+    }
+}
+
+/// Hashes `path` again with every hasher whose attribute it has, in one
+/// pass, and compares the digests with the stored ones.
+fn check_xattrs(
+    path: &Path,
+    hashers: &[Hasher],
+    read_options: &ReadOptions,
+) -> io::Result<Vec<XattrCheck>> {
+    let metadata = fs::metadata(path)?;
+    if metadata.is_dir() {
+        return Err(io::Error::other("is a directory (use --recursive)"));
+    }
+    let mut stored = Vec::new();
+    let mut wanted = Vec::new();
+    for hasher in hashers.iter().filter(|hasher| xattr::storable(hasher)) {
+        if let Some(digest) = read_xattr(path, hasher.algorithm())? {
+            stored.push(digest);
+            wanted.push(hasher.clone());
+        }
+    }
+    if wanted.is_empty() {
+        return Ok(Vec::new());
+    }
+    let computed = hash_file_with_options(path, wanted, read_options, |_| {})?;
+    Ok(stored
+        .into_iter()
+        .zip(computed)
+        .map(|(stored, computed)| XattrCheck {
+            modified: !stored.is_current(&metadata),
+            stored,
+            computed,
+        })
+        .collect())
+}
+
+/// One file of a block manifest, as written by `--block-size` with
+/// `--format json` or `ndjson`.
+struct ManifestEntry {
+    path: String,
+    algorithm: HashAlgorithm,
+    hmac: bool,
+    block_size: u64,
+    blocks: Vec<Block>,
+}
+
+fn parse_block_manifest(text: &str) -> Result<Vec<ManifestEntry>, String> {
+    let records: Vec<Value> = if text.trim_start().starts_with('[') {
+        serde_json::from_str(text).map_err(|e| e.to_string())?
+    } else {
+        text.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()
+            .map_err(|e| e.to_string())?
+    };
+
+    records.iter().map(parse_manifest_entry).collect()
+}
+
+fn parse_manifest_entry(record: &Value) -> Result<ManifestEntry, String> {
+    let path = record["path"]
+        .as_str()
+        .ok_or("record without a path")?
+        .to_string();
+    let block_size = record["block_size"]
+        .as_u64()
+        .filter(|&size| size > 0)
+        .ok_or_else(|| format!("'{}' has no block digests (see --block-size)", path))?;
+    let label = record["algorithms"][0]
+        .as_str()
+        .ok_or_else(|| format!("'{}' has no algorithm", path))?;
+    let (name, hmac) = match label.strip_prefix("HMAC-") {
+        Some(name) => (name, true),
+        None => (label, false),
+    };
+    let algorithm = HashAlgorithm::from_name(name)
+        .ok_or_else(|| format!("'{}' has unknown algorithm '{}'", path, label))?;
+
+    let blocks = record["blocks"]
+        .as_array()
+        .ok_or_else(|| format!("'{}' has no block digests (see --block-size)", path))?
+        .iter()
+        .map(|block| {
+            let offset = block["offset"].as_u64();
+            let len = block["length"].as_u64();
+            let digest = block["digest"]
+                .as_str()
+                .and_then(|hex| Digest::from_hex(algorithm, hex).ok());
+            match (offset, len, digest) {
+                (Some(offset), Some(len), Some(digest)) => Ok(Block {
+                    offset,
+                    len,
+                    digest,
+                }),
+                _ => Err(format!("'{}' has a malformed block: {}", path, block)),
+            }
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(ManifestEntry {
+        path,
+        algorithm,
+        hmac,
+        block_size,
+        blocks,
+    })
+}
+
+/// The hasher a manifest entry was written with. Keyed entries need the
+/// same key options, and algorithm, as when the manifest was written.
+fn manifest_hasher(entry: &ManifestEntry, mode_hasher: Option<&Hasher>) -> io::Result<Hasher> {
+    match mode_hasher {
+        Some(hasher) if hasher.algorithm() == entry.algorithm && hasher.is_hmac() == entry.hmac => {
+            Ok(hasher.clone())
+        }
+        Some(hasher) => Err(io::Error::other(format!(
+            "manifest uses {} but {} was selected",
+            algorithm_label(entry.algorithm, entry.hmac),
+            algorithm_label(hasher.algorithm(), hasher.is_hmac())
+        ))),
+        None if entry.hmac => Err(io::Error::other(
+            "manifest holds HMAC tags; pass --hmac-key-file or --hmac-key-env",
+        )),
+        None => match entry.blocks.first() {
+            Some(block) if entry.algorithm.has_variable_len() => {
+                Hasher::with_output_len(entry.algorithm, block.digest.as_bytes().len())
+            }
+            _ => Ok(Hasher::new(entry.algorithm)),
+        },
+    }
+}
+
+fn verify_block_manifest(
+    manifest: &Path,
+    mode_hasher: Option<&Hasher>,
+    read_options: &ReadOptions,
+    format: OutputFormat,
+) -> i32 {
+    let entries = fs::read_to_string(manifest)
+        .map_err(|e| e.to_string())
+        .and_then(|text| parse_block_manifest(&text));
+    let entries = match entries {
+        Ok(entries) if !entries.is_empty() => entries,
+        Ok(_) => {
+            eprintln!(
+                "{}: No entries found in block manifest '{}'",
+                "Error".red(),
+                manifest.display()
+            );
+            return 2;
+        }
+        Err(e) => {
+            eprintln!(
+                "{}: Cannot read block manifest '{}': {}",
+                "Error".red(),
+                manifest.display(),
+                e
+            );
+            return 2;
+        }
+    };
+
+    let (mut ok, mut failed, mut missing) = (0, 0, 0);
+    let mut json_records = Vec::new();
+
+    for entry in &entries {
+        let result = manifest_hasher(entry, mode_hasher).and_then(|hasher| {
+            let file = FileReader::open(Path::new(&entry.path), read_options)?;
+            diff_blocks(file, hasher, entry.block_size, &entry.blocks)
+        });
+        let status = match &result {
+            Ok(mismatches) if mismatches.is_empty() => {
+                ok += 1;
+                "OK"
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                missing += 1;
+                "MISSING"
+            }
+            _ => {
+                failed += 1;
+                "FAILED"
+            }
+        };
+
+        if format.is_json() {
+            let mut record = json!({
+                "path": entry.path,
+                "algorithm": algorithm_label(entry.algorithm, entry.hmac),
+                "block_size": entry.block_size,
+                "blocks": entry.blocks.len(),
+                "status": status,
+            });
+            match &result {
+                Ok(mismatches) => {
+                    record["mismatches"] = mismatches.iter().map(mismatch_json).collect()
+                }
+                Err(e) => record["error"] = Value::from(e.to_string()),
+            }
+            if format == OutputFormat::Json {
+                json_records.push(record);
+            } else {
+                println!("{}", record);
+            }
+            continue;
+        }
+
+        match &result {
+            Ok(mismatches) if mismatches.is_empty() => {
+                println!("{}: {}", entry.path, status.green().bold())
+            }
+            Ok(mismatches) => {
+                println!(
+                    "{}: {} ({} of {} blocks differ)",
+                    entry.path,
+                    status.red().bold(),
+                    mismatches.len(),
+                    entry.blocks.len()
+                );
+                for mismatch in mismatches {
+                    print_mismatch(mismatch);
+                }
+            }
+            Err(_) if status == "MISSING" => {
+                println!("{}: {}", entry.path, status.yellow().bold())
+            }
+            Err(e) => println!("{}: {} ({})", entry.path, status.red().bold(), e),
+        }
+    }
+
+    if format == OutputFormat::Json {
+        println!("{}", Value::Array(json_records));
+    } else if !format.is_json() {
+        println!(
+            "\n{} {} OK, {} FAILED, {} MISSING",
+            "Summary:".bold(),
+            ok,
+            failed,
+            missing
+        );
+    }
+
+    if failed + missing > 0 {
+        1
+    } else {
+        0
+    }
+}
+
+fn print_mismatch(mismatch: &BlockMismatch) {
+    match (&mismatch.expected, &mismatch.computed) {
+        (Some(expected), Some(computed)) => println!(
+            "  block #{} @{}: expected {}, computed {}",
+            mismatch.index, expected.offset, expected.digest, computed.digest
+        ),
+        (Some(expected), None) => println!(
+            "  block #{} @{}: missing, the file is shorter",
+            mismatch.index, expected.offset
+        ),
+        (None, Some(computed)) => println!(
+            "  block #{} @{}: not in the manifest, the file is longer",
+            mismatch.index, computed.offset
+        ),
+        (None, None) => {}
+    }
+}
+
+fn mismatch_json(mismatch: &BlockMismatch) -> Value {
+    let block = mismatch.expected.as_ref().or(mismatch.computed.as_ref());
+    json!({
+        "index": mismatch.index,
+        "offset": block.map(|b| b.offset),
+        "length": block.map(|b| b.len),
+        "expected": mismatch.expected.as_ref().map(|b| b.digest.to_hex()),
+        "computed": mismatch.computed.as_ref().map(|b| b.digest.to_hex()),
+    })
+}
+
+/// An inclusion proof as written with `--merkle-proof` and `--format json`,
+/// and read back by `--verify-proof`.
+fn proof_json(proof: &MerkleProof, root: &Digest, hmac: bool) -> Value {
+    let path: Vec<Value> = proof
+        .path
+        .iter()
+        .map(|step| {
+            let siblings: Vec<String> = step.siblings.iter().map(Digest::to_hex).collect();
+            json!({ "position": step.position, "siblings": siblings })
+        })
+        .collect();
+    json!({
+        "algorithm": algorithm_label(root.algorithm(), hmac),
+        "root": root.to_hex(),
+        "leaf_index": proof.leaf_index,
+        "leaf_offset": proof.leaf_offset(),
+        "leaf_size": proof.options.leaf_size,
+        "fan_out": proof.options.fan_out,
+        "path": path,
+    })
+}
+
+/// Reads a proof back from `proof_json`'s format. A whole `--format json`
+/// record, or an array holding one, is accepted too.
+fn parse_proof(value: &Value) -> Result<(MerkleProof, HashAlgorithm, bool), String> {
+    let mut value = value;
+    if let Some(first) = value.as_array().and_then(|records| records.first()) {
+        value = first;
+    }
+    if value.get("proof").is_some() {
+        value = &value["proof"];
+    }
+
+    let label = value["algorithm"]
+        .as_str()
+        .ok_or("proof has no algorithm")?;
+    let (name, hmac) = match label.strip_prefix("HMAC-") {
+        Some(name) => (name, true),
+        None => (label, false),
+    };
+    let algorithm = HashAlgorithm::from_name(name)
+        .ok_or_else(|| format!("proof has unknown algorithm '{}'", label))?;
+    let field = |name: &str| {
+        value[name]
+            .as_u64()
+            .ok_or_else(|| format!("proof has no {}", name))
+    };
+    let options = MerkleOptions {
+        leaf_size: field("leaf_size")?,
+        fan_out: usize::try_from(field("fan_out")?).unwrap_or(usize::MAX),
+    };
+    options.check().map_err(|e| e.to_string())?;
+    let path = value["path"]
+        .as_array()
+        .ok_or("proof has no path")?
+        .iter()
+        .map(|step| {
+            let position = step["position"].as_u64().ok_or("malformed proof step")?;
+            let siblings = step["siblings"]
+                .as_array()
+                .ok_or("malformed proof step")?
+                .iter()
+                .map(|hex| {
+                    hex.as_str()
+                        .and_then(|hex| Digest::from_hex(algorithm, hex).ok())
+                        .ok_or("malformed sibling digest in proof")
+                })
+                .collect::<Result<_, _>>()?;
+            Ok(ProofStep {
+                position: position as usize,
+                siblings,
+            })
+        })
+        .collect::<Result<_, &str>>()?;
+
+    let proof = MerkleProof {
+        leaf_index: usize::try_from(field("leaf_index")?).unwrap_or(usize::MAX),
+        options,
+        path,
+    };
+    if proof.leaf_offset().is_none() {
+        return Err(format!(
+            "proof has leaf index {} beyond any file",
+            proof.leaf_index
+        ));
+    }
+    Ok((proof, algorithm, hmac))
+}
+
+fn verify_merkle_proof(
+    leaf_path: &Path,
+    proof_path: &Path,
+    expected_root: &str,
+    mode_hasher: Option<&Hasher>,
+    format: OutputFormat,
+) -> i32 {
+    let parsed = fs::read_to_string(proof_path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| serde_json::from_str::<Value>(&text).map_err(|e| e.to_string()))
+        .and_then(|value| parse_proof(&value));
+    let (proof, algorithm, hmac) = match parsed {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!(
+                "{}: Cannot read Merkle proof '{}': {}",
+                "Error".red(),
+                proof_path.display(),
+                e
+            );
+            return 2;
+        }
+    };
+    let root = match Digest::from_hex(algorithm, expected_root) {
+        Ok(root) => root,
+        Err(e) => {
+            eprintln!(
+                "{}: Invalid root '{}' - {}",
+                "Error".red(),
+                expected_root,
+                e
+            );
+            return 2;
+        }
+    };
+    let hasher = match mode_hasher {
+        Some(hasher) if hasher.algorithm() == algorithm && hasher.is_hmac() == hmac => {
+            Ok(hasher.clone())
+        }
+        Some(_) | None if hmac => Err(io::Error::other(
+            "the proof is for HMAC tags; pass the key with --hmac-key-file or --hmac-key-env",
+        )),
+        Some(hasher) => Err(io::Error::other(format!(
+            "the proof uses {} but {} was selected",
+            algorithm.name(),
+            hasher.algorithm().name()
+        ))),
+        None if algorithm.has_variable_len() => {
+            Hasher::with_output_len(algorithm, root.as_bytes().len())
+        }
+        None => Ok(Hasher::new(algorithm)),
+    };
+    let result = hasher.and_then(|hasher| {
+        let leaf = fs::read(leaf_path)?;
+        Ok(proof.verify(&leaf, &root, &hasher))
+    });
+
+    let name = leaf_path.display().to_string();
+    let status = match &result {
+        Ok(true) => "OK",
+        Ok(false) => "FAILED",
+        Err(e) if e.kind() == io::ErrorKind::NotFound => "MISSING",
+        Err(_) => "FAILED",
+    };
+    if format.is_json() {
+        let mut record = json!({
+            "path": name,
+            "algorithm": algorithm_label(algorithm, hmac),
+            "root": root.to_hex(),
+            "leaf_index": proof.leaf_index,
+            "match": status == "OK",
+            "status": status,
+        });
+        if let Err(e) = &result {
+            record["error"] = Value::from(e.to_string());
+        }
+        if format == OutputFormat::Json {
+            println!("{}", Value::Array(vec![record]));
+        } else {
+            println!("{}", record);
+        }
+    } else {
+        match &result {
+            Ok(true) => println!(
+                "{} Leaf #{} of the Merkle tree: '{}'",
+                "✓ MATCH:".green().bold(),
+                proof.leaf_index,
+                name
+            ),
+            Ok(false) => println!(
+                "{} Leaf #{} of the Merkle tree: '{}'",
+                "✗ MISMATCH:".red().bold(),
+                proof.leaf_index,
+                name
+            ),
+            Err(e) => eprintln!("{}: Cannot verify '{}': {}", "Error".red(), name, e),
+        }
+        println!("  Root: {}", root);
+    }
+
+    if status == "OK" {
+        0
+    } else {
+        1
+    }
+}
@@ -0,0 +1,305 @@
+//! Merkle trees over fixed-size leaves, so that a single chunk of a file
+//! can be checked against a published root without the rest of the file.
+//!
+//! Leaves are hashed as `H(0x00 || data)` and inner nodes as
+//! `H(0x01 || child || child || ...)`, so a leaf can never be passed off as
+//! a node or the other way round. Each node has up to `fan_out` children;
+//! the last node of a level may have fewer. An empty input is a single
+//! empty leaf.
+
+use crate::batch::for_each_ordered;
+use crate::hash::stream_chunks_into;
+use crate::read::{FileReader, Source, DEFAULT_BUFFER_SIZE};
+use crate::{Digest, Hasher, Progress, ProgressReader, ReadOptions};
+use std::io::{self, Read};
+use std::path::Path;
+use std::time::Instant;
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MerkleOptions {
+    /// Bytes per leaf; only the last leaf may be shorter.
+    pub leaf_size: u64,
+    /// Children per inner node, at least 2.
+    pub fan_out: usize,
+}
+
+impl Default for MerkleOptions {
+    fn default() -> Self {
+        MerkleOptions {
+            leaf_size: 1024 * 1024,
+            fan_out: 2,
+        }
+    }
+}
+
+impl MerkleOptions {
+    /// Fails with `InvalidInput` unless the leaf size is positive and the
+    /// fan-out at least 2.
+    pub fn check(&self) -> io::Result<()> {
+        if self.leaf_size == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Merkle leaf size must be greater than zero",
+            ));
+        }
+        if self.fan_out < 2 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Merkle fan-out must be at least 2, not {}", self.fan_out),
+            ));
+        }
+        Ok(())
+    }
+}
+
+pub struct MerkleTree {
+    hasher: Hasher,
+    options: MerkleOptions,
+    /// `levels[0]` holds the leaf digests, the last level just the root.
+    levels: Vec<Vec<Digest>>,
+}
+
+impl MerkleTree {
+    /// Builds the tree over everything `reader` yields, hashing with fresh
+    /// copies of `hasher`.
+    pub fn from_reader<R: Read>(
+        mut reader: R,
+        hasher: Hasher,
+        options: &MerkleOptions,
+    ) -> io::Result<Self> {
+        options.check()?;
+        let buffer_len = options.leaf_size.min(DEFAULT_BUFFER_SIZE as u64) as usize;
+        let mut buffer = vec![0; buffer_len];
+        let mut leaves = Vec::new();
+        loop {
+            let mut leaf = leaf_hasher(&hasher);
+            let mut len = 0;
+            let mut chunk = (&mut reader).take(options.leaf_size);
+            stream_chunks_into(&mut chunk, &mut buffer, |data| {
+                leaf.update(data);
+                len += data.len() as u64;
+            })?;
+            if len > 0 || leaves.is_empty() {
+                leaves.push(leaf.finalize());
+            }
+            if len < options.leaf_size {
+                break;
+            }
+        }
+        Ok(Self::from_leaves(leaves, hasher, options))
+    }
+
+    /// Like [`MerkleTree::from_reader`] for a file. Large files are
+    /// memory-mapped and their leaves hashed on up to `jobs` threads (`0`
+    /// for one per CPU).
+    pub fn from_file(
+        path: &Path,
+        hasher: Hasher,
+        options: &MerkleOptions,
+        jobs: usize,
+    ) -> io::Result<Self> {
+        Self::from_file_with_options(path, hasher, options, jobs, &ReadOptions::default(), |_| {})
+    }
+
+    /// Like [`MerkleTree::from_file`], reading the file as `read_options`
+    /// says and reporting progress like [`crate::hash_file_with`]. Mapped
+    /// files are hashed on up to `jobs` threads, anything else one leaf
+    /// after the other.
+    pub fn from_file_with_options<F: FnMut(&Progress)>(
+        path: &Path,
+        hasher: Hasher,
+        options: &MerkleOptions,
+        jobs: usize,
+        read_options: &ReadOptions,
+        mut on_progress: F,
+    ) -> io::Result<Self> {
+        options.check()?;
+        let map = match Source::open(path, read_options)? {
+            Source::Mapped(map) => map,
+            stream => {
+                let reader = ProgressReader::new(FileReader::from_source(stream), on_progress);
+                return Self::from_reader(reader, hasher, options);
+            }
+        };
+
+        let leaf_size = usize::try_from(options.leaf_size).unwrap_or(usize::MAX);
+        let chunks: Vec<&[u8]> = map.chunks(leaf_size).collect();
+        let mut leaves = Vec::with_capacity(chunks.len());
+        let start = Instant::now();
+        let mut bytes = 0;
+        for_each_ordered(
+            &chunks,
+            jobs,
+            |data| {
+                let mut leaf = leaf_hasher(&hasher);
+                leaf.update(data);
+                (leaf.finalize(), data.len())
+            },
+            |(digest, len)| {
+                leaves.push(digest);
+                bytes += len as u64;
+                on_progress(&Progress {
+                    bytes,
+                    elapsed: start.elapsed(),
+                });
+            },
+        );
+        if leaves.is_empty() {
+            leaves.push(leaf_hasher(&hasher).finalize());
+        }
+        Ok(Self::from_leaves(leaves, hasher, options))
+    }
+
+    fn from_leaves(leaves: Vec<Digest>, hasher: Hasher, options: &MerkleOptions) -> Self {
+        let mut levels = vec![leaves];
+        while levels.last().unwrap().len() > 1 {
+            let parents = levels
+                .last()
+                .unwrap()
+                .chunks(options.fan_out)
+                .map(|children| node_digest(&hasher, children))
+                .collect();
+            levels.push(parents);
+        }
+        MerkleTree {
+            hasher,
+            options: *options,
+            levels,
+        }
+    }
+
+    pub fn root(&self) -> &Digest {
+        &self.levels.last().unwrap()[0]
+    }
+
+    pub fn options(&self) -> &MerkleOptions {
+        &self.options
+    }
+
+    pub fn leaf_count(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    pub fn leaves(&self) -> &[Digest] {
+        &self.levels[0]
+    }
+
+    /// The inclusion proof for leaf number `index`, or `None` if there is
+    /// no such leaf.
+    pub fn proof(&self, index: usize) -> Option<MerkleProof> {
+        if index >= self.leaf_count() {
+            return None;
+        }
+        let fan_out = self.options.fan_out;
+        let mut path = Vec::new();
+        let mut position = index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            let group = position / fan_out * fan_out;
+            let end = (group + fan_out).min(level.len());
+            let siblings = level[group..end]
+                .iter()
+                .enumerate()
+                .filter(|&(i, _)| group + i != position)
+                .map(|(_, digest)| digest.clone())
+                .collect();
+            path.push(ProofStep {
+                position: position - group,
+                siblings,
+            });
+            position /= fan_out;
+        }
+        Some(MerkleProof {
+            leaf_index: index,
+            options: self.options,
+            path,
+        })
+    }
+
+    /// The hasher the tree was built with.
+    pub fn hasher(&self) -> &Hasher {
+        &self.hasher
+    }
+}
+
+/// Evidence that a leaf belongs to a tree with a given root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleProof {
+    pub leaf_index: usize,
+    pub options: MerkleOptions,
+    /// One step per level, from the leaves up to just below the root.
+    pub path: Vec<ProofStep>,
+}
+
+/// The other children of the node on the path to the root at one level.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofStep {
+    /// Where the node on the path sits among its siblings.
+    pub position: usize,
+    /// The other children of the same parent, in order.
+    pub siblings: Vec<Digest>,
+}
+
+impl MerkleProof {
+    /// Byte offset of the leaf in the original data, or `None` if a proof
+    /// from elsewhere puts it beyond any possible file.
+    pub fn leaf_offset(&self) -> Option<u64> {
+        (self.leaf_index as u64).checked_mul(self.options.leaf_size)
+    }
+
+    /// Checks that `leaf` is leaf number [`leaf_index`](Self::leaf_index) of
+    /// a tree built with `hasher` whose root is `root`.
+    pub fn verify(&self, leaf: &[u8], root: &Digest, hasher: &Hasher) -> bool {
+        if leaf.len() as u64 > self.options.leaf_size {
+            return false;
+        }
+        let mut digest = leaf_hasher(hasher);
+        digest.update(leaf);
+        self.verify_digest(digest.finalize(), root, hasher)
+    }
+
+    /// Like [`MerkleProof::verify`], for a leaf digest that is already
+    /// known.
+    pub fn verify_digest(&self, leaf: Digest, root: &Digest, hasher: &Hasher) -> bool {
+        if self.options.check().is_err() {
+            return false;
+        }
+        let fan_out = self.options.fan_out;
+        let mut digest = leaf;
+        let mut index = self.leaf_index;
+        for step in &self.path {
+            // The positions must spell out the leaf index, or a proof for
+            // one leaf could be passed off as one for another.
+            if step.position != index % fan_out || step.siblings.len() >= fan_out {
+                return false;
+            }
+            if step.position > step.siblings.len() {
+                return false;
+            }
+            let mut children = step.siblings.clone();
+            children.insert(step.position, digest);
+            digest = node_digest(hasher, &children);
+            index /= fan_out;
+        }
+        index == 0 && digest == *root
+    }
+}
+
+fn leaf_hasher(hasher: &Hasher) -> Hasher {
+    let mut leaf = hasher.clone();
+    leaf.reset();
+    leaf.update(&[LEAF_PREFIX]);
+    leaf
+}
+
+fn node_digest(hasher: &Hasher, children: &[Digest]) -> Digest {
+    let mut node = hasher.clone();
+    node.reset();
+    node.update(&[NODE_PREFIX]);
+    for child in children {
+        node.update(child.as_bytes());
+    }
+    node.finalize()
+}
@@ -0,0 +1,64 @@
+use std::io::{self, Read};
+use std::time::{Duration, Instant};
+
+/// Snapshot of how far a hashing operation has got.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    pub bytes: u64,
+    pub elapsed: Duration,
+}
+
+impl Progress {
+    /// Average throughput so far, in bytes per second.
+    pub fn throughput(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 {
+            self.bytes as f64 / secs
+        } else {
+            0.0
+        }
+    }
+}
+
+/// A `Read` wrapper that reports the running byte count to a callback after
+/// every successful read. Wrap the source passed to any `compute_*` function
+/// to get live progress while it is hashed.
+pub struct ProgressReader<R, F> {
+    inner: R,
+    callback: F,
+    bytes: u64,
+    start: Instant,
+}
+
+impl<R: Read, F: FnMut(&Progress)> ProgressReader<R, F> {
+    pub fn new(inner: R, callback: F) -> Self {
+        Self {
+            inner,
+            callback,
+            bytes: 0,
+            start: Instant::now(),
+        }
+    }
+
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read, F: FnMut(&Progress)> Read for ProgressReader<R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if n > 0 {
+            self.bytes += n as u64;
+            (self.callback)(&Progress {
+                bytes: self.bytes,
+                elapsed: self.start.elapsed(),
+            });
+        }
+        Ok(n)
+    }
+}
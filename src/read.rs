@@ -0,0 +1,265 @@
+//! Strategies for reading file contents while hashing.
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Take};
+use std::path::Path;
+
+/// Buffer size for [`ReadStrategy::Read`] unless configured otherwise.
+pub const DEFAULT_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// Files at least this large are memory-mapped by [`ReadStrategy::Auto`].
+/// Below it, setting up the mapping costs more than the copy it saves.
+pub const MMAP_THRESHOLD: u64 = 4 * 1024 * 1024;
+
+/// Alignment of buffer address, length and file offset for `O_DIRECT`
+/// reads. A multiple of the logical block size of any common device.
+const DIRECT_ALIGN: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReadStrategy {
+    /// [`Mmap`](Self::Mmap) for regular files of at least
+    /// [`MMAP_THRESHOLD`] bytes, [`Read`](Self::Read) for everything else.
+    #[default]
+    Auto,
+    /// Map the whole file into memory and hash it in place. Files that
+    /// can't be mapped (pipes, devices, ...) are read instead.
+    Mmap,
+    /// `read` into a buffer of [`ReadOptions::buffer_size`] bytes.
+    Read,
+    /// `O_DIRECT` reads into an aligned buffer, bypassing the page cache so
+    /// that verifying huge backups doesn't evict everything else. Only on
+    /// Linux; elsewhere, and on filesystems without `O_DIRECT` support
+    /// (tmpfs, for one), this behaves like [`Read`](Self::Read).
+    Direct,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadOptions {
+    pub strategy: ReadStrategy,
+    /// Bytes per read for [`ReadStrategy::Read`] and
+    /// [`ReadStrategy::Direct`]. Direct reads round it up to a multiple of
+    /// 4096.
+    pub buffer_size: usize,
+    /// Only hash this part of the file. Ranges are always read with plain
+    /// reads, whatever the strategy.
+    pub range: Option<ByteRange>,
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        ReadOptions {
+            strategy: ReadStrategy::Auto,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            range: None,
+        }
+    }
+}
+
+/// A part of a file or other seekable source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteRange {
+    /// `len` bytes starting at `offset`, or everything after it if `len` is
+    /// `None`.
+    Slice { offset: u64, len: Option<u64> },
+    /// The last `n` bytes.
+    Tail(u64),
+}
+
+impl ByteRange {
+    /// The first `n` bytes.
+    pub fn head(n: u64) -> Self {
+        ByteRange::Slice {
+            offset: 0,
+            len: Some(n),
+        }
+    }
+
+    /// Start offset and length of the range within `size` bytes. Fails with
+    /// `UnexpectedEof` if the range doesn't fit.
+    pub fn resolve(&self, size: u64) -> io::Result<(u64, u64)> {
+        let beyond_eof =
+            |message: String| Err(io::Error::new(io::ErrorKind::UnexpectedEof, message));
+        match *self {
+            ByteRange::Slice { offset, .. } if offset > size => beyond_eof(format!(
+                "offset {} is beyond the end of the file ({} bytes)",
+                offset, size
+            )),
+            ByteRange::Slice { offset, len: None } => Ok((offset, size - offset)),
+            ByteRange::Slice {
+                offset,
+                len: Some(len),
+            } => match offset.checked_add(len) {
+                Some(end) if end <= size => Ok((offset, len)),
+                _ => beyond_eof(format!(
+                    "range of {} bytes at offset {} extends beyond the end of the file ({} bytes)",
+                    len, offset, size
+                )),
+            },
+            ByteRange::Tail(n) if n > size => beyond_eof(format!(
+                "cannot take the last {} bytes of a {}-byte file",
+                n, size
+            )),
+            ByteRange::Tail(n) => Ok((size - n, n)),
+        }
+    }
+
+    /// Seeks `reader` to the start of the range and limits it to the
+    /// range's length, ready for any of the `compute_*` functions.
+    pub fn apply<R: Read + Seek>(&self, mut reader: R) -> io::Result<Take<R>> {
+        let size = reader.seek(SeekFrom::End(0))?;
+        let (offset, len) = self.resolve(size)?;
+        reader.seek(SeekFrom::Start(offset))?;
+        Ok(reader.take(len))
+    }
+}
+
+/// An opened file, ready to be hashed either in place or chunk by chunk.
+pub(crate) enum Source {
+    Mapped(memmap2::Mmap),
+    Stream { reader: Take<File>, buffer: Buffer },
+}
+
+impl Source {
+    pub(crate) fn open(path: &Path, options: &ReadOptions) -> io::Result<Self> {
+        let buffer_size = options.buffer_size.max(1);
+        if let Some(range) = &options.range {
+            return Ok(Source::Stream {
+                reader: range.apply(File::open(path)?)?,
+                buffer: Buffer::new(buffer_size),
+            });
+        }
+        if options.strategy == ReadStrategy::Direct {
+            if let Some(file) = open_direct(path)? {
+                return Ok(Source::Stream {
+                    reader: file.take(u64::MAX),
+                    buffer: Buffer::aligned(buffer_size),
+                });
+            }
+        }
+
+        let file = File::open(path)?;
+        let metadata = file.metadata()?;
+        let map = match options.strategy {
+            ReadStrategy::Auto => metadata.is_file() && metadata.len() >= MMAP_THRESHOLD,
+            ReadStrategy::Mmap => metadata.is_file(),
+            ReadStrategy::Read | ReadStrategy::Direct => false,
+        };
+        if map {
+            // SAFETY: the map is only read while hashing. If another process
+            // truncates the file meanwhile we may fault, like any mmap user; a
+            // concurrent writer can at worst make the digest meaningless.
+            return Ok(Source::Mapped(unsafe { memmap2::Mmap::map(&file)? }));
+        }
+
+        Ok(Source::Stream {
+            reader: file.take(u64::MAX),
+            buffer: Buffer::new(buffer_size),
+        })
+    }
+}
+
+/// A file opened as [`ReadOptions`] says, for callers that need a plain
+/// `Read`. Data is copied out of the map or the (possibly aligned) read
+/// buffer, so it works with any buffer the caller passes, `O_DIRECT` or not.
+pub struct FileReader {
+    source: Source,
+    /// Read position in the map, or in the buffer for streams.
+    pos: usize,
+    /// Bytes of the stream buffer holding data.
+    filled: usize,
+}
+
+impl FileReader {
+    pub fn open(path: &Path, options: &ReadOptions) -> io::Result<Self> {
+        Source::open(path, options).map(Self::from_source)
+    }
+
+    pub(crate) fn from_source(source: Source) -> Self {
+        FileReader {
+            source,
+            pos: 0,
+            filled: 0,
+        }
+    }
+}
+
+impl Read for FileReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = match &mut self.source {
+            Source::Mapped(map) => &map[self.pos..],
+            Source::Stream { reader, buffer } => {
+                if self.pos == self.filled {
+                    self.filled = reader.read(buffer.as_mut_slice())?;
+                    self.pos = 0;
+                }
+                &buffer.as_mut_slice()[self.pos..self.filled]
+            }
+        };
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn open_direct(path: &Path) -> io::Result<Option<File>> {
+    use std::os::unix::fs::{FileExt, OpenOptionsExt};
+
+    let unsupported = |e: &io::Error| e.raw_os_error() == Some(libc::EINVAL);
+    let result = std::fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_DIRECT)
+        .open(path);
+    let file = match result {
+        Ok(file) => file,
+        // The filesystem doesn't do direct I/O.
+        Err(e) if unsupported(&e) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    // Some filesystems (FUSE, some network mounts) accept the flag but fail
+    // every read, so try one before committing to it.
+    let mut probe = Buffer::aligned(DIRECT_ALIGN);
+    match file.read_at(probe.as_mut_slice(), 0) {
+        Ok(_) => Ok(Some(file)),
+        Err(e) if unsupported(&e) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn open_direct(_path: &Path) -> io::Result<Option<File>> {
+    Ok(None)
+}
+
+/// A read buffer, optionally aligned for `O_DIRECT`.
+pub(crate) struct Buffer {
+    storage: Vec<u8>,
+    start: usize,
+    len: usize,
+}
+
+impl Buffer {
+    fn new(len: usize) -> Self {
+        Buffer {
+            storage: vec![0; len],
+            start: 0,
+            len,
+        }
+    }
+
+    fn aligned(len: usize) -> Self {
+        let len = len.div_ceil(DIRECT_ALIGN) * DIRECT_ALIGN;
+        let storage = vec![0; len + DIRECT_ALIGN];
+        let start = storage.as_ptr().align_offset(DIRECT_ALIGN);
+        Buffer {
+            storage,
+            start,
+            len,
+        }
+    }
+
+    pub(crate) fn as_mut_slice(&mut self) -> &mut [u8] {
+        &mut self.storage[self.start..self.start + self.len]
+    }
+}
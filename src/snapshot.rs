@@ -0,0 +1,328 @@
+//! Manifests of a whole directory, and comparing a directory with one later
+//! to find out what drifted.
+//!
+//! A manifest is a header line followed by one line per file, each a JSON
+//! object:
+//!
+//! ```text
+//! {"algorithm":"SHA256","exclude":["*.log"],"format":"file-hasher snapshot","gitignore":false,"include":[],"version":1}
+//! {"digest":"...","mtime":1700000000,"path":"css/site.css","size":7}
+//! ```
+//!
+//! Paths are relative to the directory, with `/` as the separator. The
+//! filters are stored so that a later comparison looks at the same files.
+
+use crate::batch::for_each_ordered;
+use crate::walk::{walk, WalkOptions};
+use crate::{hash_file_with, Digest, HashAlgorithm, Hasher};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+const FORMAT: &str = "file-hasher snapshot";
+/// The manifest version written, and the only one understood.
+pub const SNAPSHOT_VERSION: u64 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotEntry {
+    /// Relative to the snapshot root, `/`-separated.
+    pub path: String,
+    pub size: u64,
+    /// Seconds since the Unix epoch, if the platform has it.
+    pub mtime: Option<u64>,
+    pub digest: Digest,
+}
+
+impl SnapshotEntry {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "path": self.path,
+            "size": self.size,
+            "mtime": self.mtime,
+            "digest": self.digest.to_hex(),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub algorithm: HashAlgorithm,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub gitignore: bool,
+    /// Sorted by path.
+    pub entries: Vec<SnapshotEntry>,
+}
+
+/// How a directory differs from a [`Snapshot`] of it.
+#[derive(Debug, Clone, Default)]
+pub struct Drift {
+    pub added: Vec<SnapshotEntry>,
+    pub removed: Vec<SnapshotEntry>,
+    /// Files whose contents changed, as `(recorded, current)`.
+    pub modified: Vec<(SnapshotEntry, SnapshotEntry)>,
+    /// Files that turned up under a new path with unchanged contents, as
+    /// `(recorded, current)`.
+    pub renamed: Vec<(SnapshotEntry, SnapshotEntry)>,
+}
+
+impl Drift {
+    pub fn is_clean(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.modified.is_empty()
+            && self.renamed.is_empty()
+    }
+}
+
+impl Snapshot {
+    /// Hashes every regular file below `root` that `walk_options` selects,
+    /// on up to `jobs` threads (`0` for one per CPU). Symbolic links are
+    /// left out. Any file that can't be read fails the whole snapshot.
+    pub fn new(
+        root: &Path,
+        algorithm: HashAlgorithm,
+        walk_options: &WalkOptions,
+        jobs: usize,
+    ) -> io::Result<Self> {
+        let mut snapshot = Snapshot {
+            algorithm,
+            include: walk_options.include.clone(),
+            exclude: walk_options.exclude.clone(),
+            gitignore: walk_options.gitignore,
+            entries: Vec::new(),
+        };
+        snapshot.entries = snapshot.scan(root, jobs, |_| None)?;
+        Ok(snapshot)
+    }
+
+    /// Compares `root` with the snapshot, rehashing every file. With
+    /// `trust_mtime`, files whose size and mtime match the manifest are
+    /// assumed unchanged instead.
+    pub fn diff(&self, root: &Path, jobs: usize, trust_mtime: bool) -> io::Result<Drift> {
+        let recorded: BTreeMap<&str, &SnapshotEntry> = self
+            .entries
+            .iter()
+            .map(|entry| (entry.path.as_str(), entry))
+            .collect();
+        let current = self.scan(root, jobs, |entry| {
+            let known = recorded.get(entry.path.as_str())?;
+            let unchanged = trust_mtime
+                && known.size == entry.size
+                && known.mtime.is_some()
+                && known.mtime == entry.mtime;
+            unchanged.then(|| known.digest.clone())
+        })?;
+
+        let mut drift = Drift::default();
+        let mut added = Vec::new();
+        let mut seen = BTreeSet::new();
+        for entry in current {
+            match recorded.get(entry.path.as_str()) {
+                Some(&known) => {
+                    seen.insert(known.path.as_str());
+                    if known.digest != entry.digest {
+                        drift.modified.push((known.clone(), entry));
+                    }
+                }
+                None => added.push(entry),
+            }
+        }
+
+        // Pair up files that vanished with new ones of the same contents.
+        // Empty files all look alike, so they are never taken as renamed.
+        let mut vanished: BTreeMap<Vec<u8>, VecDeque<&SnapshotEntry>> = BTreeMap::new();
+        let mut removed = Vec::new();
+        for entry in &self.entries {
+            if seen.contains(entry.path.as_str()) {
+                continue;
+            }
+            if entry.size == 0 {
+                removed.push(entry.clone());
+            } else {
+                vanished
+                    .entry(entry.digest.as_bytes().to_vec())
+                    .or_default()
+                    .push_back(entry);
+            }
+        }
+        for entry in added {
+            let source = vanished
+                .get_mut(entry.digest.as_bytes())
+                .and_then(VecDeque::pop_front);
+            match source {
+                Some(source) => drift.renamed.push((source.clone(), entry)),
+                None => drift.added.push(entry),
+            }
+        }
+        removed.extend(vanished.into_values().flatten().cloned());
+        removed.sort_by(|a, b| a.path.cmp(&b.path));
+        drift.removed = removed;
+        Ok(drift)
+    }
+
+    /// Lists and hashes the files below `root`, taking the digest from
+    /// `known` where it returns one.
+    fn scan<F>(&self, root: &Path, jobs: usize, known: F) -> io::Result<Vec<SnapshotEntry>>
+    where
+        F: Fn(&SnapshotEntry) -> Option<Digest> + Sync,
+    {
+        if !root.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("'{}' is not a directory", root.display()),
+            ));
+        }
+        let options = WalkOptions {
+            include: self.include.clone(),
+            exclude: self.exclude.clone(),
+            gitignore: self.gitignore,
+            ..Default::default()
+        };
+        let (walked, errors) = walk(root, &options)?;
+        if let Some(e) = errors.into_iter().next() {
+            return Err(e);
+        }
+
+        let hasher = Hasher::new(self.algorithm);
+        let mut entries = Vec::with_capacity(walked.len());
+        let mut failure = None;
+        for_each_ordered(
+            &walked,
+            jobs,
+            |entry| {
+                let path = entry.path();
+                let with_path =
+                    |e: io::Error| io::Error::new(e.kind(), format!("{}: {}", path.display(), e));
+                let metadata = fs::metadata(path).map_err(with_path)?;
+                let mut entry = SnapshotEntry {
+                    path: relative_path(root, path),
+                    size: metadata.len(),
+                    mtime: metadata
+                        .modified()
+                        .ok()
+                        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                        .map(|d| d.as_secs()),
+                    digest: Digest::new(self.algorithm, Vec::new()),
+                };
+                entry.digest = match known(&entry) {
+                    Some(digest) => digest,
+                    None => hash_file_with(path, vec![hasher.clone()], |_| {})
+                        .map_err(with_path)?
+                        .remove(0),
+                };
+                Ok(entry)
+            },
+            |result: io::Result<SnapshotEntry>| match result {
+                Ok(entry) => entries.push(entry),
+                Err(e) => {
+                    failure.get_or_insert(e);
+                }
+            },
+        );
+        if let Some(e) = failure {
+            return Err(e);
+        }
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(entries)
+    }
+
+    /// The manifest: a header line, then one line per entry, each a JSON
+    /// object.
+    pub fn to_manifest(&self) -> String {
+        let header = json!({
+            "format": FORMAT,
+            "version": SNAPSHOT_VERSION,
+            "algorithm": self.algorithm.name(),
+            "include": self.include,
+            "exclude": self.exclude,
+            "gitignore": self.gitignore,
+        });
+        let mut manifest = header.to_string() + "\n";
+        for entry in &self.entries {
+            manifest += &entry.to_json().to_string();
+            manifest.push('\n');
+        }
+        manifest
+    }
+
+    /// Parses a manifest written by [`Snapshot::to_manifest`]. Fails with
+    /// `InvalidData` if it isn't one, or is of another version.
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty());
+        let header: Value = lines
+            .next()
+            .and_then(|(_, line)| serde_json::from_str(line).ok())
+            .filter(|header: &Value| header["format"] == FORMAT)
+            .ok_or_else(|| invalid("not a file-hasher snapshot manifest".to_string()))?;
+        match header["version"].as_u64() {
+            Some(SNAPSHOT_VERSION) => {}
+            Some(version) => {
+                return Err(invalid(format!(
+                    "unsupported manifest version {} (expected {})",
+                    version, SNAPSHOT_VERSION
+                )))
+            }
+            None => return Err(invalid("manifest has no version".to_string())),
+        }
+        let algorithm = header["algorithm"]
+            .as_str()
+            .and_then(HashAlgorithm::from_name)
+            .ok_or_else(|| invalid("manifest has no known algorithm".to_string()))?;
+        let strings = |key: &str| -> io::Result<Vec<String>> {
+            match &header[key] {
+                Value::Null => Ok(Vec::new()),
+                Value::Array(items) => items
+                    .iter()
+                    .map(|item| {
+                        item.as_str()
+                            .map(str::to_string)
+                            .ok_or_else(|| invalid(format!("'{}' must hold strings", key)))
+                    })
+                    .collect(),
+                _ => Err(invalid(format!("'{}' must be a list", key))),
+            }
+        };
+
+        let mut entries = Vec::new();
+        for (index, line) in lines {
+            let bad_entry = || invalid(format!("line {}: malformed entry", index + 1));
+            let item: Value = serde_json::from_str(line).map_err(|_| bad_entry())?;
+            let path = item["path"].as_str().ok_or_else(bad_entry)?;
+            let digest = item["digest"].as_str().ok_or_else(bad_entry)?;
+            entries.push(SnapshotEntry {
+                path: path.to_string(),
+                size: item["size"].as_u64().ok_or_else(bad_entry)?,
+                mtime: item["mtime"].as_u64(),
+                digest: Digest::from_hex(algorithm, digest)
+                    .map_err(|e| invalid(format!("line {}: {}", index + 1, e)))?,
+            });
+        }
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Ok(Snapshot {
+            algorithm,
+            include: strings("include")?,
+            exclude: strings("exclude")?,
+            gitignore: header["gitignore"].as_bool().unwrap_or(false),
+            entries,
+        })
+    }
+}
+
+/// `path` relative to `root`, with components joined by `/`.
+fn relative_path(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    let components: Vec<_> = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect();
+    components.join("/")
+}
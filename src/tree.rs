@@ -0,0 +1,226 @@
+//! Digests of whole directory trees that only depend on what is in the
+//! tree, not on the order the filesystem happens to list it in.
+//!
+//! The tree digest is the hash of this encoding, with all integers
+//! big-endian:
+//!
+//! ```text
+//! "file-hasher tree v1" 0x00
+//! flags: u8                bit 0: mode bits included
+//!                          bit 1: directories included
+//! then for every entry, sorted by path bytes:
+//!     type: u8             'f' file, 'l' symlink, 'd' directory
+//!     path length: u64, path bytes
+//!     mode: u32            only if bit 0 is set
+//!     content length: u64, content bytes
+//! ```
+//!
+//! Paths are relative to the root, with components joined by `/` whatever
+//! the platform. The content of a file is its digest, of a symlink the path
+//! it points to, and of a directory nothing. Mode bits are the permission
+//! bits (`mode & 0o7777`) on Unix and 0 elsewhere.
+
+use crate::batch::for_each_ordered;
+use crate::walk::{walk, WalkEntry, WalkOptions};
+use crate::{hash_file_with, Digest, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+
+const HEADER: &[u8] = b"file-hasher tree v1\0";
+const FLAG_MODE: u8 = 1;
+const FLAG_DIRECTORIES: u8 = 2;
+
+#[derive(Debug, Clone, Default)]
+pub struct TreeOptions {
+    /// Which entries make up the tree. With
+    /// [`directories`](WalkOptions::directories) set, empty directories
+    /// change the digest too.
+    pub walk: WalkOptions,
+    /// Include each entry's permission bits, so that a `chmod` changes the
+    /// digest.
+    pub mode: bool,
+}
+
+impl TreeOptions {
+    fn flags(&self) -> u8 {
+        let mut flags = 0;
+        if self.mode {
+            flags |= FLAG_MODE;
+        }
+        if self.walk.directories {
+            flags |= FLAG_DIRECTORIES;
+        }
+        flags
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TreeEntryKind {
+    File(Digest),
+    Symlink(PathBuf),
+    Dir,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeEntry {
+    /// Relative to the root of the tree.
+    pub path: PathBuf,
+    /// Permission bits, if [`TreeOptions::mode`] is set.
+    pub mode: Option<u32>,
+    pub kind: TreeEntryKind,
+}
+
+pub struct Tree {
+    digest: Digest,
+    entries: Vec<TreeEntry>,
+}
+
+impl Tree {
+    /// Walks `root` and hashes every file in it with a fresh copy of
+    /// `hasher`, on up to `jobs` threads (`0` for one per CPU). Any entry
+    /// that can't be read fails the whole tree, as its digest would be
+    /// meaningless.
+    pub fn from_dir(
+        root: &Path,
+        hasher: &Hasher,
+        options: &TreeOptions,
+        jobs: usize,
+    ) -> io::Result<Self> {
+        if !root.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("'{}' is not a directory", root.display()),
+            ));
+        }
+        let (walked, errors) = walk(root, &options.walk)?;
+        if let Some(e) = errors.into_iter().next() {
+            return Err(e);
+        }
+
+        let mut entries = Vec::with_capacity(walked.len());
+        let mut failure = None;
+        for_each_ordered(
+            &walked,
+            jobs,
+            |entry| tree_entry(root, entry, hasher, options.mode),
+            |result| match result {
+                Ok(entry) => entries.push(entry),
+                Err(e) => {
+                    failure.get_or_insert(e);
+                }
+            },
+        );
+        if let Some(e) = failure {
+            return Err(e);
+        }
+        Ok(Self::from_entries(entries, hasher, options))
+    }
+
+    /// The tree made up of `entries`, in any order. Entries without a mode
+    /// count as mode 0 if [`TreeOptions::mode`] is set.
+    pub fn from_entries(entries: Vec<TreeEntry>, hasher: &Hasher, options: &TreeOptions) -> Self {
+        let mut keyed: Vec<(Vec<u8>, TreeEntry)> = entries
+            .into_iter()
+            .map(|entry| (canonical_path(&entry.path), entry))
+            .collect();
+        keyed.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut tree = hasher.clone();
+        tree.reset();
+        tree.update(HEADER);
+        tree.update(&[options.flags()]);
+        for (path, entry) in &keyed {
+            let (kind, content): (u8, &[u8]) = match &entry.kind {
+                TreeEntryKind::File(digest) => (b'f', digest.as_bytes()),
+                TreeEntryKind::Symlink(target) => (b'l', target.as_os_str().as_encoded_bytes()),
+                TreeEntryKind::Dir => (b'd', &[]),
+            };
+            tree.update(&[kind]);
+            update_with_len(&mut tree, path);
+            if options.mode {
+                tree.update(&entry.mode.unwrap_or(0).to_be_bytes());
+            }
+            update_with_len(&mut tree, content);
+        }
+
+        Tree {
+            digest: tree.finalize(),
+            entries: keyed.into_iter().map(|(_, entry)| entry).collect(),
+        }
+    }
+
+    pub fn digest(&self) -> &Digest {
+        &self.digest
+    }
+
+    /// The entries in canonical order.
+    pub fn entries(&self) -> &[TreeEntry] {
+        &self.entries
+    }
+}
+
+fn tree_entry(
+    root: &Path,
+    entry: &WalkEntry,
+    hasher: &Hasher,
+    mode: bool,
+) -> io::Result<TreeEntry> {
+    let path = entry.path();
+    let with_path = |e: io::Error| io::Error::new(e.kind(), format!("{}: {}", path.display(), e));
+    let kind = match entry {
+        WalkEntry::File(path) => {
+            let mut fresh = hasher.clone();
+            fresh.reset();
+            let mut digests = hash_file_with(path, vec![fresh], |_| {}).map_err(with_path)?;
+            TreeEntryKind::File(digests.remove(0))
+        }
+        WalkEntry::Symlink { target, .. } => TreeEntryKind::Symlink(target.clone()),
+        WalkEntry::Dir(_) => TreeEntryKind::Dir,
+    };
+    let mode = if mode {
+        let follow = !matches!(entry, WalkEntry::Symlink { .. });
+        Some(mode_bits(path, follow).map_err(with_path)?)
+    } else {
+        None
+    };
+    Ok(TreeEntry {
+        path: path.strip_prefix(root).unwrap_or(path).to_path_buf(),
+        mode,
+        kind,
+    })
+}
+
+/// Permission bits of `path`, or of what it links to if `follow` is set.
+#[cfg(unix)]
+fn mode_bits(path: &Path, follow: bool) -> io::Result<u32> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let metadata = if follow {
+        std::fs::metadata(path)?
+    } else {
+        std::fs::symlink_metadata(path)?
+    };
+    Ok(metadata.permissions().mode() & 0o7777)
+}
+
+#[cfg(not(unix))]
+fn mode_bits(_path: &Path, _follow: bool) -> io::Result<u32> {
+    Ok(0)
+}
+
+/// `path` with its components joined by `/`.
+fn canonical_path(path: &Path) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for component in path.components() {
+        if !bytes.is_empty() {
+            bytes.push(b'/');
+        }
+        bytes.extend_from_slice(component.as_os_str().as_encoded_bytes());
+    }
+    bytes
+}
+
+fn update_with_len(hasher: &mut Hasher, data: &[u8]) {
+    hasher.update(&(data.len() as u64).to_be_bytes());
+    hasher.update(data);
+}
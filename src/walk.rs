@@ -0,0 +1,142 @@
+//! Recursive directory traversal with glob filtering.
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// What to do with symbolic links found while walking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SymlinkPolicy {
+    /// Descend into linked directories and hash linked files.
+    Follow,
+    /// Leave symlinks out entirely.
+    #[default]
+    Skip,
+    /// Report the link itself so the caller can hash its target path.
+    HashTarget,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct WalkOptions {
+    /// Only files whose path relative to the root matches one of these are
+    /// kept. Empty means everything.
+    pub include: Vec<String>,
+    /// Files and directories matching any of these are skipped.
+    pub exclude: Vec<String>,
+    /// Honour `.gitignore` and `.git/info/exclude` files, and skip `.git`
+    /// itself.
+    pub gitignore: bool,
+    pub symlinks: SymlinkPolicy,
+    /// Also report the directories below the root, so that empty ones can
+    /// be told apart from missing ones.
+    pub directories: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum WalkEntry {
+    File(PathBuf),
+    Symlink { path: PathBuf, target: PathBuf },
+    Dir(PathBuf),
+}
+
+impl WalkEntry {
+    pub fn path(&self) -> &Path {
+        match self {
+            WalkEntry::File(path) => path,
+            WalkEntry::Symlink { path, .. } => path,
+            WalkEntry::Dir(path) => path,
+        }
+    }
+}
+
+/// Collects every file below `root`, sorted by path so that repeated runs
+/// over an unchanged tree give identical output. Errors for individual
+/// entries (unreadable directories, symlink loops, ...) are returned
+/// separately instead of aborting the walk.
+pub fn walk(root: &Path, options: &WalkOptions) -> io::Result<(Vec<WalkEntry>, Vec<io::Error>)> {
+    let include = build_globset(&options.include)?;
+    let exclude = Arc::new(build_globset(&options.exclude)?);
+
+    let mut builder = WalkBuilder::new(root);
+    builder
+        .standard_filters(false)
+        .git_ignore(options.gitignore)
+        .git_exclude(options.gitignore)
+        .parents(options.gitignore)
+        .require_git(false)
+        .follow_links(options.symlinks == SymlinkPolicy::Follow);
+
+    let filter_root = root.to_path_buf();
+    let skip_git = options.gitignore;
+    builder.filter_entry(move |entry| {
+        if entry.depth() == 0 {
+            return true;
+        }
+        let is_git_dir = entry.file_name() == ".git"
+            && entry
+                .file_type()
+                .is_some_and(|file_type| file_type.is_dir());
+        if skip_git && is_git_dir {
+            return false;
+        }
+        let relative = entry
+            .path()
+            .strip_prefix(&filter_root)
+            .unwrap_or(entry.path());
+        !exclude.is_match(relative)
+    });
+
+    let mut entries = Vec::new();
+    let mut errors = Vec::new();
+
+    for result in builder.build() {
+        let entry = match result {
+            Ok(entry) => entry,
+            Err(e) => {
+                errors.push(io::Error::other(e));
+                continue;
+            }
+        };
+        let Some(file_type) = entry.file_type() else {
+            continue;
+        };
+
+        let relative = entry.path().strip_prefix(root).unwrap_or(entry.path());
+        if !options.include.is_empty() && !include.is_match(relative) {
+            continue;
+        }
+
+        if file_type.is_symlink() {
+            if options.symlinks == SymlinkPolicy::HashTarget {
+                match std::fs::read_link(entry.path()) {
+                    Ok(target) => entries.push(WalkEntry::Symlink {
+                        path: entry.into_path(),
+                        target,
+                    }),
+                    Err(e) => errors.push(e),
+                }
+            }
+        } else if file_type.is_file() {
+            entries.push(WalkEntry::File(entry.into_path()));
+        } else if file_type.is_dir() && options.directories && entry.depth() > 0 {
+            entries.push(WalkEntry::Dir(entry.into_path()));
+        }
+    }
+
+    entries.sort_by(|a, b| a.path().cmp(b.path()));
+    Ok((entries, errors))
+}
+
+fn build_globset(patterns: &[String]) -> io::Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob =
+            Glob::new(pattern).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+}
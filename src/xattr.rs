@@ -0,0 +1,287 @@
+//! Digests kept in extended attributes of the files they belong to, as an
+//! alternative to a [`HashCache`](crate::HashCache) that travels with the
+//! file through copies and backups that preserve attributes.
+//!
+//! Each algorithm gets an attribute of its own, named by
+//! [`HashAlgorithm::xattr_name`], e.g. `user.checksum.sha256`. Its value is
+//! the hex digest and the modification time of the file when it was
+//! hashed, in seconds and nanoseconds since the Unix epoch:
+//!
+//! ```text
+//! 9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08 1700000000.123456789
+//! ```
+//!
+//! A digest whose time still matches the file can be trusted without reading
+//! the file. Like the cache, files modified within the last two seconds are
+//! hashed but not stored, since a write in the same timestamp tick would go
+//! unnoticed. One that doesn't match although the time does means the
+//! contents changed without a write, i.e. bit rot. Only plain digests of
+//! the algorithm's default length are stored; keyed hashes and HMACs are
+//! not. Extended attributes are only supported on Linux.
+
+use crate::cache::RACY_WINDOW;
+use crate::{hash_file_with_options, Digest, HashAlgorithm, Hasher, Progress, ReadOptions};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A digest read back from an extended attribute.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredDigest {
+    pub digest: Digest,
+    /// The file's modification time when it was hashed.
+    pub mtime: SystemTime,
+}
+
+impl StoredDigest {
+    /// Whether the file hasn't been modified since it was hashed, going by
+    /// its current `metadata`.
+    pub fn is_current(&self, metadata: &fs::Metadata) -> bool {
+        metadata.modified().is_ok_and(|mtime| mtime == self.mtime)
+    }
+
+    fn to_value(&self) -> io::Result<String> {
+        let since_epoch = self.mtime.duration_since(UNIX_EPOCH).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "modification time is before 1970",
+            )
+        })?;
+        Ok(format!(
+            "{} {}.{:09}",
+            self.digest.to_hex(),
+            since_epoch.as_secs(),
+            since_epoch.subsec_nanos()
+        ))
+    }
+
+    fn parse(algorithm: HashAlgorithm, value: &[u8]) -> Option<Self> {
+        let value = std::str::from_utf8(value).ok()?;
+        let (hex, mtime) = value.trim_end().split_once(' ')?;
+        let digest = Digest::from_hex(algorithm, hex).ok()?;
+        if digest.as_bytes().len() != algorithm.digest_len() {
+            return None;
+        }
+        let (secs, nanos) = mtime.split_once('.')?;
+        let nanos: u32 = nanos.parse().ok().filter(|&n| n < 1_000_000_000)?;
+        let since_epoch = Duration::new(secs.parse().ok()?, nanos);
+        Some(StoredDigest {
+            digest,
+            mtime: UNIX_EPOCH.checked_add(since_epoch)?,
+        })
+    }
+}
+
+/// Which way [`hash_file_with_xattrs`] uses the attributes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct XattrOptions {
+    /// Take digests from attributes that are still current instead of
+    /// reading the file.
+    pub read: bool,
+    /// Store the digests that had to be computed.
+    pub write: bool,
+}
+
+/// Reads the digest stored for `algorithm` on `path`. `Ok(None)` if there is
+/// none, and `InvalidData` if the attribute isn't one this crate wrote.
+pub fn read_xattr(path: &Path, algorithm: HashAlgorithm) -> io::Result<Option<StoredDigest>> {
+    let Some(value) = sys::get(path, algorithm.xattr_name())? else {
+        return Ok(None);
+    };
+    StoredDigest::parse(algorithm, &value)
+        .map(Some)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("malformed {} attribute", algorithm.xattr_name()),
+            )
+        })
+}
+
+/// Stores `stored` on `path`, replacing any digest of the same algorithm.
+/// Fails with `InvalidInput` for digests not of the algorithm's default
+/// length, and `Unsupported` where extended attributes aren't.
+pub fn write_xattr(path: &Path, stored: &StoredDigest) -> io::Result<()> {
+    let algorithm = stored.digest.algorithm();
+    if stored.digest.as_bytes().len() != algorithm.digest_len() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "only {}-byte {} digests can be stored",
+                algorithm.digest_len(),
+                algorithm.name()
+            ),
+        ));
+    }
+    sys::set(path, algorithm.xattr_name(), stored.to_value()?.as_bytes())
+}
+
+/// Like [`crate::hash_file_with_options`], but reading and writing digests
+/// in extended attributes as `xattrs` says. Hashers whose digests can't be
+/// stored are always run, and digests of files modified too recently to
+/// tell later writes apart are not stored. Failing to store a digest fails
+/// the call, since the caller asked for it; the digests are lost then.
+pub fn hash_file_with_xattrs<F: FnMut(&Progress)>(
+    path: &Path,
+    hashers: Vec<Hasher>,
+    options: &ReadOptions,
+    xattrs: XattrOptions,
+    on_progress: F,
+) -> io::Result<Vec<Digest>> {
+    let started = SystemTime::now();
+    let metadata = fs::metadata(path)?;
+    let usable = |hasher: &Hasher| options.range.is_none() && storable(hasher);
+
+    let mut digests: Vec<Option<Digest>> = hashers
+        .iter()
+        .map(|hasher| {
+            if !xattrs.read || !usable(hasher) {
+                return None;
+            }
+            read_xattr(path, hasher.algorithm())
+                .ok()
+                .flatten()
+                .filter(|stored| stored.is_current(&metadata))
+                .map(|stored| stored.digest)
+        })
+        .collect();
+    let missing: Vec<Hasher> = hashers
+        .iter()
+        .zip(&digests)
+        .filter(|(_, digest)| digest.is_none())
+        .map(|(hasher, _)| hasher.clone())
+        .collect();
+    if missing.is_empty() {
+        return Ok(digests.into_iter().flatten().collect());
+    }
+
+    let mut computed = hash_file_with_options(path, missing, options, on_progress)?.into_iter();
+    // A file written to while it was read has no single digest to store.
+    let mtime = metadata.modified()?;
+    let settled = fs::metadata(path)
+        .is_ok_and(|after| after.len() == metadata.len() && after.modified().ok() == Some(mtime));
+    let racy = mtime + RACY_WINDOW > started;
+    for (hasher, slot) in hashers.iter().zip(&mut digests) {
+        if slot.is_some() {
+            continue;
+        }
+        let digest = computed.next().expect("one digest per missing hasher");
+        if xattrs.write && usable(hasher) && !racy {
+            if !settled {
+                return Err(io::Error::other(
+                    "file changed while it was hashed; no digest stored",
+                ));
+            }
+            write_xattr(
+                path,
+                &StoredDigest {
+                    digest: digest.clone(),
+                    mtime,
+                },
+            )?;
+        }
+        *slot = Some(digest);
+    }
+    Ok(digests.into_iter().flatten().collect())
+}
+
+/// Whether `hasher`'s digests belong in the algorithm's attribute.
+pub fn storable(hasher: &Hasher) -> bool {
+    hasher.cache_id().is_some() && hasher.output_len() == hasher.algorithm().digest_len()
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+    use std::ffi::CString;
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    fn c_string(bytes: &[u8]) -> io::Result<CString> {
+        CString::new(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+    }
+
+    pub fn get(path: &Path, name: &str) -> io::Result<Option<Vec<u8>>> {
+        let path = c_string(path.as_os_str().as_bytes())?;
+        let name = c_string(name.as_bytes())?;
+        loop {
+            // SAFETY: both strings are NUL-terminated, and a null buffer of
+            // size 0 only asks for the value's length.
+            let len =
+                unsafe { libc::getxattr(path.as_ptr(), name.as_ptr(), std::ptr::null_mut(), 0) };
+            if len < 0 {
+                return missing_as_none(io::Error::last_os_error());
+            }
+            let mut value = vec![0u8; len as usize];
+            // SAFETY: `value` is writable for `value.len()` bytes.
+            let read = unsafe {
+                libc::getxattr(
+                    path.as_ptr(),
+                    name.as_ptr(),
+                    value.as_mut_ptr().cast(),
+                    value.len(),
+                )
+            };
+            if read >= 0 {
+                value.truncate(read as usize);
+                return Ok(Some(value));
+            }
+            let e = io::Error::last_os_error();
+            // The value grew in between; ask again.
+            if e.raw_os_error() != Some(libc::ERANGE) {
+                return missing_as_none(e);
+            }
+        }
+    }
+
+    pub fn set(path: &Path, name: &str, value: &[u8]) -> io::Result<()> {
+        let path = c_string(path.as_os_str().as_bytes())?;
+        let name = c_string(name.as_bytes())?;
+        // SAFETY: both strings are NUL-terminated and `value` is readable
+        // for `value.len()` bytes.
+        let result = unsafe {
+            libc::setxattr(
+                path.as_ptr(),
+                name.as_ptr(),
+                value.as_ptr().cast(),
+                value.len(),
+                0,
+            )
+        };
+        if result == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+
+    fn missing_as_none(e: io::Error) -> io::Result<Option<Vec<u8>>> {
+        if e.raw_os_error() == Some(libc::ENODATA) {
+            Ok(None)
+        } else {
+            Err(e)
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod sys {
+    use std::io;
+    use std::path::Path;
+
+    fn unsupported() -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            "extended attributes are only supported on Linux",
+        )
+    }
+
+    pub fn get(_path: &Path, _name: &str) -> io::Result<Option<Vec<u8>>> {
+        Err(unsupported())
+    }
+
+    pub fn set(_path: &Path, _name: &str, _value: &[u8]) -> io::Result<()> {
+        Err(unsupported())
+    }
+}
@@ -0,0 +1,80 @@
+use file_hasher::batch::for_each_ordered;
+use file_hasher::{compute_sha256, hash_file, hash_files, HashAlgorithm, Hasher};
+use std::io::Cursor;
+use std::thread;
+use std::time::Duration;
+use tempfile::tempdir;
+
+#[test]
+fn test_hash_files_keeps_input_order() {
+    let dir = tempdir().unwrap();
+    let mut paths = Vec::new();
+    for i in 0..50 {
+        let path = dir.path().join(format!("file{}.txt", i));
+        std::fs::write(&path, "x".repeat(i * 100)).unwrap();
+        paths.push(path);
+    }
+    paths.insert(10, dir.path().join("missing.txt"));
+
+    let hashers = [
+        Hasher::new(HashAlgorithm::SHA256),
+        Hasher::new(HashAlgorithm::MD5),
+    ];
+    let results = hash_files(&paths, &hashers, 4);
+
+    assert_eq!(results.len(), paths.len());
+    for (result, path) in results.iter().zip(&paths) {
+        assert_eq!(&result.path, path);
+        if path.ends_with("missing.txt") {
+            assert!(result.digests.is_err());
+            continue;
+        }
+        let digests = result.digests.as_ref().unwrap();
+        assert_eq!(digests[0], hash_file(path, HashAlgorithm::SHA256).unwrap());
+        assert_eq!(digests[1], hash_file(path, HashAlgorithm::MD5).unwrap());
+    }
+}
+
+#[test]
+fn test_hash_files_single_job_matches_parallel() {
+    let dir = tempdir().unwrap();
+    let paths: Vec<_> = (0..20)
+        .map(|i| {
+            let path = dir.path().join(format!("{}", i));
+            std::fs::write(&path, i.to_string()).unwrap();
+            path
+        })
+        .collect();
+    let hashers = [Hasher::new(HashAlgorithm::BLAKE3)];
+
+    let serial: Vec<_> = hash_files(&paths, &hashers, 1)
+        .into_iter()
+        .map(|r| r.digests.unwrap())
+        .collect();
+    let parallel: Vec<_> = hash_files(&paths, &hashers, 0)
+        .into_iter()
+        .map(|r| r.digests.unwrap())
+        .collect();
+    assert_eq!(serial, parallel);
+
+    let expected = compute_sha256(&mut Cursor::new(b"7")).unwrap();
+    let sha = hash_files(&paths[7..8], &[Hasher::new(HashAlgorithm::SHA256)], 2);
+    assert_eq!(sha[0].digests.as_ref().unwrap()[0], expected);
+}
+
+#[test]
+fn test_for_each_ordered_emits_in_input_order() {
+    // Earlier items take longest, so they finish last.
+    let items: Vec<u64> = (0..8).collect();
+    let mut emitted = Vec::new();
+    for_each_ordered(
+        &items,
+        8,
+        |&i| {
+            thread::sleep(Duration::from_millis((8 - i) * 10));
+            i * 2
+        },
+        |result| emitted.push(result),
+    );
+    assert_eq!(emitted, vec![0, 2, 4, 6, 8, 10, 12, 14]);
+}
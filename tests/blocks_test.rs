@@ -0,0 +1,120 @@
+use file_hasher::{
+    diff_blocks, hash_reader, Block, Blocks, FileReader, HashAlgorithm, Hasher, ReadOptions,
+    ReadStrategy,
+};
+use std::io::Cursor;
+use tempfile::tempdir;
+
+fn sha256(data: &[u8]) -> file_hasher::Digest {
+    hash_reader(&mut Cursor::new(data), HashAlgorithm::SHA256).unwrap()
+}
+
+fn data() -> Vec<u8> {
+    (0..10_000u32).map(|i| (i % 251) as u8).collect()
+}
+
+#[test]
+fn test_blocks_and_full_digest() {
+    let data = data();
+    let mut blocks = Blocks::new(Cursor::new(&data), Hasher::new(HashAlgorithm::SHA256), 4096);
+    let collected: Vec<Block> = blocks.by_ref().map(Result::unwrap).collect();
+
+    let spans: Vec<(u64, u64)> = collected.iter().map(|b| (b.offset, b.len)).collect();
+    assert_eq!(spans, vec![(0, 4096), (4096, 4096), (8192, 1808)]);
+    for block in &collected {
+        let start = block.offset as usize;
+        let end = start + block.len as usize;
+        assert_eq!(block.digest, sha256(&data[start..end]));
+    }
+    assert_eq!(blocks.full_digest(), sha256(&data));
+}
+
+#[test]
+fn test_blocks_of_file_with_every_read_strategy() {
+    let data = data();
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("data.bin");
+    std::fs::write(&path, &data).unwrap();
+    let hasher = Hasher::new(HashAlgorithm::SHA256);
+    let expected: Vec<Block> = Blocks::new(Cursor::new(&data), hasher.clone(), 4096)
+        .map(Result::unwrap)
+        .collect();
+
+    for strategy in [
+        ReadStrategy::Auto,
+        ReadStrategy::Mmap,
+        ReadStrategy::Read,
+        ReadStrategy::Direct,
+    ] {
+        // A buffer size that lines up with neither the blocks nor the file.
+        let options = ReadOptions {
+            strategy,
+            buffer_size: 1000,
+            ..Default::default()
+        };
+        let reader = FileReader::open(&path, &options).unwrap();
+        let mut blocks = Blocks::new(reader, hasher.clone(), 4096);
+        let collected: Vec<Block> = blocks.by_ref().map(Result::unwrap).collect();
+        assert_eq!(collected, expected, "{:?}", strategy);
+        assert_eq!(blocks.full_digest(), sha256(&data));
+    }
+}
+
+#[test]
+fn test_blocks_edge_cases() {
+    let hasher = Hasher::new(HashAlgorithm::SHA256);
+
+    let mut empty = Blocks::new(Cursor::new(Vec::new()), hasher.clone(), 16);
+    assert!(empty.next().is_none());
+    assert_eq!(empty.full_digest(), sha256(b""));
+
+    // An exact multiple of the block size has no short trailing block.
+    let exact: Vec<Block> = Blocks::new(Cursor::new(vec![1u8; 32]), hasher.clone(), 16)
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(exact.len(), 2);
+    assert_eq!(exact[1].len, 16);
+
+    // Keys carry over to every block.
+    let keyed = Hasher::blake3_keyed(&[7u8; 32]);
+    let block = Blocks::new(Cursor::new(b"abc"), keyed.clone(), 2)
+        .next()
+        .unwrap()
+        .unwrap();
+    let mut expected = keyed;
+    expected.update(b"ab");
+    assert_eq!(block.digest, expected.finalize());
+}
+
+#[test]
+fn test_diff_blocks_reports_changed_and_missing_blocks() {
+    let original = data();
+    let hasher = Hasher::new(HashAlgorithm::SHA256);
+    let expected: Vec<Block> = Blocks::new(Cursor::new(&original), hasher.clone(), 4096)
+        .map(Result::unwrap)
+        .collect();
+
+    let intact = diff_blocks(Cursor::new(&original), hasher.clone(), 4096, &expected).unwrap();
+    assert!(intact.is_empty());
+
+    let mut corrupted = original.clone();
+    corrupted[5000] ^= 0xff;
+    let mismatches = diff_blocks(Cursor::new(&corrupted), hasher.clone(), 4096, &expected).unwrap();
+    assert_eq!(mismatches.len(), 1);
+    assert_eq!(mismatches[0].index, 1);
+    assert_eq!(mismatches[0].expected.as_ref(), Some(&expected[1]));
+    assert_eq!(mismatches[0].computed.as_ref().unwrap().offset, 4096);
+
+    let truncated = &original[..4096];
+    let mismatches = diff_blocks(Cursor::new(truncated), hasher.clone(), 4096, &expected).unwrap();
+    let indexes: Vec<usize> = mismatches.iter().map(|m| m.index).collect();
+    assert_eq!(indexes, vec![1, 2]);
+    assert!(mismatches.iter().all(|m| m.computed.is_none()));
+
+    let mut extended = original.clone();
+    extended.extend_from_slice(&[0u8; 5000]);
+    let mismatches = diff_blocks(Cursor::new(&extended), hasher, 4096, &expected).unwrap();
+    let indexes: Vec<usize> = mismatches.iter().map(|m| m.index).collect();
+    assert_eq!(indexes, vec![2, 3]);
+    assert!(mismatches[1].expected.is_none());
+}
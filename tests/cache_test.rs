@@ -0,0 +1,149 @@
+#![cfg(unix)]
+
+use file_hasher::{hash_file, HashAlgorithm, HashCache, Hasher, PruneStats};
+use std::fs::{self, File};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+use tempfile::tempdir;
+
+/// Writes `contents` and backdates the file past the cache's racy window.
+fn write_old(path: &Path, contents: &[u8], mtime: SystemTime) {
+    fs::write(path, contents).unwrap();
+    File::options()
+        .append(true)
+        .open(path)
+        .unwrap()
+        .set_modified(mtime)
+        .unwrap();
+}
+
+fn entries(cache: &HashCache) -> usize {
+    count_files(cache.dir())
+}
+
+fn count_files(dir: &Path) -> usize {
+    fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                count_files(&path)
+            } else {
+                1
+            }
+        })
+        .sum()
+}
+
+#[test]
+fn test_cache_reuses_digest_of_unchanged_file() {
+    let dir = tempdir().unwrap();
+    let cache = HashCache::open(&dir.path().join("cache")).unwrap();
+    let file = dir.path().join("data.bin");
+    let hour_ago = SystemTime::now() - Duration::from_secs(3600);
+    write_old(&file, b"original", hour_ago);
+    let hasher = Hasher::new(HashAlgorithm::SHA256);
+
+    let first = cache.hash_file(&file, &hasher).unwrap();
+    assert_eq!(first, hash_file(&file, HashAlgorithm::SHA256).unwrap());
+    assert_eq!(entries(&cache), 1);
+
+    // Same inode, size and mtime: the cache can't tell, which shows the
+    // digest came from it rather than from the file.
+    write_old(&file, b"tampered", hour_ago);
+    assert_eq!(cache.hash_file(&file, &hasher).unwrap(), first);
+
+    // A different mtime misses.
+    write_old(&file, b"tampered", hour_ago + Duration::from_secs(1));
+    let changed = cache.hash_file(&file, &hasher).unwrap();
+    assert_eq!(changed, hash_file(&file, HashAlgorithm::SHA256).unwrap());
+    assert_ne!(changed, first);
+
+    // Other algorithms get entries of their own.
+    let blake3 = cache
+        .hash_file(&file, &Hasher::new(HashAlgorithm::BLAKE3))
+        .unwrap();
+    assert_eq!(blake3, hash_file(&file, HashAlgorithm::BLAKE3).unwrap());
+    assert_eq!(entries(&cache), 3);
+}
+
+#[test]
+fn test_cache_refresh() {
+    let dir = tempdir().unwrap();
+    let cache_dir = dir.path().join("cache");
+    let file = dir.path().join("data.bin");
+    let hour_ago = SystemTime::now() - Duration::from_secs(3600);
+    write_old(&file, b"original", hour_ago);
+    let hasher = Hasher::new(HashAlgorithm::SHA256);
+    let cache = HashCache::open(&cache_dir).unwrap();
+    cache.hash_file(&file, &hasher).unwrap();
+
+    write_old(&file, b"restored", hour_ago);
+    let expected = hash_file(&file, HashAlgorithm::SHA256).unwrap();
+    let refreshing = HashCache::open(&cache_dir).unwrap().with_refresh(true);
+    assert_eq!(refreshing.hash_file(&file, &hasher).unwrap(), expected);
+    // The refreshed entry is what later runs see.
+    assert_eq!(cache.hash_file(&file, &hasher).unwrap(), expected);
+}
+
+#[test]
+fn test_cache_skips_recent_and_keyed() {
+    let dir = tempdir().unwrap();
+    let cache = HashCache::open(&dir.path().join("cache")).unwrap();
+    let file = dir.path().join("data.bin");
+
+    // Written just now, so another write could share its mtime.
+    fs::write(&file, b"fresh").unwrap();
+    cache
+        .hash_file(&file, &Hasher::new(HashAlgorithm::SHA256))
+        .unwrap();
+    assert_eq!(entries(&cache), 0);
+
+    write_old(
+        &file,
+        b"fresh",
+        SystemTime::now() - Duration::from_secs(3600),
+    );
+    let keyed = Hasher::blake3_keyed(&[7; 32]);
+    cache.hash_file(&file, &keyed).unwrap();
+    assert_eq!(entries(&cache), 0);
+}
+
+#[test]
+fn test_cache_prune() {
+    let dir = tempdir().unwrap();
+    let cache = HashCache::open(&dir.path().join("cache")).unwrap();
+    let hour_ago = SystemTime::now() - Duration::from_secs(3600);
+    let hasher = Hasher::new(HashAlgorithm::SHA256);
+    // Entries must record any file name faithfully, line breaks included.
+    for name in ["kept\nchanged", "changed", "deleted"] {
+        let file = dir.path().join(name);
+        write_old(&file, name.as_bytes(), hour_ago);
+        cache.hash_file(&file, &hasher).unwrap();
+    }
+    write_old(&dir.path().join("changed"), b"other", hour_ago);
+    assert!(dir.path().join("kept\nchanged").exists());
+    fs::remove_file(dir.path().join("deleted")).unwrap();
+
+    assert_eq!(
+        cache.prune(None).unwrap(),
+        PruneStats {
+            kept: 1,
+            removed: 2
+        }
+    );
+    assert_eq!(
+        cache.prune(Some(Duration::from_secs(3600))).unwrap(),
+        PruneStats {
+            kept: 1,
+            removed: 0
+        }
+    );
+    assert_eq!(
+        cache.prune(Some(Duration::ZERO)).unwrap(),
+        PruneStats {
+            kept: 0,
+            removed: 1
+        }
+    );
+}
@@ -0,0 +1,176 @@
+use assert_cmd::Command;
+use file_hasher::checksum::{format_bsd_line, format_gnu_line, parse_line, ParseChecksumError};
+use file_hasher::{hash_file, Digest, HashAlgorithm};
+use predicates::prelude::*;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+const HELLO_SHA256: &str = "dffd6021bb2bd5b0af676290809ec3a53191dd81c7f70a4b28688a362182986f";
+const HELLO_MD5: &str = "65a8e27d8879283831b664bd8b7f0ad4";
+
+#[test]
+fn test_parse_gnu_lines() {
+    let text = parse_line(&format!("{}  hello.txt", HELLO_SHA256), None).unwrap();
+    assert_eq!(text.path, Path::new("hello.txt"));
+    assert_eq!(text.expected.algorithm(), HashAlgorithm::SHA256);
+    assert!(!text.binary);
+
+    let binary = parse_line(&format!("{} *my file.bin\n", HELLO_MD5), None).unwrap();
+    assert_eq!(binary.path, Path::new("my file.bin"));
+    assert_eq!(binary.expected.algorithm(), HashAlgorithm::MD5);
+    assert!(binary.binary);
+
+    let escaped = parse_line(&format!("\\{}  a\\\\b\\nc", HELLO_SHA256), None).unwrap();
+    assert_eq!(escaped.path, Path::new("a\\b\nc"));
+}
+
+#[test]
+fn test_parse_bsd_lines() {
+    let entry = parse_line(&format!("MD5 (dir/a (1).txt) = {}", HELLO_MD5), None).unwrap();
+    assert_eq!(entry.path, Path::new("dir/a (1).txt"));
+    assert_eq!(entry.expected.algorithm(), HashAlgorithm::MD5);
+    assert_eq!(entry.expected, HELLO_MD5);
+
+    assert_eq!(
+        parse_line(&format!("WHIRLPOOL (a) = {}", HELLO_MD5), None).unwrap_err(),
+        ParseChecksumError::UnknownAlgorithm("WHIRLPOOL".to_string())
+    );
+}
+
+#[test]
+fn test_bsd_lines_carry_variable_digest_lengths() {
+    let hex = "511bc81dde11180838c562c82bb35f3223f46061ebde4a955c27b3f489cf1e03";
+    let digest = Digest::from_hex(HashAlgorithm::BLAKE2B, hex).unwrap();
+    let line = format_bsd_line(&digest, "hello.txt");
+    assert_eq!(line, format!("BLAKE2b-256 (hello.txt) = {}", hex));
+
+    let entry = parse_line(&line, None).unwrap();
+    assert_eq!(entry.expected, digest);
+    assert_eq!(entry.expected.algorithm(), HashAlgorithm::BLAKE2B);
+
+    // The tag's length has to agree with the digest
+    assert_eq!(
+        parse_line(&format!("BLAKE2b-512 (hello.txt) = {}", hex), None).unwrap_err(),
+        ParseChecksumError::Malformed
+    );
+}
+
+#[test]
+fn test_parse_rejects_malformed_lines() {
+    assert_eq!(
+        parse_line("not a checksum", None).unwrap_err(),
+        ParseChecksumError::Malformed
+    );
+    assert!(parse_line(&format!("{}  a", HELLO_MD5), Some(HashAlgorithm::SHA256)).is_err());
+}
+
+#[test]
+fn test_format_lines_escape_like_coreutils() {
+    let digest = Digest::from_hex(HashAlgorithm::MD5, HELLO_MD5).unwrap();
+    assert_eq!(
+        format_gnu_line(&digest, "plain.txt"),
+        format!("{}  plain.txt", HELLO_MD5)
+    );
+    assert_eq!(
+        format_gnu_line(&digest, "a\\b\nc"),
+        format!("\\{}  a\\\\b\\nc", HELLO_MD5)
+    );
+    assert_eq!(
+        format_bsd_line(&digest, "a\nb"),
+        format!("\\MD5 (a\\nb) = {}", HELLO_MD5)
+    );
+
+    let line = format_gnu_line(&digest, "a\\b\nc");
+    assert_eq!(parse_line(&line, None).unwrap().path, Path::new("a\\b\nc"));
+}
+
+#[test]
+fn test_cli_check_reports_each_entry() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("good.txt"), b"Hello, World!").unwrap();
+    fs::write(dir.path().join("bad.txt"), b"tampered").unwrap();
+    fs::write(
+        dir.path().join("SUMS"),
+        format!(
+            "{h}  good.txt\n{h}  bad.txt\n{h}  gone.txt\nMD5 (good.txt) = {m}\n",
+            h = HELLO_SHA256,
+            m = HELLO_MD5
+        ),
+    )
+    .unwrap();
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .current_dir(dir.path())
+        .args(["--check", "SUMS"])
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("good.txt: OK"))
+        .stdout(predicate::str::contains("bad.txt: FAILED"))
+        .stdout(predicate::str::contains("gone.txt: MISSING"))
+        .stdout(predicate::str::contains("2 OK, 1 FAILED, 1 MISSING"));
+}
+
+#[test]
+fn test_cli_check_all_ok() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("good.txt"), b"Hello, World!").unwrap();
+    fs::write(
+        dir.path().join("SUMS"),
+        format!("{}  good.txt\n", HELLO_SHA256),
+    )
+    .unwrap();
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .current_dir(dir.path())
+        .args(["-c", "SUMS"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_cli_check_does_not_guess_non_cryptographic_checksums() {
+    let dir = tempdir().unwrap();
+    let file = dir.path().join("a");
+    fs::write(&file, b"Hello, World!").unwrap();
+    // 32 hex digits, like an MD5 line.
+    let xxh128 = hash_file(&file, HashAlgorithm::XXH128).unwrap();
+    fs::write(dir.path().join("SUMS"), format!("{}  a\n", xxh128)).unwrap();
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .current_dir(dir.path())
+        .args(["--check", "SUMS"])
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("a: FAILED"))
+        .stdout(predicate::str::contains("a: OK").not());
+
+    // Named explicitly, it is checked, with a warning.
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .current_dir(dir.path())
+        .args(["--check", "SUMS", "--algorithm", "xxh128"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("a: OK"))
+        .stderr(predicate::str::contains(
+            "XXH128 is not a cryptographic hash",
+        ));
+}
+
+#[test]
+fn test_cli_check_without_valid_lines() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("SUMS"), "garbage\n").unwrap();
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .current_dir(dir.path())
+        .args(["--check", "SUMS"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("No properly formatted"));
+}
@@ -28,7 +28,7 @@ fn test_cli_multiple_files() {
     File::create(&file2).unwrap().write_all(b"File 2").unwrap();
 
     let mut cmd = Command::cargo_bin("file-hasher").unwrap();
-    cmd.args(&[file1.to_str().unwrap(), file2.to_str().unwrap()])
+    cmd.args([file1.to_str().unwrap(), file2.to_str().unwrap()])
         .assert()
         .success()
         .stdout(predicate::str::contains("file1.txt"))
@@ -47,7 +47,7 @@ fn test_cli_algorithm_selection() {
     // Test MD5
     Command::cargo_bin("file-hasher")
         .unwrap()
-        .args(&["--algorithm", "md5", file_path.to_str().unwrap()])
+        .args(["--algorithm", "md5", file_path.to_str().unwrap()])
         .assert()
         .success()
         .stdout(predicate::str::contains("MD5"));
@@ -55,7 +55,7 @@ fn test_cli_algorithm_selection() {
     // Test SHA1
     Command::cargo_bin("file-hasher")
         .unwrap()
-        .args(&["--algorithm", "sha1", file_path.to_str().unwrap()])
+        .args(["--algorithm", "sha1", file_path.to_str().unwrap()])
         .assert()
         .success()
         .stdout(predicate::str::contains("SHA1"));
@@ -63,7 +63,7 @@ fn test_cli_algorithm_selection() {
     // Test All
     Command::cargo_bin("file-hasher")
         .unwrap()
-        .args(&["--algorithm", "all", file_path.to_str().unwrap()])
+        .args(["--algorithm", "all", file_path.to_str().unwrap()])
         .assert()
         .success()
         .stdout(predicate::str::contains("SHA256"))
@@ -80,3 +80,1128 @@ fn test_cli_invalid_file() {
         .failure()
         .stderr(predicate::str::contains("Error"));
 }
+
+#[test]
+fn test_cli_gnu_and_bsd_formats() {
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("test.txt");
+    File::create(&file_path)
+        .unwrap()
+        .write_all(b"Hello, World!")
+        .unwrap();
+    let path = file_path.to_str().unwrap();
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["--format", "gnu", path])
+        .assert()
+        .success()
+        .stdout(format!(
+            "dffd6021bb2bd5b0af676290809ec3a53191dd81c7f70a4b28688a362182986f  {}\n",
+            path
+        ));
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["--format", "bsd", "--algorithm", "md5", path])
+        .assert()
+        .success()
+        .stdout(format!(
+            "MD5 ({}) = 65a8e27d8879283831b664bd8b7f0ad4\n",
+            path
+        ));
+}
+
+#[test]
+fn test_cli_gnu_output_round_trips_through_check() {
+    let dir = tempdir().unwrap();
+    File::create(dir.path().join("a.txt"))
+        .unwrap()
+        .write_all(b"a")
+        .unwrap();
+    File::create(dir.path().join("b.txt"))
+        .unwrap()
+        .write_all(b"b")
+        .unwrap();
+
+    let output = Command::cargo_bin("file-hasher")
+        .unwrap()
+        .current_dir(dir.path())
+        .args(["--format", "bsd", "--algorithm", "all", "a.txt", "b.txt"])
+        .output()
+        .unwrap();
+    std::fs::write(dir.path().join("SUMS"), output.stdout).unwrap();
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .current_dir(dir.path())
+        .args(["--check", "SUMS"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("30 OK, 0 FAILED, 0 MISSING"));
+}
+
+#[test]
+fn test_cli_json_output() {
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("test.txt");
+    File::create(&file_path)
+        .unwrap()
+        .write_all(b"Hello, World!")
+        .unwrap();
+    let missing = dir.path().join("missing.txt");
+
+    let output = Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args([
+            "--format",
+            "json",
+            "--algorithm",
+            "all",
+            file_path.to_str().unwrap(),
+            missing.to_str().unwrap(),
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+
+    let records: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let records = records.as_array().unwrap();
+    assert_eq!(records.len(), 2);
+
+    assert_eq!(records[0]["size"], 13);
+    assert_eq!(
+        records[0]["digests"]["SHA256"],
+        "dffd6021bb2bd5b0af676290809ec3a53191dd81c7f70a4b28688a362182986f"
+    );
+    assert_eq!(
+        records[0]["digests"]["MD5"],
+        "65a8e27d8879283831b664bd8b7f0ad4"
+    );
+    assert!(records[0]["mtime"].is_u64());
+    assert!(records[0]["elapsed_secs"].is_f64());
+    assert!(records[0].get("error").is_none());
+
+    assert!(records[1]["error"].is_string());
+}
+
+#[test]
+fn test_cli_ndjson_output() {
+    let dir = tempdir().unwrap();
+    let file1 = dir.path().join("file1.txt");
+    let file2 = dir.path().join("file2.txt");
+    File::create(&file1).unwrap().write_all(b"File 1").unwrap();
+    File::create(&file2).unwrap().write_all(b"File 2").unwrap();
+
+    let output = Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args([
+            "--format",
+            "ndjson",
+            file1.to_str().unwrap(),
+            file2.to_str().unwrap(),
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["path"], file1.to_str().unwrap());
+    assert_eq!(lines[1]["algorithms"], serde_json::json!(["SHA256"]));
+}
+
+#[test]
+fn test_cli_hashes_stdin() {
+    let hello_sha256 = "dffd6021bb2bd5b0af676290809ec3a53191dd81c7f70a4b28688a362182986f";
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["--format", "gnu", "-"])
+        .write_stdin("Hello, World!")
+        .assert()
+        .success()
+        .stdout(format!("{}  -\n", hello_sha256));
+
+    // Piped input without any file arguments
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["--format", "gnu"])
+        .write_stdin("Hello, World!")
+        .assert()
+        .success()
+        .stdout(format!("{}  -\n", hello_sha256));
+}
+
+#[test]
+fn test_cli_verifies_stdin() {
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args([
+            "--algorithm",
+            "md5",
+            "--verify",
+            "65a8e27d8879283831b664bd8b7f0ad4",
+            "-",
+        ])
+        .write_stdin("Hello, World!")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("MATCH"));
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args([
+            "--algorithm",
+            "md5",
+            "--verify",
+            "65a8e27d8879283831b664bd8b7f0ad4",
+        ])
+        .write_stdin("tampered")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("MISMATCH"));
+}
+
+#[test]
+fn test_cli_shake_length() {
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("test.txt");
+    std::fs::write(&file_path, b"Hello, World!").unwrap();
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["--algorithm", "shake128", "--length", "64"])
+        .arg(&file_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("SHAKE128: 2bf5e6dee6079fad\n"));
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["--algorithm", "shake256", "--verify"])
+        .arg("b3be97bfd978833a65588ceae8a34cf59e95585a")
+        .arg(&file_path)
+        .assert()
+        .success();
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["--algorithm", "sha3-256", "--length", "64"])
+        .arg(&file_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("only applies to shake128"));
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["--algorithm", "shake128", "--length", "12"])
+        .arg(&file_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("multiple of 8"));
+}
+
+#[test]
+fn test_cli_blake3_keyed_and_derive_key() {
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("empty");
+    let key_path = dir.path().join("key");
+    std::fs::write(&file_path, b"").unwrap();
+    std::fs::write(&key_path, b"whats the Elvish word for friend").unwrap();
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["--algorithm", "blake3", "--format", "gnu", "--key-file"])
+        .arg(&key_path)
+        .arg(&file_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "92b2b75604ed3c761f9d6f62392c8a9227ad0ea3f09573e783f1498a4ed60d26",
+        ));
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["--algorithm", "blake3", "--key-file"])
+        .arg(&key_path)
+        .args([
+            "--verify",
+            "92b2b75604ed3c761f9d6f62392c8a9227ad0ea3f09573e783f1498a4ed60d26",
+        ])
+        .arg(&file_path)
+        .assert()
+        .success();
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["--algorithm", "blake3", "--format", "gnu", "--derive-key"])
+        .arg("BLAKE3 2019-12-27 16:29:52 test vectors context")
+        .arg(&file_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "2cc39783c223154fea8dfb7c1b1660f2ac2dcbd1c1de8277b0b0dd39b7e50d7d",
+        ));
+
+    // A short key is rejected rather than padded
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["--algorithm", "blake3", "--key-file"])
+        .arg(&file_path)
+        .arg(&file_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("exactly 32 bytes"));
+}
+
+#[test]
+fn test_cli_blake2_parameters() {
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("test.txt");
+    let key_path = dir.path().join("key");
+    std::fs::write(&file_path, b"Hello, World!").unwrap();
+    std::fs::write(&key_path, b"secret").unwrap();
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args([
+            "--algorithm",
+            "blake2b",
+            "--length",
+            "256",
+            "--format",
+            "gnu",
+        ])
+        .args([
+            "--salt",
+            "73616c7479",
+            "--personalization",
+            "6d65",
+            "--key-file",
+        ])
+        .arg(&key_path)
+        .arg(&file_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "65e2fe0b415e21b656fa517b57828078793a56b208eac824d1eddc22a2bd6a29",
+        ));
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["--algorithm", "blake2s", "--salt", "00112233445566778899"])
+        .arg(&file_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("salt is 10 bytes"));
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["--algorithm", "sha256", "--salt", "00"])
+        .arg(&file_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("require --algorithm blake2b"));
+}
+
+#[test]
+fn test_cli_hmac_key_sources() {
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("data.txt");
+    std::fs::write(&file_path, "what do ya want for nothing?").unwrap();
+    let key_path = dir.path().join("hmac.key");
+    std::fs::write(&key_path, "Jefe").unwrap();
+    let tag = "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843";
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .arg("--hmac-key-file")
+        .arg(&key_path)
+        .arg(&file_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!("HMAC-SHA256: {}", tag)))
+        .stdout(predicate::str::contains("Jefe").not());
+
+    let output = Command::cargo_bin("file-hasher")
+        .unwrap()
+        .env("FILE_HASHER_TEST_KEY", "Jefe")
+        .args(["--hmac-key-env", "FILE_HASHER_TEST_KEY", "--format", "gnu"])
+        .arg(&file_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let sums_path = dir.path().join("HMACSUMS");
+    std::fs::write(&sums_path, &output.stdout).unwrap();
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .arg("--check")
+        .arg(&sums_path)
+        .arg("--hmac-key-file")
+        .arg(&key_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("1 OK, 0 FAILED"));
+
+    // Without the key the tags are plain SHA-256 mismatches.
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .arg("--check")
+        .arg(&sums_path)
+        .assert()
+        .code(1);
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .env("FILE_HASHER_TEST_KEY", "Jefe")
+        .args(["--hmac-key-env", "FILE_HASHER_TEST_KEY", "--verify", tag])
+        .arg(&file_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("MATCH"));
+}
+
+#[test]
+fn test_cli_hmac_rejects_bad_input() {
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("data.txt");
+    std::fs::write(&file_path, "data").unwrap();
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .env("FILE_HASHER_TEST_KEY", "secret")
+        .args([
+            "--hmac-key-env",
+            "FILE_HASHER_TEST_KEY",
+            "--algorithm",
+            "md5",
+        ])
+        .arg(&file_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("HMAC requires"))
+        .stderr(predicate::str::contains("secret").not());
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .env_remove("FILE_HASHER_UNSET_KEY")
+        .args(["--hmac-key-env", "FILE_HASHER_UNSET_KEY"])
+        .arg(&file_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("is not set"));
+
+    let empty_key = dir.path().join("empty.key");
+    std::fs::write(&empty_key, "").unwrap();
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .arg("--hmac-key-file")
+        .arg(&empty_key)
+        .arg(&file_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("HMAC key is empty"));
+}
+
+#[test]
+fn test_cli_jobs_keeps_input_order() {
+    let dir = tempdir().unwrap();
+    let mut files = Vec::new();
+    for name in ["c.txt", "a.txt", "b.txt", "d.txt"] {
+        let path = dir.path().join(name);
+        std::fs::write(&path, name).unwrap();
+        files.push(path);
+    }
+
+    let run = |jobs: &str| {
+        let output = Command::cargo_bin("file-hasher")
+            .unwrap()
+            .args(["--format", "gnu", "--jobs", jobs])
+            .args(&files)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let serial = run("1");
+    let lines: Vec<&str> = serial.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert!(lines[0].ends_with("c.txt"));
+    assert!(lines[3].ends_with("d.txt"));
+    assert_eq!(run("4"), serial);
+    assert_eq!(run("0"), serial);
+}
+
+#[test]
+fn test_cli_io_strategies() {
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("data.bin");
+    std::fs::write(&file_path, vec![7u8; 100_000]).unwrap();
+
+    let run = |args: &[&str]| {
+        let output = Command::cargo_bin("file-hasher")
+            .unwrap()
+            .args(["--format", "gnu"])
+            .args(args)
+            .arg(&file_path)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let auto = run(&[]);
+    assert_eq!(run(&["--io", "mmap"]), auto);
+    assert_eq!(run(&["--io", "read", "--buffer-size", "4K"]), auto);
+    assert_eq!(run(&["--io", "direct", "--buffer-size", "1M"]), auto);
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["--buffer-size", "0"])
+        .arg(&file_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("greater than zero"));
+}
+
+#[test]
+fn test_cli_ranges() {
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("image.bin");
+    std::fs::write(&file_path, "0123456789abcdef").unwrap();
+
+    let cases: [(&[&str], &str); 3] = [
+        (
+            &["--head", "4"],
+            "1be2e452b46d7a0d9656bbb1f768e8248eba1b75baed65f5d99eafa948899a6a",
+        ),
+        (
+            &["--tail", "4"],
+            "9727bc3a31f5a09994a8408791a22db4b12502452e6e5d00a59a0ef8342b41cf",
+        ),
+        (
+            &["--offset", "4", "--range-length", "6"],
+            "472bbe83616e93d3c09a79103ae47d8f71e3d35a966d6e8b22f743218d04171d",
+        ),
+    ];
+    for (args, expected) in cases {
+        Command::cargo_bin("file-hasher")
+            .unwrap()
+            .args(["--format", "gnu"])
+            .args(args)
+            .arg(&file_path)
+            .assert()
+            .success()
+            .stdout(predicate::str::starts_with(expected));
+
+        Command::cargo_bin("file-hasher")
+            .unwrap()
+            .args(args)
+            .args(["--verify", expected])
+            .arg(&file_path)
+            .assert()
+            .success();
+    }
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["--offset", "10", "--range-length", "7"])
+        .arg(&file_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "extends beyond the end of the file (16 bytes)",
+        ));
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["--head", "1", "-"])
+        .write_stdin("data")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("not standard input"));
+}
+
+#[test]
+fn test_cli_block_manifest_round_trip() {
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("image.bin");
+    let mut data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+    std::fs::write(&file_path, &data).unwrap();
+
+    let output = Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["--block-size", "4K", "--format", "json"])
+        .arg(&file_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json[0]["block_size"], 4096);
+    assert_eq!(json[0]["blocks"].as_array().unwrap().len(), 3);
+    assert_eq!(json[0]["blocks"][2]["length"], 1808);
+    let manifest = dir.path().join("blocks.json");
+    std::fs::write(&manifest, &output.stdout).unwrap();
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .arg("--verify-blocks")
+        .arg(&manifest)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("1 OK, 0 FAILED"));
+
+    data[5000] ^= 0xff;
+    std::fs::write(&file_path, &data).unwrap();
+    for io in ["auto", "mmap", "read", "direct"] {
+        Command::cargo_bin("file-hasher")
+            .unwrap()
+            .arg("--verify-blocks")
+            .arg(&manifest)
+            .args(["--io", io, "--buffer-size", "1000"])
+            .assert()
+            .code(1)
+            .stdout(predicate::str::contains("1 of 3 blocks differ"))
+            .stdout(predicate::str::contains("block #1 @4096"));
+    }
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["--block-size", "4K", "--format", "gnu"])
+        .arg(&file_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--block-size needs --format"));
+}
+
+#[test]
+fn test_cli_merkle_proof_round_trip() {
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("release.tar");
+    let data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+    std::fs::write(&file_path, &data).unwrap();
+
+    let output = Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["--merkle", "--leaf-size", "1K", "--fan-out", "3"])
+        .args(["--merkle-proof", "4", "--format", "json"])
+        .arg(&file_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json[0]["merkle"]["leaves"], 10);
+    let root = json[0]["digests"]["SHA256"].as_str().unwrap().to_string();
+    assert_eq!(json[0]["proof"]["root"], root.as_str());
+    let proof_path = dir.path().join("leaf4.proof");
+    std::fs::write(&proof_path, serde_json::to_vec(&json[0]["proof"]).unwrap()).unwrap();
+
+    let leaf_path = dir.path().join("leaf4");
+    std::fs::write(&leaf_path, &data[4096..5120]).unwrap();
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .arg("--verify-proof")
+        .arg(&proof_path)
+        .args(["--verify", &root])
+        .arg(&leaf_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("MATCH"));
+
+    let wrong_leaf = dir.path().join("leaf5");
+    std::fs::write(&wrong_leaf, &data[5120..6144]).unwrap();
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .arg("--verify-proof")
+        .arg(&proof_path)
+        .args(["--verify", &root])
+        .arg(&wrong_leaf)
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("MISMATCH"));
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["--merkle", "--fan-out", "1"])
+        .arg(&file_path)
+        .assert()
+        .failure();
+
+    // Proofs come from elsewhere; bad parameters are reported, not trusted.
+    for (field, value, error) in [
+        ("fan_out", 0, "fan-out must be at least 2"),
+        ("leaf_size", 0, "leaf size must be greater than zero"),
+        ("leaf_index", u64::MAX, "beyond any file"),
+    ] {
+        let mut bad = json[0]["proof"].clone();
+        bad[field] = value.into();
+        std::fs::write(&proof_path, serde_json::to_vec(&bad).unwrap()).unwrap();
+        Command::cargo_bin("file-hasher")
+            .unwrap()
+            .arg("--verify-proof")
+            .arg(&proof_path)
+            .args(["--verify", &root])
+            .arg(&leaf_path)
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains(error));
+    }
+}
+
+#[test]
+fn test_cli_tree() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().join("site");
+    std::fs::create_dir_all(root.join("css")).unwrap();
+    std::fs::write(root.join("index.html"), b"<html></html>").unwrap();
+    std::fs::write(root.join("css/site.css"), b"body {}").unwrap();
+
+    let tree = |extra: &[&str]| {
+        let output = Command::cargo_bin("file-hasher")
+            .unwrap()
+            .args(["--tree", "--format", "json"])
+            .args(extra)
+            .arg(&root)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        json[0].clone()
+    };
+    let json = tree(&[]);
+    assert_eq!(json["entries"].as_array().unwrap().len(), 2);
+    assert_eq!(json["entries"][0]["path"], "css/site.css");
+    assert_eq!(json["entries"][0]["type"], "file");
+    let digest = json["tree"].as_str().unwrap().to_string();
+
+    std::fs::create_dir(root.join("img")).unwrap();
+    assert_eq!(tree(&[])["tree"], digest.as_str());
+    assert_ne!(tree(&["--tree-dirs"])["tree"], digest.as_str());
+    assert_ne!(tree(&["--exclude", "css"])["tree"], digest.as_str());
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .arg("--tree")
+        .arg(&root)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!("SHA256 tree: {}", digest)))
+        .stdout(predicate::str::contains("Entries: 2"));
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .arg("--tree")
+        .arg(root.join("index.html"))
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("not a directory"));
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["--tree", "--format", "gnu"])
+        .arg(&root)
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_cli_snapshot_and_diff() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().join("release");
+    std::fs::create_dir_all(root.join("bin")).unwrap();
+    std::fs::write(root.join("bin/app"), b"application").unwrap();
+    std::fs::write(root.join("README"), b"read me").unwrap();
+    let manifest = dir.path().join("release.manifest");
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["snapshot", "-a", "blake3", "-o"])
+        .arg(&manifest)
+        .arg(&root)
+        .assert()
+        .success();
+    let text = std::fs::read_to_string(&manifest).unwrap();
+    assert!(text
+        .lines()
+        .next()
+        .unwrap()
+        .contains("\"algorithm\":\"BLAKE3\""));
+    assert_eq!(text.lines().count(), 3);
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .arg("diff")
+        .arg(&manifest)
+        .arg(&root)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "0 ADDED, 0 REMOVED, 0 MODIFIED, 0 RENAMED",
+        ));
+
+    std::fs::write(root.join("bin/app"), b"patched").unwrap();
+    std::fs::rename(root.join("README"), root.join("README.txt")).unwrap();
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .arg("diff")
+        .arg(&manifest)
+        .arg(&root)
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("bin/app: MODIFIED"))
+        .stdout(predicate::str::contains("README -> README.txt: RENAMED"));
+
+    let output = Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["diff", "--format", "json"])
+        .arg(&manifest)
+        .arg(&root)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["clean"], false);
+    assert_eq!(json["modified"][0]["before"]["path"], "bin/app");
+    assert_eq!(json["renamed"][0]["after"]["path"], "README.txt");
+
+    std::fs::write(&manifest, b"bin/app  0123\n").unwrap();
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .arg("diff")
+        .arg(&manifest)
+        .arg(&root)
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "not a file-hasher snapshot manifest",
+        ));
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["snapshot", "-a", "all"])
+        .arg(&root)
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_cli_dupes() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().join("share");
+    std::fs::create_dir_all(root.join("copy")).unwrap();
+    std::fs::write(root.join("report.pdf"), b"quarterly numbers").unwrap();
+    std::fs::write(root.join("copy/report.pdf"), b"quarterly numbers").unwrap();
+    std::fs::write(root.join("copy/report (1).pdf"), b"quarterly numbers").unwrap();
+    std::fs::write(root.join("notes.txt"), b"something else").unwrap();
+
+    let output = Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["dupes", "--format", "json"])
+        .arg(&root)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["sets"].as_array().unwrap().len(), 1);
+    assert_eq!(json["sets"][0]["paths"].as_array().unwrap().len(), 3);
+    assert_eq!(json["wasted_bytes"], 34);
+
+    let script = dir.path().join("dedupe.sh");
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["dupes", "--delete", "--script"])
+        .arg(&script)
+        .arg(&root)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("34 bytes wasted"))
+        .stdout(predicate::str::contains("add --confirm"));
+    // Without --confirm nothing is touched.
+    assert!(root.join("copy/report.pdf").exists());
+    let script = std::fs::read_to_string(&script).unwrap();
+    assert!(script.starts_with("#!/bin/sh"));
+    assert_eq!(script.matches("\nrm -- ").count(), 2);
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["dupes", "--delete", "--confirm"])
+        .arg(&root)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Deleted"));
+    assert!(root.join("copy/report (1).pdf").exists());
+    assert!(!root.join("copy/report.pdf").exists());
+    assert!(!root.join("report.pdf").exists());
+    assert!(root.join("notes.txt").exists());
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["dupes", "--confirm"])
+        .arg(&root)
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_cli_dupes_refuses_to_remove_by_checksum() {
+    let dir = tempdir().unwrap();
+    std::fs::write(dir.path().join("a"), b"same").unwrap();
+    std::fs::write(dir.path().join("b"), b"same").unwrap();
+
+    for action in ["--hardlink", "--delete"] {
+        Command::cargo_bin("file-hasher")
+            .unwrap()
+            .args(["dupes", "-a", "crc32", action, "--confirm"])
+            .arg(dir.path())
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("not a cryptographic hash"));
+    }
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["dupes", "-a", "xxh3", "--script"])
+        .arg(dir.path().join("dedupe.sh"))
+        .arg(dir.path())
+        .assert()
+        .code(2);
+    assert!(dir.path().join("b").exists());
+    assert!(!dir.path().join("dedupe.sh").exists());
+
+    // Listing them is fine.
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["dupes", "-a", "crc32"])
+        .arg(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("2 copies"));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_cli_dupes_script_names() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let dir = tempdir().unwrap();
+    let root = dir.path().join("share");
+    std::fs::create_dir(&root).unwrap();
+    std::fs::write(root.join("a\nkept"), b"same").unwrap();
+    std::fs::write(root.join("b"), b"same").unwrap();
+
+    // A line break in the kept name stays inside its comment.
+    let script = dir.path().join("dedupe.sh");
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["dupes", "--script"])
+        .arg(&script)
+        .arg(&root)
+        .assert()
+        .success();
+    let text = std::fs::read_to_string(&script).unwrap();
+    assert!(text.contains("a\\nkept'\nrm -- "));
+    assert_eq!(text.matches("\nrm -- ").count(), 1);
+
+    // Names that aren't UTF-8 can't be spelled safely, so no script at all.
+    std::fs::remove_file(&script).unwrap();
+    std::fs::write(root.join(OsStr::from_bytes(b"c\xff")), b"same").unwrap();
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["dupes", "--script"])
+        .arg(&script)
+        .arg(&root)
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("not valid UTF-8"));
+    assert!(!script.exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_cli_cache() {
+    let dir = tempdir().unwrap();
+    let cache = dir.path().join("cache");
+    let file_path = dir.path().join("artifact.bin");
+    std::fs::write(&file_path, b"build output").unwrap();
+    let hour_ago = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+    File::options()
+        .append(true)
+        .open(&file_path)
+        .unwrap()
+        .set_modified(hour_ago)
+        .unwrap();
+
+    let hash = |extra: &[&str]| {
+        let output = Command::cargo_bin("file-hasher")
+            .unwrap()
+            .args(["--format", "gnu"])
+            .args(extra)
+            .arg(&file_path)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let cache_dir = cache.to_str().unwrap();
+    let original = hash(&["--cache-dir", cache_dir]);
+
+    // Same size and mtime, so only the cache can explain the old digest.
+    std::fs::write(&file_path, b"build outpuT").unwrap();
+    File::options()
+        .append(true)
+        .open(&file_path)
+        .unwrap()
+        .set_modified(hour_ago)
+        .unwrap();
+    assert_eq!(hash(&["--cache-dir", cache_dir]), original);
+    let uncached = hash(&["--no-cache"]);
+    assert_ne!(uncached, original);
+    assert_eq!(
+        hash(&["--refresh-cache", "--cache-dir", cache_dir]),
+        uncached
+    );
+    assert_eq!(hash(&["--cache-dir", cache_dir]), uncached);
+
+    std::fs::remove_file(&file_path).unwrap();
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["prune-cache", "--cache-dir", cache_dir])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("removed 1 entries, kept 0"));
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["--no-cache", "--cache-dir", cache_dir])
+        .arg(dir.path())
+        .assert()
+        .failure();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_cli_prune_cache_from_another_directory() {
+    let dir = tempdir().unwrap();
+    let cache = dir.path().join("cache");
+    let work = dir.path().join("work");
+    std::fs::create_dir(&work).unwrap();
+    let file_path = work.join("artifact.bin");
+    std::fs::write(&file_path, b"build output").unwrap();
+    File::options()
+        .append(true)
+        .open(&file_path)
+        .unwrap()
+        .set_modified(std::time::SystemTime::now() - std::time::Duration::from_secs(3600))
+        .unwrap();
+
+    // Hashed by a relative path...
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .current_dir(&work)
+        .arg("--cache-dir")
+        .arg(&cache)
+        .arg("artifact.bin")
+        .assert()
+        .success();
+    // ...the entry still matches its file when pruning from elsewhere.
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .current_dir(dir.path())
+        .arg("prune-cache")
+        .arg("--cache-dir")
+        .arg(&cache)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("removed 0 entries, kept 1"));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_cli_xattr() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().join("archive");
+    std::fs::create_dir(&root).unwrap();
+    let photo = root.join("photo.raw");
+    std::fs::write(&photo, b"pixels").unwrap();
+    // Older than the racy window, so its digest gets stored.
+    File::options()
+        .append(true)
+        .open(&photo)
+        .unwrap()
+        .set_modified(std::time::SystemTime::now() - std::time::Duration::from_secs(3600))
+        .unwrap();
+    std::fs::write(root.join("untagged.raw"), b"more pixels").unwrap();
+
+    let output = Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["--write-xattr", "--format", "gnu"])
+        .arg(&photo)
+        .output()
+        .unwrap();
+    if !output.status.success() {
+        // No user xattrs on this filesystem.
+        assert!(String::from_utf8_lossy(&output.stderr).contains("not supported"));
+        return;
+    }
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["--verify-xattr", "--recursive"])
+        .arg(&root)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("photo.raw: OK"))
+        .stdout(predicate::str::contains("untagged.raw: NO CHECKSUM"));
+
+    // Flip a byte without touching the mtime.
+    let mtime = std::fs::metadata(&photo).unwrap().modified().unwrap();
+    std::fs::write(&photo, b"pixelz").unwrap();
+    File::options()
+        .append(true)
+        .open(&photo)
+        .unwrap()
+        .set_modified(mtime)
+        .unwrap();
+
+    let cached = Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["--use-xattr", "--format", "gnu"])
+        .arg(&photo)
+        .output()
+        .unwrap();
+    assert_eq!(cached.stdout, output.stdout);
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["--verify-xattr", "--recursive"])
+        .arg(&root)
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("photo.raw: FAILED (SHA256)"))
+        .stdout(predicate::str::contains("1 FAILED"));
+
+    // A real write is a change, not corruption.
+    std::fs::write(&photo, b"edited pixels").unwrap();
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["--verify-xattr"])
+        .arg(&photo)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("photo.raw: CHANGED (SHA256)"));
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["--write-xattr", "--head", "4"])
+        .arg(&photo)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("whole-file digests"));
+}
@@ -0,0 +1,106 @@
+use file_hasher::{compute_sha256, verify_reader, Digest, HashAlgorithm, ParseDigestError};
+use std::io::Cursor;
+
+const HELLO_SHA256: &str = "dffd6021bb2bd5b0af676290809ec3a53191dd81c7f70a4b28688a362182986f";
+
+#[test]
+fn test_digest_carries_algorithm_and_bytes() {
+    let digest = compute_sha256(&mut Cursor::new(b"Hello, World!")).unwrap();
+    assert_eq!(digest.algorithm(), HashAlgorithm::SHA256);
+    assert_eq!(digest.as_bytes().len(), 32);
+    assert_eq!(digest.to_string(), HELLO_SHA256);
+    assert_eq!(digest.to_hex_upper(), HELLO_SHA256.to_uppercase());
+}
+
+#[test]
+fn test_digest_encodings() {
+    let digest = Digest::new(HashAlgorithm::MD5, vec![0xfb; 16]);
+    assert_eq!(digest.to_base64(), "+/v7+/v7+/v7+/v7+/v7+w==");
+    assert_eq!(digest.to_base64url(), "-_v7-_v7-_v7-_v7-_v7-w");
+    assert_eq!(digest.to_base32(), "7P57X6737P57X6737P57X6737M======");
+}
+
+#[test]
+fn test_digest_parse_and_compare() {
+    let computed = compute_sha256(&mut Cursor::new(b"Hello, World!")).unwrap();
+
+    let parsed: Digest = HELLO_SHA256.to_uppercase().parse().unwrap();
+    assert_eq!(parsed.algorithm(), HashAlgorithm::SHA256);
+    assert_eq!(parsed, computed);
+
+    let explicit = Digest::from_hex(HashAlgorithm::SHA256, HELLO_SHA256).unwrap();
+    assert_eq!(explicit, computed);
+
+    let other = Digest::from_hex(HashAlgorithm::SHA256, &"0".repeat(64)).unwrap();
+    assert_ne!(other, computed);
+}
+
+#[test]
+fn test_digest_parse_errors() {
+    assert_eq!(
+        "xyz".parse::<Digest>().unwrap_err(),
+        ParseDigestError::InvalidHex
+    );
+    assert_eq!(
+        "abcd".parse::<Digest>().unwrap_err(),
+        ParseDigestError::UnknownLength(2)
+    );
+    assert!(matches!(
+        Digest::from_hex(HashAlgorithm::SHA1, HELLO_SHA256),
+        Err(ParseDigestError::LengthMismatch { .. })
+    ));
+}
+
+#[test]
+fn test_ambiguous_digest_lengths() {
+    assert_eq!(
+        HashAlgorithm::candidates_for_digest_len(32),
+        &[
+            HashAlgorithm::SHA256,
+            HashAlgorithm::SHA512_256,
+            HashAlgorithm::SHA3_256,
+            HashAlgorithm::BLAKE3
+        ]
+    );
+    assert_eq!(
+        HashAlgorithm::candidates_for_digest_len(28),
+        &[
+            HashAlgorithm::SHA224,
+            HashAlgorithm::SHA512_224,
+            HashAlgorithm::SHA3_224
+        ]
+    );
+    assert_eq!(
+        HashAlgorithm::from_digest_len(64),
+        Some(HashAlgorithm::SHA512)
+    );
+    // Checksums have to be named; a collision could pass for MD5 otherwise.
+    assert_eq!(
+        HashAlgorithm::candidates_for_digest_len(16),
+        &[HashAlgorithm::MD5]
+    );
+    assert!(HashAlgorithm::candidates_for_digest_len(8).is_empty());
+    assert_eq!(HashAlgorithm::from_digest_len(4), None);
+
+    let sha512_256 = "0686f0a605973dc1bf035d1e2b9bad1985a0bff712ddd88abd8d2593e5f99030";
+    let guessed: Digest = sha512_256.parse().unwrap();
+    assert_eq!(guessed.algorithm(), HashAlgorithm::SHA256);
+
+    let strict = verify_reader(&mut Cursor::new(b"Hello, World!"), &guessed, false).unwrap();
+    assert!(!strict.is_match());
+
+    let resolved = verify_reader(&mut Cursor::new(b"Hello, World!"), &guessed, true).unwrap();
+    assert!(resolved.is_match());
+    assert_eq!(resolved.expected.algorithm(), HashAlgorithm::SHA512_256);
+}
+
+#[test]
+fn test_xof_digests_accept_any_length() {
+    let short = Digest::from_hex(HashAlgorithm::SHAKE128, "2bf5e6dee6079fad").unwrap();
+    assert_eq!(short.as_bytes().len(), 8);
+    assert!(Digest::from_hex(HashAlgorithm::SHAKE256, "").is_err());
+
+    // The expected digest's length decides how much output to compare.
+    let verification = verify_reader(&mut Cursor::new(b"Hello, World!"), &short, false).unwrap();
+    assert!(verification.is_match());
+}
@@ -0,0 +1,110 @@
+use file_hasher::{find_duplicates, hash_file, DupeOptions, HashAlgorithm};
+use std::fs;
+use std::path::PathBuf;
+use tempfile::tempdir;
+
+fn data(len: usize, seed: u8) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8 ^ seed).collect()
+}
+
+#[test]
+fn test_find_duplicates_in_stages() {
+    let dir = tempdir().unwrap();
+    let write = |name: &str, contents: &[u8]| {
+        let path = dir.path().join(name);
+        fs::write(&path, contents).unwrap();
+        path
+    };
+    let big = data(100_000, 0);
+    // Same size and prefix as `big`, but a different last byte.
+    let mut almost = big.clone();
+    *almost.last_mut().unwrap() ^= 1;
+    let paths: Vec<PathBuf> = vec![
+        write("big1", &big),
+        write("big2", &big),
+        write("almost", &almost),
+        write("other", &data(100_000, 7)),
+        write("small1", b"small"),
+        write("small2", b"small"),
+        write("unique", b"unique size"),
+        write("empty1", b""),
+        write("empty2", b""),
+    ];
+
+    let options = DupeOptions {
+        prefix_len: 4096,
+        ..Default::default()
+    };
+    let scan = find_duplicates(&paths, HashAlgorithm::SHA256, &options, 2);
+    assert!(scan.errors.is_empty());
+    assert_eq!(scan.files, 7);
+    // `unique` is the only file without a same-sized partner.
+    assert_eq!(scan.prefix_hashed, 6);
+    // `other` differs within the prefix, and the small files fit in it.
+    assert_eq!(scan.fully_hashed, 3);
+
+    assert_eq!(scan.sets.len(), 2);
+    assert_eq!(scan.sets[0].paths, [paths[0].clone(), paths[1].clone()]);
+    assert_eq!(scan.sets[0].wasted_bytes(), 100_000);
+    assert_eq!(
+        scan.sets[0].digest,
+        hash_file(&paths[0], HashAlgorithm::SHA256).unwrap()
+    );
+    assert_eq!(scan.sets[1].paths, [paths[4].clone(), paths[5].clone()]);
+    assert_eq!(
+        scan.sets[1].digest,
+        hash_file(&paths[4], HashAlgorithm::SHA256).unwrap()
+    );
+    assert_eq!(scan.wasted_bytes(), 100_005);
+
+    let no_prefix = DupeOptions {
+        prefix_len: 0,
+        ..Default::default()
+    };
+    let scan = find_duplicates(&paths, HashAlgorithm::BLAKE3, &no_prefix, 1);
+    assert_eq!(scan.prefix_hashed, 0);
+    assert_eq!(scan.fully_hashed, 6);
+    assert_eq!(scan.sets.len(), 2);
+}
+
+#[test]
+fn test_find_duplicates_options_and_errors() {
+    let dir = tempdir().unwrap();
+    let a = dir.path().join("a");
+    let b = dir.path().join("b");
+    fs::write(&a, b"tiny").unwrap();
+    fs::write(&b, b"tiny").unwrap();
+    let missing = dir.path().join("missing");
+    let paths = vec![a.clone(), b.clone(), missing];
+
+    let scan = find_duplicates(&paths, HashAlgorithm::SHA256, &DupeOptions::default(), 1);
+    assert_eq!(scan.errors.len(), 1);
+    assert_eq!(scan.sets.len(), 1);
+
+    let options = DupeOptions {
+        min_size: 5,
+        ..Default::default()
+    };
+    let scan = find_duplicates(&paths, HashAlgorithm::SHA256, &options, 1);
+    assert!(scan.sets.is_empty());
+    assert_eq!(scan.files, 0);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_find_duplicates_ignores_hard_links() {
+    let dir = tempdir().unwrap();
+    let original = dir.path().join("original");
+    let link = dir.path().join("link");
+    fs::write(&original, b"shared contents").unwrap();
+    fs::hard_link(&original, &link).unwrap();
+
+    let scan = find_duplicates(
+        &[original, link],
+        HashAlgorithm::SHA256,
+        &DupeOptions::default(),
+        1,
+    );
+    assert_eq!(scan.files, 1);
+    assert!(scan.sets.is_empty());
+}
@@ -1,5 +1,9 @@
-use file_hasher::{hash_file, HashAlgorithm};
-use std::io::Write;
+use file_hasher::{
+    compute_blake3, compute_sha256, hash_file, hash_file_multi, hash_file_range, hash_file_with,
+    hash_file_with_options, hash_file_with_progress, ByteRange, HashAlgorithm, Hasher, ReadOptions,
+    ReadStrategy,
+};
+use std::io::{Cursor, ErrorKind, Write};
 use std::path::Path;
 use tempfile::NamedTempFile;
 
@@ -18,8 +22,8 @@ fn test_large_file_streaming() {
     let hash = hash_file(path, HashAlgorithm::SHA256).expect("Failed to hash file");
 
     // Verify it completes without running out of memory
-    assert!(!hash.is_empty());
-    assert_eq!(hash.len(), 64); // SHA256 produces 64 hex characters
+    assert!(!hash.to_hex().is_empty());
+    assert_eq!(hash.to_hex().len(), 64); // SHA256 produces 64 hex characters
 }
 
 #[test]
@@ -36,9 +40,9 @@ fn test_multiple_algorithm_streaming() {
     let md5 = hash_file(path, HashAlgorithm::MD5).expect("Failed to hash MD5");
     let sha1 = hash_file(path, HashAlgorithm::SHA1).expect("Failed to hash SHA1");
 
-    assert_eq!(sha256.len(), 64);
-    assert_eq!(md5.len(), 32);
-    assert_eq!(sha1.len(), 40);
+    assert_eq!(sha256.to_hex().len(), 64);
+    assert_eq!(md5.to_hex().len(), 32);
+    assert_eq!(sha1.to_hex().len(), 40);
 }
 
 #[test]
@@ -46,3 +50,213 @@ fn test_nonexistent_file() {
     let result = hash_file(Path::new("nonexistent.txt"), HashAlgorithm::SHA256);
     assert!(result.is_err());
 }
+
+#[test]
+fn test_multi_digest_single_pass() {
+    let mut temp_file = NamedTempFile::new().expect("Failed to create temp file");
+    temp_file
+        .write_all(b"Test data for multiple algorithms")
+        .expect("Failed to write");
+    temp_file.flush().expect("Failed to flush");
+
+    let path = temp_file.path();
+    let algorithms = [
+        HashAlgorithm::SHA256,
+        HashAlgorithm::MD5,
+        HashAlgorithm::SHA1,
+    ];
+    let results = hash_file_multi(path, &algorithms).expect("Failed to hash file");
+
+    assert_eq!(results.len(), 3);
+    for (algorithm, hash) in algorithms.into_iter().zip(results) {
+        assert_eq!(hash.algorithm(), algorithm);
+        assert_eq!(hash, hash_file(path, algorithm).unwrap());
+    }
+}
+
+#[test]
+fn test_progress_reports_bytes_hashed() {
+    let mut temp_file = NamedTempFile::new().expect("Failed to create temp file");
+    let data = vec![7u8; 3 * 1024 * 1024 + 17];
+    temp_file.write_all(&data).expect("Failed to write");
+    temp_file.flush().expect("Failed to flush");
+
+    let mut updates = Vec::new();
+    let results = hash_file_with_progress(temp_file.path(), &[HashAlgorithm::SHA256], |p| {
+        updates.push(p.bytes)
+    })
+    .expect("Failed to hash file");
+
+    assert_eq!(
+        results[0],
+        hash_file(temp_file.path(), HashAlgorithm::SHA256).unwrap()
+    );
+    assert!(updates.len() > 1);
+    assert!(updates.windows(2).all(|w| w[0] < w[1]));
+    assert_eq!(*updates.last().unwrap(), data.len() as u64);
+}
+
+#[test]
+fn test_large_blake3_file_hashed_in_parallel() {
+    let mut temp_file = NamedTempFile::new().expect("Failed to create temp file");
+    let data: Vec<u8> = (0..40 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+    temp_file.write_all(&data).expect("Failed to write");
+    temp_file.flush().expect("Failed to flush");
+
+    let mut updates = Vec::new();
+    let hashers = vec![
+        Hasher::new(HashAlgorithm::BLAKE3),
+        Hasher::new(HashAlgorithm::SHA256),
+    ];
+    let results = hash_file_with(temp_file.path(), hashers, |p| updates.push(p.bytes))
+        .expect("Failed to hash file");
+
+    assert_eq!(results[0], compute_blake3(&mut Cursor::new(&data)).unwrap());
+    assert_eq!(
+        results[1],
+        hash_file(temp_file.path(), HashAlgorithm::SHA256).unwrap()
+    );
+    assert_eq!(*updates.last().unwrap(), data.len() as u64);
+}
+
+#[test]
+fn test_read_strategies_agree() {
+    // Deliberately not a multiple of any buffer or block size.
+    let data: Vec<u8> = (0..5 * 1024 * 1024 + 12345)
+        .map(|i| (i % 253) as u8)
+        .collect();
+    let mut temp_file = NamedTempFile::new().expect("Failed to create temp file");
+    temp_file.write_all(&data).expect("Failed to write");
+    temp_file.flush().expect("Failed to flush");
+    let expected = compute_sha256(&mut Cursor::new(&data)).unwrap();
+
+    for strategy in [
+        ReadStrategy::Auto,
+        ReadStrategy::Mmap,
+        ReadStrategy::Read,
+        ReadStrategy::Direct,
+    ] {
+        for buffer_size in [1000, 64 * 1024, 8 * 1024 * 1024] {
+            let options = ReadOptions {
+                strategy,
+                buffer_size,
+                ..ReadOptions::default()
+            };
+            let mut last = 0;
+            let results = hash_file_with_options(
+                temp_file.path(),
+                vec![Hasher::new(HashAlgorithm::SHA256)],
+                &options,
+                |p| last = p.bytes,
+            )
+            .expect("Failed to hash file");
+            assert_eq!(results[0], expected, "{:?}", options);
+            assert_eq!(last, data.len() as u64, "{:?}", options);
+        }
+    }
+}
+
+#[test]
+fn test_read_strategies_on_empty_file() {
+    let temp_file = NamedTempFile::new().expect("Failed to create temp file");
+    let expected = compute_sha256(&mut Cursor::new(b"")).unwrap();
+    for strategy in [ReadStrategy::Mmap, ReadStrategy::Direct] {
+        let options = ReadOptions {
+            strategy,
+            ..ReadOptions::default()
+        };
+        let results = hash_file_with_options(
+            temp_file.path(),
+            vec![Hasher::new(HashAlgorithm::SHA256)],
+            &options,
+            |_| {},
+        )
+        .expect("Failed to hash file");
+        assert_eq!(results[0], expected);
+    }
+}
+
+#[test]
+fn test_hash_file_range() {
+    let mut temp_file = NamedTempFile::new().expect("Failed to create temp file");
+    temp_file.write_all(b"0123456789abcdef").unwrap();
+    temp_file.flush().unwrap();
+    let path = temp_file.path();
+    let sha256 = |data: &[u8]| compute_sha256(&mut Cursor::new(data)).unwrap();
+
+    let cases = [
+        (ByteRange::head(4), &b"0123"[..]),
+        (ByteRange::Tail(4), b"cdef"),
+        (
+            ByteRange::Slice {
+                offset: 4,
+                len: Some(6),
+            },
+            b"456789",
+        ),
+        (
+            ByteRange::Slice {
+                offset: 10,
+                len: None,
+            },
+            b"abcdef",
+        ),
+        (
+            ByteRange::Slice {
+                offset: 16,
+                len: None,
+            },
+            b"",
+        ),
+        (ByteRange::Tail(16), b"0123456789abcdef"),
+    ];
+    for (range, data) in cases {
+        let digest = hash_file_range(path, HashAlgorithm::SHA256, range).unwrap();
+        assert_eq!(digest, sha256(data), "{:?}", range);
+
+        let options = ReadOptions {
+            range: Some(range),
+            ..ReadOptions::default()
+        };
+        let results = hash_file_with_options(
+            path,
+            vec![Hasher::new(HashAlgorithm::SHA256)],
+            &options,
+            |_| {},
+        )
+        .unwrap();
+        assert_eq!(results[0], sha256(data), "{:?}", range);
+    }
+
+    // Ranges work on any seekable reader too.
+    let mut reader = ByteRange::Tail(3).apply(Cursor::new(b"abcdef")).unwrap();
+    assert_eq!(compute_sha256(&mut reader).unwrap(), sha256(b"def"));
+}
+
+#[test]
+fn test_hash_file_range_beyond_eof() {
+    let mut temp_file = NamedTempFile::new().expect("Failed to create temp file");
+    temp_file.write_all(b"0123456789abcdef").unwrap();
+    temp_file.flush().unwrap();
+
+    for range in [
+        ByteRange::head(17),
+        ByteRange::Tail(17),
+        ByteRange::Slice {
+            offset: 17,
+            len: None,
+        },
+        ByteRange::Slice {
+            offset: 10,
+            len: Some(7),
+        },
+        ByteRange::Slice {
+            offset: 1,
+            len: Some(u64::MAX),
+        },
+    ] {
+        let err = hash_file_range(temp_file.path(), HashAlgorithm::SHA256, range).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof, "{:?}", range);
+        assert!(err.to_string().contains("16"), "{}", err);
+    }
+}
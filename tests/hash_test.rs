@@ -1,5 +1,12 @@
-use file_hasher::{compute_md5, compute_sha1, compute_sha256};
-use std::io::Cursor;
+use file_hasher::{
+    compute_adler32, compute_blake2b, compute_blake2s, compute_blake3, compute_crc32,
+    compute_crc32c, compute_md5, compute_multi, compute_sha1, compute_sha224, compute_sha256,
+    compute_sha384, compute_sha3_224, compute_sha3_256, compute_sha3_384, compute_sha3_512,
+    compute_sha512, compute_sha512_224, compute_sha512_256, compute_shake128, compute_shake256,
+    compute_xxh128, compute_xxh3, compute_xxh64, hash_reader_with, Blake2Options, HashAlgorithm,
+    Hasher, Hmac,
+};
+use std::io::{self, Cursor};
 
 #[test]
 fn test_sha256_computation() {
@@ -38,3 +45,318 @@ fn test_empty_input() {
         "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
     );
 }
+
+#[test]
+fn test_incremental_hasher_matches_streaming() {
+    for algorithm in [
+        HashAlgorithm::SHA256,
+        HashAlgorithm::MD5,
+        HashAlgorithm::SHA1,
+    ] {
+        let mut hasher = Hasher::new(algorithm);
+        hasher.update(b"Hello, ");
+        hasher.update(b"World!");
+
+        let expected = compute_multi(&mut Cursor::new(b"Hello, World!"), &[algorithm]).unwrap();
+        assert_eq!(hasher.finalize(), expected[0]);
+    }
+}
+
+#[test]
+fn test_incremental_hasher_reset_and_clone() {
+    let mut hasher = Hasher::new(HashAlgorithm::SHA256);
+    hasher.update(b"garbage");
+    hasher.reset();
+    hasher.update(b"Hello, ");
+
+    let mut forked = hasher.clone();
+    forked.update(b"World!");
+    hasher.update(b"World!");
+
+    assert_eq!(hasher.finalize(), forked.finalize());
+}
+
+#[test]
+fn test_incremental_hasher_as_writer() {
+    let mut hasher = Hasher::new(HashAlgorithm::SHA256);
+    io::copy(&mut Cursor::new(b"Hello, World!"), &mut hasher).unwrap();
+    assert_eq!(
+        hasher.finalize(),
+        "dffd6021bb2bd5b0af676290809ec3a53191dd81c7f70a4b28688a362182986f"
+    );
+}
+
+#[test]
+fn test_sha2_family_computation() {
+    let data = b"Hello, World!";
+    let cases = [
+        (
+            compute_sha224(&mut Cursor::new(data)).unwrap(),
+            "72a23dfa411ba6fde01dbfabf3b00a709c93ebf273dc29e2d8b261ff",
+        ),
+        (
+            compute_sha384(&mut Cursor::new(data)).unwrap(),
+            "5485cc9b3365b4305dfb4e8337e0a598a574f8242bf17289e0dd6c20a3cd44a089de16ab4ab308f63e44b1170eb5f515",
+        ),
+        (
+            compute_sha512(&mut Cursor::new(data)).unwrap(),
+            "374d794a95cdcfd8b35993185fef9ba368f160d8daf432d08ba9f1ed1e5abe6cc69291e0fa2fe0006a52570ef18c19def4e617c33ce52ef0a6e5fbe318cb0387",
+        ),
+        (
+            compute_sha512_224(&mut Cursor::new(data)).unwrap(),
+            "766745f058e8a0438f19de48ae56ea5f123fe738af39bca050a7547a",
+        ),
+        (
+            compute_sha512_256(&mut Cursor::new(data)).unwrap(),
+            "0686f0a605973dc1bf035d1e2b9bad1985a0bff712ddd88abd8d2593e5f99030",
+        ),
+    ];
+
+    for (digest, expected) in cases {
+        assert_eq!(digest, expected);
+        assert_eq!(digest.as_bytes().len(), digest.algorithm().digest_len());
+        let multi = compute_multi(&mut Cursor::new(data), &[digest.algorithm()]).unwrap();
+        assert_eq!(multi[0], digest);
+    }
+}
+
+#[test]
+fn test_sha3_family_computation() {
+    let data = b"Hello, World!";
+    let cases = [
+        (
+            compute_sha3_224(&mut Cursor::new(data)).unwrap(),
+            "853048fb8b11462b6100385633c0cc8dcdc6e2b8e376c28102bc84f2",
+        ),
+        (
+            compute_sha3_256(&mut Cursor::new(data)).unwrap(),
+            "1af17a664e3fa8e419b8ba05c2a173169df76162a5a286e0c405b460d478f7ef",
+        ),
+        (
+            compute_sha3_384(&mut Cursor::new(data)).unwrap(),
+            "aa9ad8a49f31d2ddcabbb7010a1566417cff803fef50eba239558826f872e468c5743e7f026b0a8e5b2d7a1cc465cdbe",
+        ),
+        (
+            compute_sha3_512(&mut Cursor::new(data)).unwrap(),
+            "38e05c33d7b067127f217d8c856e554fcff09c9320b8a5979ce2ff5d95dd27ba35d1fba50c562dfd1d6cc48bc9c5baa4390894418cc942d968f97bcb659419ed",
+        ),
+        (
+            compute_shake128(&mut Cursor::new(data), 32).unwrap(),
+            "2bf5e6dee6079fad604f573194ba8426bd4d30eb13e8ba2edae70e529b570cbd",
+        ),
+        (
+            compute_shake256(&mut Cursor::new(data), 20).unwrap(),
+            "b3be97bfd978833a65588ceae8a34cf59e95585a",
+        ),
+    ];
+
+    for (digest, expected) in cases {
+        assert_eq!(digest, expected);
+        let multi = compute_multi(&mut Cursor::new(data), &[digest.algorithm()]).unwrap();
+        assert_eq!(multi[0].algorithm(), digest.algorithm());
+    }
+}
+
+#[test]
+fn test_shake_output_length() {
+    let mut hasher = Hasher::with_output_len(HashAlgorithm::SHAKE128, 8).unwrap();
+    assert_eq!(hasher.output_len(), 8);
+    hasher.update(b"garbage");
+    hasher.reset();
+    hasher.update(b"Hello, World!");
+    assert_eq!(hasher.finalize(), "2bf5e6dee6079fad");
+
+    let defaults = hash_reader_with(
+        &mut Cursor::new(b"Hello, World!"),
+        vec![
+            Hasher::new(HashAlgorithm::SHAKE128),
+            Hasher::new(HashAlgorithm::SHAKE256),
+        ],
+    )
+    .unwrap();
+    assert_eq!(defaults[0].as_bytes().len(), 32);
+    assert_eq!(defaults[1].as_bytes().len(), 64);
+
+    assert!(Hasher::with_output_len(HashAlgorithm::SHAKE256, 0).is_err());
+    assert!(Hasher::with_output_len(HashAlgorithm::SHA3_256, 16).is_err());
+}
+
+#[test]
+fn test_blake3_modes() {
+    // From the official BLAKE3 test vectors, empty input.
+    assert_eq!(
+        compute_blake3(&mut Cursor::new(b"")).unwrap(),
+        "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+    );
+
+    let mut keyed = Hasher::blake3_keyed(b"whats the Elvish word for friend");
+    keyed.update(b"garbage");
+    keyed.reset();
+    assert_eq!(
+        keyed.finalize(),
+        "92b2b75604ed3c761f9d6f62392c8a9227ad0ea3f09573e783f1498a4ed60d26"
+    );
+
+    let derived =
+        Hasher::blake3_derive_key("BLAKE3 2019-12-27 16:29:52 test vectors context").finalize();
+    assert_eq!(
+        derived,
+        "2cc39783c223154fea8dfb7c1b1660f2ac2dcbd1c1de8277b0b0dd39b7e50d7d"
+    );
+    assert_eq!(derived.algorithm(), HashAlgorithm::BLAKE3);
+}
+
+#[test]
+fn test_blake2_lengths_and_parameters() {
+    let data = b"Hello, World!";
+    assert_eq!(
+        compute_blake2b(&mut Cursor::new(data), 64).unwrap(),
+        "7dfdb888af71eae0e6a6b751e8e3413d767ef4fa52a7993daa9ef097f7aa3d949199c113caa37c94f80cf3b22f7d9d6e4f5def4ff927830cffe4857c34be3d89"
+    );
+    assert_eq!(
+        compute_blake2b(&mut Cursor::new(data), 32).unwrap(),
+        "511bc81dde11180838c562c82bb35f3223f46061ebde4a955c27b3f489cf1e03"
+    );
+    assert_eq!(
+        compute_blake2s(&mut Cursor::new(data), 32).unwrap(),
+        "ec9db904d636ef61f1421b2ba47112a4fa6b8964fd4a0a514834455c21df7812"
+    );
+    for (result, len) in [
+        (compute_blake2b(&mut Cursor::new(data), 0), 0),
+        (compute_blake2b(&mut Cursor::new(data), 65), 65),
+        (compute_blake2s(&mut Cursor::new(data), 33), 33),
+    ] {
+        let err = result.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput, "{}", len);
+    }
+
+    let options = Blake2Options {
+        output_len: Some(32),
+        key: b"secret".to_vec(),
+        salt: b"salty".to_vec(),
+        personalization: b"me".to_vec(),
+    };
+    let mut hasher = Hasher::blake2(HashAlgorithm::BLAKE2B, &options).unwrap();
+    hasher.update(b"garbage");
+    hasher.reset();
+    hasher.update(data);
+    assert_eq!(
+        hasher.finalize(),
+        "65e2fe0b415e21b656fa517b57828078793a56b208eac824d1eddc22a2bd6a29"
+    );
+
+    let options = Blake2Options {
+        key: vec![b'k'; 32],
+        salt: vec![1, 2],
+        personalization: b"app".to_vec(),
+        ..Default::default()
+    };
+    let mut hasher = Hasher::blake2(HashAlgorithm::BLAKE2S, &options).unwrap();
+    hasher.update(data);
+    assert_eq!(
+        hasher.finalize(),
+        "6727781b4c5fd420e747f7c07b1643ecf3585da43cfc43eabfa439bdaca178b3"
+    );
+}
+
+#[test]
+fn test_blake2_rejects_invalid_parameters() {
+    let too_long_salt = Blake2Options {
+        salt: vec![0; 9],
+        ..Default::default()
+    };
+    assert!(Hasher::blake2(HashAlgorithm::BLAKE2S, &too_long_salt).is_err());
+    assert!(Hasher::blake2(HashAlgorithm::BLAKE2B, &too_long_salt).is_ok());
+    assert!(Hasher::blake2(HashAlgorithm::SHA256, &Blake2Options::default()).is_err());
+    assert!(Hasher::with_output_len(HashAlgorithm::BLAKE2S, 33).is_err());
+    assert_eq!(
+        HashAlgorithm::BLAKE2B.output_len_range(),
+        1..=HashAlgorithm::BLAKE2B.digest_len()
+    );
+
+    // Every length collides with another algorithm, so it is never guessed
+    assert!(!HashAlgorithm::candidates_for_digest_len(64).contains(&HashAlgorithm::BLAKE2B));
+}
+
+#[test]
+fn test_non_cryptographic_checksums() {
+    // The standard "123456789" check values, and xxHash of empty input
+    let check: &[u8] = b"123456789";
+    let empty: &[u8] = b"";
+    let cases = [
+        (HashAlgorithm::CRC32, check, "cbf43926"),
+        (HashAlgorithm::CRC32C, check, "e3069283"),
+        (HashAlgorithm::ADLER32, check, "091e01de"),
+        (HashAlgorithm::XXH64, empty, "ef46db3751d8e999"),
+        (HashAlgorithm::XXH3, empty, "2d06800538d394c2"),
+        (
+            HashAlgorithm::XXH128,
+            empty,
+            "99aa06d3014798d86001c324468d497f",
+        ),
+    ];
+
+    for (algorithm, data, expected) in cases {
+        let digest = match algorithm {
+            HashAlgorithm::CRC32 => compute_crc32(&mut Cursor::new(data)),
+            HashAlgorithm::CRC32C => compute_crc32c(&mut Cursor::new(data)),
+            HashAlgorithm::ADLER32 => compute_adler32(&mut Cursor::new(data)),
+            HashAlgorithm::XXH64 => compute_xxh64(&mut Cursor::new(data)),
+            HashAlgorithm::XXH3 => compute_xxh3(&mut Cursor::new(data)),
+            _ => compute_xxh128(&mut Cursor::new(data)),
+        }
+        .unwrap();
+        assert_eq!(digest, expected);
+        assert!(!algorithm.is_cryptographic());
+
+        let mut hasher = Hasher::new(algorithm);
+        hasher.update(data);
+        assert_eq!(hasher.finalize(), digest);
+    }
+
+    assert!(HashAlgorithm::MD5.is_cryptographic());
+    assert!(HashAlgorithm::BLAKE3.is_cryptographic());
+}
+
+#[test]
+fn test_hmac_rfc_4231_vectors() {
+    let mut hmac = Hmac::new(HashAlgorithm::SHA256, b"Jefe").unwrap();
+    hmac.update(b"what do ya want for nothing?");
+    assert_eq!(
+        hmac.finalize().to_hex(),
+        "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+    );
+
+    let mut hmac = Hmac::new(HashAlgorithm::SHA512, &[0x0b; 20]).unwrap();
+    hmac.update(b"Hi There");
+    let expected = hmac.clone().finalize();
+    assert_eq!(
+        expected.to_hex(),
+        "87aa7cdea5ef619d4ff0b4241a1d6cb02379f4e2ce4ec2787ad0b30545e17cde\
+         daa833b7d6b8a702038b274eaea3f4e4be9d914eeb61f1702e696c203a126854"
+    );
+    assert!(hmac.verify(&expected));
+
+    // The key survives a reset, like every other Hasher parameter.
+    let mut hasher = Hasher::from(Hmac::new(HashAlgorithm::SHA1, b"Jefe").unwrap());
+    hasher.update(b"unrelated");
+    hasher.reset();
+    hasher.update(b"what do ya want for nothing?");
+    assert_eq!(
+        hasher.finalize().to_hex(),
+        "effcdf6ae5eb2fa2d27416d5f184df9c259a7c79"
+    );
+}
+
+#[test]
+fn test_hmac_rejects_other_algorithms() {
+    for algorithm in [
+        HashAlgorithm::MD5,
+        HashAlgorithm::BLAKE3,
+        HashAlgorithm::SHA3_256,
+    ] {
+        assert!(!Hmac::supports(algorithm));
+        let err = Hmac::new(algorithm, b"key").err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -20,9 +20,9 @@ fn test_real_file_hashing() {
     let sha1 = hash_file(&file_path, HashAlgorithm::SHA1).unwrap();
 
     // Verify hash lengths
-    assert_eq!(sha256.len(), 64);
-    assert_eq!(md5.len(), 32);
-    assert_eq!(sha1.len(), 40);
+    assert_eq!(sha256.to_hex().len(), 64);
+    assert_eq!(md5.to_hex().len(), 32);
+    assert_eq!(sha1.to_hex().len(), 40);
 
     // Verify actual hash values
     assert_eq!(
@@ -114,7 +114,7 @@ fn test_large_file_streaming_integration() {
     let duration = start.elapsed();
 
     // Verify hash is correct
-    assert_eq!(hash.len(), 64);
+    assert_eq!(hash.to_hex().len(), 64);
 
     // Ensure it completed in reasonable time (less than 10 seconds)
     assert!(
@@ -132,11 +132,11 @@ fn test_fixture_files() {
 
     if small_path.exists() {
         let hash = hash_file(&small_path, HashAlgorithm::SHA256).unwrap();
-        assert_eq!(hash.len(), 64);
+        assert_eq!(hash.to_hex().len(), 64);
     }
 
     if medium_path.exists() {
         let hash = hash_file(&medium_path, HashAlgorithm::SHA256).unwrap();
-        assert_eq!(hash.len(), 64);
+        assert_eq!(hash.to_hex().len(), 64);
     }
 }
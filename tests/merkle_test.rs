@@ -0,0 +1,189 @@
+use file_hasher::{
+    Digest, HashAlgorithm, Hasher, MerkleOptions, MerkleTree, ReadOptions, ReadStrategy,
+};
+use std::io::{Cursor, Write};
+use tempfile::NamedTempFile;
+
+fn data(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8).collect()
+}
+
+fn sha256(parts: &[&[u8]]) -> Digest {
+    let mut hasher = Hasher::new(HashAlgorithm::SHA256);
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize()
+}
+
+fn options(leaf_size: u64, fan_out: usize) -> MerkleOptions {
+    MerkleOptions { leaf_size, fan_out }
+}
+
+#[test]
+fn test_merkle_root_uses_domain_separation() {
+    let data = data(2500);
+    let tree = MerkleTree::from_reader(
+        Cursor::new(&data),
+        Hasher::new(HashAlgorithm::SHA256),
+        &options(1000, 2),
+    )
+    .unwrap();
+
+    let leaves: Vec<Digest> = data
+        .chunks(1000)
+        .map(|chunk| sha256(&[&[0x00], chunk]))
+        .collect();
+    assert_eq!(tree.leaves(), &leaves[..]);
+    let left = sha256(&[&[0x01], leaves[0].as_bytes(), leaves[1].as_bytes()]);
+    let right = sha256(&[&[0x01], leaves[2].as_bytes()]);
+    let root = sha256(&[&[0x01], left.as_bytes(), right.as_bytes()]);
+    assert_eq!(tree.root(), &root);
+}
+
+#[test]
+fn test_merkle_single_and_empty_inputs() {
+    let hasher = Hasher::new(HashAlgorithm::SHA256);
+
+    let empty = MerkleTree::from_reader(Cursor::new(b""), hasher.clone(), &options(16, 2)).unwrap();
+    assert_eq!(empty.leaf_count(), 1);
+    assert_eq!(empty.root(), &sha256(&[&[0x00]]));
+
+    let single =
+        MerkleTree::from_reader(Cursor::new(b"abc"), hasher.clone(), &options(16, 2)).unwrap();
+    assert_eq!(single.root(), &sha256(&[&[0x00], b"abc"]));
+    let proof = single.proof(0).unwrap();
+    assert!(proof.path.is_empty());
+    assert!(proof.verify(b"abc", single.root(), &hasher));
+    assert!(single.proof(1).is_none());
+}
+
+#[test]
+fn test_merkle_proofs_verify_for_every_leaf() {
+    let data = data(10_000);
+    let hasher = Hasher::new(HashAlgorithm::BLAKE3);
+    for fan_out in [2, 3, 4, 16] {
+        let tree =
+            MerkleTree::from_reader(Cursor::new(&data), hasher.clone(), &options(512, fan_out))
+                .unwrap();
+        assert_eq!(tree.leaf_count(), 20);
+        for (index, leaf) in data.chunks(512).enumerate() {
+            let proof = tree.proof(index).unwrap();
+            assert_eq!(proof.leaf_offset(), Some(index as u64 * 512));
+            assert!(proof.verify(leaf, tree.root(), &hasher), "leaf {}", index);
+
+            let mut tampered = leaf.to_vec();
+            tampered[0] ^= 1;
+            assert!(!proof.verify(&tampered, tree.root(), &hasher));
+        }
+    }
+}
+
+#[test]
+fn test_merkle_proof_rejects_forgeries() {
+    let data = data(4096);
+    let hasher = Hasher::new(HashAlgorithm::SHA256);
+    let tree =
+        MerkleTree::from_reader(Cursor::new(&data), hasher.clone(), &options(1024, 2)).unwrap();
+    let leaf = &data[1024..2048];
+    let proof = tree.proof(1).unwrap();
+
+    // The leaf proven at the wrong index.
+    let mut moved = proof.clone();
+    moved.leaf_index = 0;
+    assert!(!moved.verify(leaf, tree.root(), &hasher));
+
+    // Parameters no tree can have.
+    for bad in [options(1024, 0), options(1024, 1), options(0, 2)] {
+        let mut broken = proof.clone();
+        broken.options = bad;
+        assert!(!broken.verify(leaf, tree.root(), &hasher));
+    }
+    let mut far = proof.clone();
+    far.leaf_index = usize::MAX;
+    assert_eq!(far.leaf_offset(), None);
+
+    // A changed sibling.
+    let mut forged = proof.clone();
+    forged.path[1].siblings[0] = sha256(&[b"forged"]);
+    assert!(!forged.verify(leaf, tree.root(), &hasher));
+
+    // The children of an inner node passed off as leaf data, with the
+    // proof shortened by one level: the 0x00/0x01 prefixes tell them apart.
+    let leaves = tree.leaves();
+    let node_preimage = [leaves[0].as_bytes(), leaves[1].as_bytes()].concat();
+    let mut lifted = tree.proof(0).unwrap();
+    lifted.path.remove(0);
+    assert!(!lifted.verify(&node_preimage, tree.root(), &hasher));
+
+    // Wrong root or wrong hasher.
+    assert!(!proof.verify(leaf, &sha256(&[b"root"]), &hasher));
+    assert!(!proof.verify(leaf, tree.root(), &Hasher::new(HashAlgorithm::SHA512_256)));
+}
+
+#[test]
+fn test_merkle_from_file_matches_from_reader() {
+    let data = data(5 * 1024 * 1024 + 7);
+    let mut temp_file = NamedTempFile::new().unwrap();
+    temp_file.write_all(&data).unwrap();
+    temp_file.flush().unwrap();
+
+    let hasher = Hasher::new(HashAlgorithm::SHA256);
+    let options = options(64 * 1024, 4);
+    let from_reader =
+        MerkleTree::from_reader(Cursor::new(&data), hasher.clone(), &options).unwrap();
+    for jobs in [1, 0] {
+        let from_file =
+            MerkleTree::from_file(temp_file.path(), hasher.clone(), &options, jobs).unwrap();
+        assert_eq!(from_file.root(), from_reader.root());
+        assert_eq!(from_file.leaf_count(), 81);
+    }
+}
+
+#[test]
+fn test_merkle_from_file_with_read_options_reports_progress() {
+    let data = data(5 * 1024 * 1024 + 7);
+    let mut temp_file = NamedTempFile::new().unwrap();
+    temp_file.write_all(&data).unwrap();
+    temp_file.flush().unwrap();
+
+    let hasher = Hasher::new(HashAlgorithm::SHA256);
+    let options = options(64 * 1024, 4);
+    let expected = MerkleTree::from_reader(Cursor::new(&data), hasher.clone(), &options).unwrap();
+    for strategy in [
+        ReadStrategy::Auto,
+        ReadStrategy::Mmap,
+        ReadStrategy::Read,
+        ReadStrategy::Direct,
+    ] {
+        let read_options = ReadOptions {
+            strategy,
+            buffer_size: 10_000,
+            ..Default::default()
+        };
+        let mut reported = Vec::new();
+        let tree = MerkleTree::from_file_with_options(
+            temp_file.path(),
+            hasher.clone(),
+            &options,
+            0,
+            &read_options,
+            |progress| reported.push(progress.bytes),
+        )
+        .unwrap();
+        assert_eq!(tree.root(), expected.root(), "{:?}", strategy);
+        assert!(reported.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(reported.last(), Some(&(data.len() as u64)));
+    }
+}
+
+#[test]
+fn test_merkle_invalid_options() {
+    let hasher = Hasher::new(HashAlgorithm::SHA256);
+    for bad in [options(0, 2), options(1024, 1)] {
+        let err = MerkleTree::from_reader(Cursor::new(b"x"), hasher.clone(), &bad)
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,121 @@
+use file_hasher::walk::WalkOptions;
+use file_hasher::{HashAlgorithm, Snapshot, SnapshotEntry};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+use tempfile::tempdir;
+
+fn make_tree(root: &Path) {
+    fs::create_dir_all(root.join("lib")).unwrap();
+    fs::write(root.join("app.bin"), b"application").unwrap();
+    fs::write(root.join("lib/core.so"), b"core library").unwrap();
+    fs::write(root.join("config.toml"), b"debug = false").unwrap();
+    fs::write(root.join("run.log"), b"started").unwrap();
+}
+
+fn paths(entries: &[SnapshotEntry]) -> Vec<&str> {
+    entries.iter().map(|e| e.path.as_str()).collect()
+}
+
+fn snapshot(root: &Path) -> Snapshot {
+    let options = WalkOptions {
+        exclude: vec!["*.log".to_string()],
+        ..Default::default()
+    };
+    Snapshot::new(root, HashAlgorithm::SHA256, &options, 2).unwrap()
+}
+
+#[test]
+fn test_snapshot_manifest_round_trip() {
+    let dir = tempdir().unwrap();
+    make_tree(dir.path());
+    let snapshot = snapshot(dir.path());
+    assert_eq!(
+        paths(&snapshot.entries),
+        ["app.bin", "config.toml", "lib/core.so"]
+    );
+    assert_eq!(snapshot.entries[0].size, 11);
+
+    let manifest = snapshot.to_manifest();
+    assert!(manifest.starts_with('{'));
+    assert!(manifest.lines().next().unwrap().contains("\"version\":1"));
+    let parsed = Snapshot::parse(&manifest).unwrap();
+    assert_eq!(parsed.algorithm, HashAlgorithm::SHA256);
+    assert_eq!(parsed.exclude, ["*.log"]);
+    assert_eq!(parsed.entries, snapshot.entries);
+    assert!(parsed.diff(dir.path(), 1, false).unwrap().is_clean());
+}
+
+#[test]
+fn test_snapshot_diff_reports_drift() {
+    let dir = tempdir().unwrap();
+    make_tree(dir.path());
+    let snapshot = snapshot(dir.path());
+
+    fs::write(dir.path().join("config.toml"), b"debug = true").unwrap();
+    fs::rename(dir.path().join("lib/core.so"), dir.path().join("core.so")).unwrap();
+    fs::remove_file(dir.path().join("app.bin")).unwrap();
+    fs::write(dir.path().join("backdoor.sh"), b"#!/bin/sh").unwrap();
+    // Excluded when the snapshot was taken, so still ignored.
+    fs::write(dir.path().join("run.log"), b"started twice").unwrap();
+
+    let drift = snapshot.diff(dir.path(), 1, false).unwrap();
+    assert!(!drift.is_clean());
+    assert_eq!(paths(&drift.added), ["backdoor.sh"]);
+    assert_eq!(paths(&drift.removed), ["app.bin"]);
+    assert_eq!(drift.modified.len(), 1);
+    assert_eq!(drift.modified[0].0.path, "config.toml");
+    assert_ne!(drift.modified[0].0.digest, drift.modified[0].1.digest);
+    assert_eq!(drift.renamed.len(), 1);
+    assert_eq!(drift.renamed[0].0.path, "lib/core.so");
+    assert_eq!(drift.renamed[0].1.path, "core.so");
+}
+
+#[test]
+fn test_snapshot_diff_trust_mtime() {
+    let dir = tempdir().unwrap();
+    make_tree(dir.path());
+    let file = dir.path().join("config.toml");
+    let mtime = SystemTime::now() - Duration::from_secs(3600);
+    fs::File::options()
+        .write(true)
+        .open(&file)
+        .unwrap()
+        .set_modified(mtime)
+        .unwrap();
+    let snapshot = snapshot(dir.path());
+
+    // Same size and mtime, different contents: only a full rehash notices.
+    fs::write(&file, b"debug = fals3").unwrap();
+    fs::File::options()
+        .write(true)
+        .open(&file)
+        .unwrap()
+        .set_modified(mtime)
+        .unwrap();
+    assert!(snapshot.diff(dir.path(), 1, true).unwrap().is_clean());
+    assert_eq!(
+        snapshot.diff(dir.path(), 1, false).unwrap().modified.len(),
+        1
+    );
+}
+
+#[test]
+fn test_snapshot_parse_rejects_bad_manifests() {
+    let header = r#"{"format":"file-hasher snapshot","version":1,"algorithm":"SHA256"}"#;
+    let entry = r#"{"path":"a","size":1,"mtime":0,"digest":"00"}"#;
+    for text in [
+        "",
+        "not json",
+        r#"{"format":"something else","version":1,"algorithm":"SHA256"}"#,
+        r#"{"format":"file-hasher snapshot","version":2,"algorithm":"SHA256"}"#,
+        r#"{"format":"file-hasher snapshot","version":1,"algorithm":"nope"}"#,
+        &format!("{}\n{}", header, entry),
+        &format!("{}\n{{\"path\":\"a\"}}", header),
+    ] {
+        let err = Snapshot::parse(text).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidData, "{:?}", text);
+    }
+    assert!(Snapshot::parse(header).unwrap().entries.is_empty());
+}
@@ -0,0 +1,177 @@
+use file_hasher::walk::WalkOptions;
+use file_hasher::{
+    hash_file, Digest, HashAlgorithm, Hasher, Tree, TreeEntry, TreeEntryKind, TreeOptions,
+};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::tempdir;
+
+fn sha256(parts: &[&[u8]]) -> Digest {
+    let mut hasher = Hasher::new(HashAlgorithm::SHA256);
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize()
+}
+
+fn tree_digest(root: &Path, options: &TreeOptions) -> Digest {
+    Tree::from_dir(root, &Hasher::new(HashAlgorithm::SHA256), options, 1)
+        .unwrap()
+        .digest()
+        .clone()
+}
+
+#[test]
+fn test_tree_encoding() {
+    let dir = tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("a/b")).unwrap();
+    fs::write(dir.path().join("a/b/x"), b"hi\n").unwrap();
+    fs::write(dir.path().join("a.y"), b"yo\n").unwrap();
+
+    // "a.y" sorts before "a/b/x" because '.' < '/'.
+    let y = sha256(&[b"yo\n"]);
+    let x = sha256(&[b"hi\n"]);
+    let expected = sha256(&[
+        b"file-hasher tree v1\0",
+        &[0],
+        b"f",
+        &3u64.to_be_bytes(),
+        b"a.y",
+        &32u64.to_be_bytes(),
+        y.as_bytes(),
+        b"f",
+        &5u64.to_be_bytes(),
+        b"a/b/x",
+        &32u64.to_be_bytes(),
+        x.as_bytes(),
+    ]);
+    let tree = Tree::from_dir(
+        dir.path(),
+        &Hasher::new(HashAlgorithm::SHA256),
+        &TreeOptions::default(),
+        2,
+    )
+    .unwrap();
+    assert_eq!(tree.digest(), &expected);
+    let paths: Vec<&Path> = tree.entries().iter().map(|e| e.path.as_path()).collect();
+    assert_eq!(paths, [Path::new("a.y"), Path::new("a/b/x")]);
+    assert_eq!(
+        tree.entries()[1].kind,
+        TreeEntryKind::File(hash_file(&dir.path().join("a/b/x"), HashAlgorithm::SHA256).unwrap())
+    );
+}
+
+#[test]
+fn test_tree_is_independent_of_entry_order() {
+    let entries: Vec<TreeEntry> = ["z", "a/1", "a/2", "m"]
+        .iter()
+        .map(|name| TreeEntry {
+            path: PathBuf::from(name),
+            mode: None,
+            kind: TreeEntryKind::File(sha256(&[name.as_bytes()])),
+        })
+        .collect();
+    let hasher = Hasher::new(HashAlgorithm::BLAKE3);
+    let options = TreeOptions::default();
+    let forward = Tree::from_entries(entries.clone(), &hasher, &options);
+    let mut reversed = entries;
+    reversed.reverse();
+    let backward = Tree::from_entries(reversed, &hasher, &options);
+    assert_eq!(forward.digest(), backward.digest());
+    assert_eq!(forward.entries(), backward.entries());
+
+    // Two directories built in a different order hash the same.
+    let first = tempdir().unwrap();
+    let second = tempdir().unwrap();
+    for name in ["b", "a", "c"] {
+        fs::write(first.path().join(name), name).unwrap();
+    }
+    for name in ["c", "b", "a"] {
+        fs::write(second.path().join(name), name).unwrap();
+    }
+    assert_eq!(
+        tree_digest(first.path(), &options),
+        tree_digest(second.path(), &options)
+    );
+}
+
+#[test]
+fn test_tree_changes() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("file"), b"contents").unwrap();
+    let options = TreeOptions::default();
+    let original = tree_digest(dir.path(), &options);
+
+    fs::rename(dir.path().join("file"), dir.path().join("renamed")).unwrap();
+    let renamed = tree_digest(dir.path(), &options);
+    assert_ne!(original, renamed);
+
+    fs::write(dir.path().join("renamed"), b"Contents").unwrap();
+    assert_ne!(tree_digest(dir.path(), &options), renamed);
+}
+
+#[test]
+fn test_tree_directories_option() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("file"), b"contents").unwrap();
+    let with_dirs = TreeOptions {
+        walk: WalkOptions {
+            directories: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let without = tree_digest(dir.path(), &TreeOptions::default());
+    let with = tree_digest(dir.path(), &with_dirs);
+    assert_ne!(without, with);
+
+    fs::create_dir(dir.path().join("empty")).unwrap();
+    assert_eq!(tree_digest(dir.path(), &TreeOptions::default()), without);
+    assert_ne!(tree_digest(dir.path(), &with_dirs), with);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_tree_mode_option() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempdir().unwrap();
+    let file = dir.path().join("script.sh");
+    fs::write(&file, b"#!/bin/sh\n").unwrap();
+    fs::set_permissions(&file, fs::Permissions::from_mode(0o644)).unwrap();
+    let with_mode = TreeOptions {
+        mode: true,
+        ..Default::default()
+    };
+    let plain = tree_digest(dir.path(), &TreeOptions::default());
+    let moded = tree_digest(dir.path(), &with_mode);
+
+    fs::set_permissions(&file, fs::Permissions::from_mode(0o755)).unwrap();
+    assert_eq!(tree_digest(dir.path(), &TreeOptions::default()), plain);
+    assert_ne!(tree_digest(dir.path(), &with_mode), moded);
+
+    let tree = Tree::from_dir(
+        dir.path(),
+        &Hasher::new(HashAlgorithm::SHA256),
+        &with_mode,
+        1,
+    )
+    .unwrap();
+    assert_eq!(tree.entries()[0].mode, Some(0o755));
+}
+
+#[test]
+fn test_tree_needs_a_directory() {
+    let dir = tempdir().unwrap();
+    let file = dir.path().join("file");
+    fs::write(&file, b"contents").unwrap();
+    let err = Tree::from_dir(
+        &file,
+        &Hasher::new(HashAlgorithm::SHA256),
+        &TreeOptions::default(),
+        1,
+    )
+    .err()
+    .unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
@@ -18,7 +18,7 @@ fn test_verify_correct_hash() {
 
     Command::cargo_bin("file-hasher")
         .unwrap()
-        .args(&["--verify", correct_hash, file_path.to_str().unwrap()])
+        .args(["--verify", correct_hash, file_path.to_str().unwrap()])
         .assert()
         .success()
         .stdout(predicate::str::contains("MATCH"));
@@ -37,7 +37,7 @@ fn test_verify_incorrect_hash() {
 
     Command::cargo_bin("file-hasher")
         .unwrap()
-        .args(&["--verify", incorrect_hash, file_path.to_str().unwrap()])
+        .args(["--verify", incorrect_hash, file_path.to_str().unwrap()])
         .assert()
         .failure()
         .stdout(predicate::str::contains("MISMATCH"));
@@ -57,7 +57,7 @@ fn test_verify_with_algorithm() {
 
     Command::cargo_bin("file-hasher")
         .unwrap()
-        .args(&[
+        .args([
             "--algorithm",
             "md5",
             "--verify",
@@ -81,7 +81,7 @@ fn test_verify_multiple_files() {
     // This should fail as we can't verify one hash against multiple files
     Command::cargo_bin("file-hasher")
         .unwrap()
-        .args(&[
+        .args([
             "--verify",
             "somehash",
             file1.to_str().unwrap(),
@@ -91,3 +91,149 @@ fn test_verify_multiple_files() {
         .failure()
         .stderr(predicate::str::contains("multiple files"));
 }
+
+#[test]
+fn test_verify_json_output() {
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("test.txt");
+    File::create(&file_path)
+        .unwrap()
+        .write_all(b"Hello, World!")
+        .unwrap();
+
+    let incorrect_hash = "0000000000000000000000000000000000000000000000000000000000000000";
+    let output = Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args([
+            "--format",
+            "ndjson",
+            "--verify",
+            incorrect_hash,
+            file_path.to_str().unwrap(),
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+
+    let record: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(record["expected"], incorrect_hash);
+    assert_eq!(
+        record["computed"],
+        "dffd6021bb2bd5b0af676290809ec3a53191dd81c7f70a4b28688a362182986f"
+    );
+    assert_eq!(record["match"], false);
+    assert_eq!(record["algorithm"], "SHA256");
+}
+
+#[test]
+fn test_verify_detects_sha2_variants_by_length() {
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("test.txt");
+    File::create(&file_path)
+        .unwrap()
+        .write_all(b"Hello, World!")
+        .unwrap();
+
+    // SHA-512/256 shares its length with SHA-256
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args([
+            "--verify",
+            "0686f0a605973dc1bf035d1e2b9bad1985a0bff712ddd88abd8d2593e5f99030",
+            file_path.to_str().unwrap(),
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("SHA512/256"));
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args([
+            "--verify",
+            "374d794a95cdcfd8b35993185fef9ba368f160d8daf432d08ba9f1ed1e5abe6cc69291e0fa2fe0006a52570ef18c19def4e617c33ce52ef0a6e5fbe318cb0387",
+            file_path.to_str().unwrap(),
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("SHA512"));
+
+    // An explicit algorithm disables the fallback
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args([
+            "--algorithm",
+            "sha256",
+            "--verify",
+            "0686f0a605973dc1bf035d1e2b9bad1985a0bff712ddd88abd8d2593e5f99030",
+            file_path.to_str().unwrap(),
+        ])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("MISMATCH"));
+}
+
+#[test]
+fn test_verify_blake2_requires_explicit_algorithm() {
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("test.txt");
+    std::fs::write(&file_path, b"Hello, World!").unwrap();
+    let blake2b_256 = "511bc81dde11180838c562c82bb35f3223f46061ebde4a955c27b3f489cf1e03";
+
+    // Taken for a 256-bit SHA-2/SHA-3/BLAKE3 digest without --algorithm
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["--verify", blake2b_256])
+        .arg(&file_path)
+        .assert()
+        .failure();
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["--algorithm", "blake2b", "--verify", blake2b_256])
+        .arg(&file_path)
+        .assert()
+        .success();
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args([
+            "--algorithm",
+            "blake2b",
+            "--length",
+            "512",
+            "--verify",
+            blake2b_256,
+        ])
+        .arg(&file_path)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("256 bits"));
+}
+
+#[test]
+fn test_verify_warns_about_non_cryptographic_checksums() {
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("test.txt");
+    std::fs::write(&file_path, b"Hello, World!").unwrap();
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args(["--algorithm", "crc32", "--verify", "ec4ac3d0"])
+        .arg(&file_path)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "CRC32 is not a cryptographic hash",
+        ));
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .args([
+            "--verify",
+            "dffd6021bb2bd5b0af676290809ec3a53191dd81c7f70a4b28688a362182986f",
+        ])
+        .arg(&file_path)
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty());
+}
@@ -0,0 +1,180 @@
+use assert_cmd::Command;
+use file_hasher::walk::{walk, SymlinkPolicy, WalkEntry, WalkOptions};
+use predicates::prelude::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::tempdir;
+
+fn make_tree(root: &Path) {
+    fs::create_dir_all(root.join("src/sub")).unwrap();
+    fs::create_dir_all(root.join("target")).unwrap();
+    fs::write(root.join("src/main.rs"), b"fn main() {}").unwrap();
+    fs::write(root.join("src/sub/lib.rs"), b"// lib").unwrap();
+    fs::write(root.join("target/out.bin"), b"\x00\x01").unwrap();
+    fs::write(root.join("notes.md"), b"notes").unwrap();
+    fs::write(root.join(".gitignore"), b"*.md\n").unwrap();
+}
+
+fn relative_paths(root: &Path, entries: &[WalkEntry]) -> Vec<PathBuf> {
+    entries
+        .iter()
+        .map(|e| e.path().strip_prefix(root).unwrap().to_path_buf())
+        .collect()
+}
+
+#[test]
+fn test_walk_is_sorted_and_complete() {
+    let dir = tempdir().unwrap();
+    make_tree(dir.path());
+
+    let (entries, errors) = walk(dir.path(), &WalkOptions::default()).unwrap();
+    assert!(errors.is_empty());
+    assert_eq!(
+        relative_paths(dir.path(), &entries),
+        vec![
+            PathBuf::from(".gitignore"),
+            PathBuf::from("notes.md"),
+            PathBuf::from("src/main.rs"),
+            PathBuf::from("src/sub/lib.rs"),
+            PathBuf::from("target/out.bin"),
+        ]
+    );
+}
+
+#[test]
+fn test_walk_include_exclude_and_gitignore() {
+    let dir = tempdir().unwrap();
+    make_tree(dir.path());
+
+    let options = WalkOptions {
+        include: vec!["*.rs".to_string()],
+        exclude: vec!["src/sub".to_string()],
+        ..Default::default()
+    };
+    let (entries, _) = walk(dir.path(), &options).unwrap();
+    assert_eq!(
+        relative_paths(dir.path(), &entries),
+        vec![PathBuf::from("src/main.rs")]
+    );
+
+    let options = WalkOptions {
+        exclude: vec!["target".to_string()],
+        gitignore: true,
+        ..Default::default()
+    };
+    let (entries, _) = walk(dir.path(), &options).unwrap();
+    assert_eq!(
+        relative_paths(dir.path(), &entries),
+        vec![
+            PathBuf::from(".gitignore"),
+            PathBuf::from("src/main.rs"),
+            PathBuf::from("src/sub/lib.rs"),
+        ]
+    );
+}
+
+#[test]
+fn test_walk_gitignore_skips_git_dir() {
+    let dir = tempdir().unwrap();
+    make_tree(dir.path());
+    fs::create_dir_all(dir.path().join(".git/info")).unwrap();
+    fs::write(dir.path().join(".git/config"), b"[core]").unwrap();
+    fs::write(dir.path().join(".git/info/exclude"), b"target\n").unwrap();
+
+    let options = WalkOptions {
+        gitignore: true,
+        ..Default::default()
+    };
+    let (entries, _) = walk(dir.path(), &options).unwrap();
+    assert_eq!(
+        relative_paths(dir.path(), &entries),
+        vec![
+            PathBuf::from(".gitignore"),
+            PathBuf::from("src/main.rs"),
+            PathBuf::from("src/sub/lib.rs"),
+        ]
+    );
+
+    // Without --gitignore, .git is just another directory.
+    let (entries, _) = walk(dir.path(), &WalkOptions::default()).unwrap();
+    assert!(relative_paths(dir.path(), &entries).contains(&PathBuf::from(".git/config")));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_walk_symlink_policies() {
+    let dir = tempdir().unwrap();
+    make_tree(dir.path());
+    std::os::unix::fs::symlink("src/main.rs", dir.path().join("link.rs")).unwrap();
+
+    let only_rs = |symlinks| WalkOptions {
+        include: vec!["*.rs".to_string()],
+        symlinks,
+        ..Default::default()
+    };
+
+    let (skipped, _) = walk(dir.path(), &only_rs(SymlinkPolicy::Skip)).unwrap();
+    assert_eq!(skipped.len(), 2);
+
+    let (followed, _) = walk(dir.path(), &only_rs(SymlinkPolicy::Follow)).unwrap();
+    assert_eq!(followed.len(), 3);
+    assert!(matches!(followed[0], WalkEntry::File(_)));
+
+    let (targets, _) = walk(dir.path(), &only_rs(SymlinkPolicy::HashTarget)).unwrap();
+    assert_eq!(
+        targets[0],
+        WalkEntry::Symlink {
+            path: dir.path().join("link.rs"),
+            target: PathBuf::from("src/main.rs"),
+        }
+    );
+}
+
+#[test]
+fn test_cli_recursive() {
+    let dir = tempdir().unwrap();
+    make_tree(dir.path());
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .current_dir(dir.path())
+        .args([
+            "--recursive",
+            "--format",
+            "gnu",
+            "--exclude",
+            "target",
+            "src",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("src/main.rs"))
+        .stdout(predicate::str::contains("src/sub/lib.rs"))
+        .stdout(predicate::str::contains("target").not());
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .current_dir(dir.path())
+        .arg("src")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--recursive"));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_cli_recursive_non_utf8_names() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join(OsStr::from_bytes(b"bad\xff")), b"data").unwrap();
+
+    Command::cargo_bin("file-hasher")
+        .unwrap()
+        .current_dir(dir.path())
+        .args(["--recursive", "--format", "gnu", "."])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("bad\u{fffd}"));
+}
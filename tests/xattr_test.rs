@@ -0,0 +1,181 @@
+use file_hasher::xattr::storable;
+use file_hasher::{
+    hash_file, hash_file_with_xattrs, read_xattr, write_xattr, HashAlgorithm, Hasher, ReadOptions,
+    StoredDigest, XattrOptions,
+};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::ErrorKind;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+use tempfile::tempdir;
+
+/// Whether `path`'s filesystem takes `user.` attributes; tests that need
+/// them pass trivially where it doesn't.
+fn xattrs_supported(path: &Path) -> bool {
+    let probe = StoredDigest {
+        digest: hash_file(path, HashAlgorithm::CRC32).unwrap(),
+        mtime: SystemTime::UNIX_EPOCH,
+    };
+    match write_xattr(path, &probe) {
+        Ok(()) => true,
+        Err(e)
+            if matches!(
+                e.kind(),
+                ErrorKind::Unsupported | ErrorKind::PermissionDenied
+            ) =>
+        {
+            false
+        }
+        Err(e) => panic!("{}", e),
+    }
+}
+
+/// Writes `contents` and backdates the file past the racy window.
+fn write_old(path: &Path, contents: &[u8]) {
+    fs::write(path, contents).unwrap();
+    File::options()
+        .append(true)
+        .open(path)
+        .unwrap()
+        .set_modified(SystemTime::now() - Duration::from_secs(3600))
+        .unwrap();
+}
+
+/// Overwrites `path` with `contents` of the same length and puts its
+/// modification time back, the way bit rot would look.
+fn rot(path: &Path, contents: &[u8]) {
+    let mtime = fs::metadata(path).unwrap().modified().unwrap();
+    fs::write(path, contents).unwrap();
+    File::options()
+        .append(true)
+        .open(path)
+        .unwrap()
+        .set_modified(mtime)
+        .unwrap();
+}
+
+#[test]
+fn test_xattr_names() {
+    assert_eq!(HashAlgorithm::SHA256.xattr_name(), "user.checksum.sha256");
+    assert_eq!(
+        HashAlgorithm::SHA512_256.xattr_name(),
+        "user.checksum.sha512-256"
+    );
+    assert_eq!(HashAlgorithm::BLAKE2B.xattr_name(), "user.checksum.blake2b");
+    let names: HashSet<_> = HashAlgorithm::VARIANTS
+        .iter()
+        .map(|algorithm| algorithm.xattr_name())
+        .collect();
+    assert_eq!(names.len(), HashAlgorithm::VARIANTS.len());
+}
+
+#[test]
+fn test_xattr_round_trip() {
+    let dir = tempdir().unwrap();
+    let file = dir.path().join("archive.tar");
+    write_old(&file, b"archived");
+    if !xattrs_supported(&file) {
+        return;
+    }
+    assert_eq!(read_xattr(&file, HashAlgorithm::SHA256).unwrap(), None);
+
+    let hashers = vec![
+        Hasher::new(HashAlgorithm::SHA256),
+        Hasher::new(HashAlgorithm::BLAKE3),
+    ];
+    let write = XattrOptions {
+        read: false,
+        write: true,
+    };
+    let digests = hash_file_with_xattrs(
+        &file,
+        hashers.clone(),
+        &ReadOptions::default(),
+        write,
+        |_| {},
+    )
+    .unwrap();
+    let metadata = fs::metadata(&file).unwrap();
+    for digest in &digests {
+        let stored = read_xattr(&file, digest.algorithm()).unwrap().unwrap();
+        assert_eq!(&stored.digest, digest);
+        assert!(stored.is_current(&metadata));
+    }
+
+    // Reading trusts the attributes while the mtime matches.
+    rot(&file, b"archiveD");
+    let read = XattrOptions {
+        read: true,
+        write: false,
+    };
+    let trusted = hash_file_with_xattrs(
+        &file,
+        hashers.clone(),
+        &ReadOptions::default(),
+        read,
+        |_| {},
+    )
+    .unwrap();
+    assert_eq!(trusted, digests);
+    let rehashed = hash_file(&file, HashAlgorithm::SHA256).unwrap();
+    assert_ne!(rehashed, digests[0]);
+
+    // Once the file is modified properly, they are ignored.
+    fs::write(&file, b"rewritten").unwrap();
+    File::options()
+        .append(true)
+        .open(&file)
+        .unwrap()
+        .set_modified(SystemTime::now() - Duration::from_secs(60))
+        .unwrap();
+    let stored = read_xattr(&file, HashAlgorithm::SHA256).unwrap().unwrap();
+    assert!(!stored.is_current(&fs::metadata(&file).unwrap()));
+    let fresh =
+        hash_file_with_xattrs(&file, hashers, &ReadOptions::default(), read, |_| {}).unwrap();
+    assert_eq!(fresh[0], hash_file(&file, HashAlgorithm::SHA256).unwrap());
+}
+
+#[test]
+fn test_xattr_skips_recently_modified() {
+    let dir = tempdir().unwrap();
+    let file = dir.path().join("fresh");
+    fs::write(&file, b"just written").unwrap();
+    if !xattrs_supported(&file) {
+        return;
+    }
+
+    // Another write within the same timestamp tick would go unnoticed.
+    let write = XattrOptions {
+        read: false,
+        write: true,
+    };
+    let hashers = vec![Hasher::new(HashAlgorithm::SHA256)];
+    let digests =
+        hash_file_with_xattrs(&file, hashers, &ReadOptions::default(), write, |_| {}).unwrap();
+    assert_eq!(digests[0], hash_file(&file, HashAlgorithm::SHA256).unwrap());
+    assert_eq!(read_xattr(&file, HashAlgorithm::SHA256).unwrap(), None);
+}
+
+#[test]
+fn test_xattr_only_plain_digests() {
+    assert!(storable(&Hasher::new(HashAlgorithm::SHAKE128)));
+    assert!(!storable(
+        &Hasher::with_output_len(HashAlgorithm::SHAKE128, 16).unwrap()
+    ));
+    assert!(!storable(&Hasher::blake3_keyed(&[1; 32])));
+
+    let dir = tempdir().unwrap();
+    let file = dir.path().join("data");
+    fs::write(&file, b"data").unwrap();
+    let short = StoredDigest {
+        digest: Hasher::with_output_len(HashAlgorithm::BLAKE2B, 32)
+            .unwrap()
+            .finalize(),
+        mtime: SystemTime::now(),
+    };
+    assert_eq!(
+        write_xattr(&file, &short).unwrap_err().kind(),
+        ErrorKind::InvalidInput
+    );
+}